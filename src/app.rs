@@ -1,98 +1,75 @@
 use std::collections::HashMap;
-use crate::components::file_browser::{FileAbsolutePath, FileBrowser, FileNewName};
+use crate::components::file_browser::{FileAbsolutePath, FileBrowser, FileName, FileNewName};
+use crate::components::numbering::NumberingMutation;
 use crate::components::regex::RegexMutation;
-use egui::{Grid, Label, RichText};
+use crate::components::add::AddMutation;
+use crate::components::auto_date::AutoDateMutation;
 use crate::components::case::{CaseMutation};
+use crate::components::command::CommandMutation;
+use crate::components::extension::ExtensionMutation;
+use crate::components::mapping::MappingMutation;
+use crate::components::metadata::MetadataMutation;
+use crate::components::normalize::NormalizeMutation;
+use crate::components::remove::RemoveMutation;
+use crate::components::replace::ReplaceMutation;
+use crate::components::sanitize::SanitizeMutation;
+use crate::components::template::TemplateMutation;
+use crate::components::transliterate::TransliterateMutation;
+use crate::components::truncate::TruncateMutation;
+use crate::utilities::migration::LegacyState;
 use crate::utilities::mutation_pipeline::MutationPipeline;
 
+// Bump when persisted settings need migrating, see `TemplateApp::migrate`.
+const CONFIG_VERSION: u32 = 1;
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct TemplateApp {
+    // Missing from state saved before versioning, which reads as 0
+    #[serde(default)]
+    config_version: u32,
+
     file_browser: FileBrowser,
     regex_mutation: RegexMutation,
     case_mutation: CaseMutation,
-
-    replace_match: String,
-    replace_with: String,
-    replace_case_sensitive: bool,
-    replace_first_only: bool,
-    replace_enabled: bool,
-
-    remove_first_n: String,
-    remove_last_n: String,
-    remove_from: String,
-    remove_to: String,
-    remove_chars: String,
-    remove_words: String,
-    remove_trim: bool,
-    remove_digits: bool,
-    remove_accents: bool,
-    remove_enabled: bool,
-
-    add_prefix: String,
-    add_insert: String,
-    add_at_pos: String,
-    add_suffix: String,
-    add_word_space: bool,
-    add_enabled: bool,
-
-    auto_date_type: String,
-    auto_date_format: String,
-    auto_date_enabled: bool,
-
-    numbering_mode: String,
-    numbering_at: String,
-    numbering_start: String,
-    numbering_increment: String,
-    numbering_separator: String,
-    numbering_pad: String,
-    numbering_break: String,
-    numbering_base: String,
-    numbering_base_case: String,
-    numbering_enabled: bool,
+    command_mutation: CommandMutation,
+    replace_mutation: ReplaceMutation,
+    remove_mutation: RemoveMutation,
+    add_mutation: AddMutation,
+    auto_date_mutation: AutoDateMutation,
+    metadata_mutation: MetadataMutation,
+    template_mutation: TemplateMutation,
+    transliterate_mutation: TransliterateMutation,
+    normalize_mutation: NormalizeMutation,
+    sanitize_mutation: SanitizeMutation,
+    numbering_mutation: NumberingMutation,
+    extension_mutation: ExtensionMutation,
+    mapping_mutation: MappingMutation,
+    truncate_mutation: TruncateMutation,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             file_browser: FileBrowser::new(),
             regex_mutation: RegexMutation::default(),
             case_mutation: CaseMutation::default(),
-            replace_match: "".to_string(),
-            replace_with: "".to_string(),
-            replace_case_sensitive: false,
-            replace_first_only: false,
-            replace_enabled: false,
-            remove_first_n: "".to_string(),
-            remove_last_n: "".to_string(),
-            remove_from: "".to_string(),
-            remove_to: "".to_string(),
-            remove_chars: "".to_string(),
-            remove_words: "".to_string(),
-            remove_trim: false,
-            remove_digits: false,
-            remove_accents: false,
-            remove_enabled: false,
-            add_prefix: "".to_string(),
-            add_insert: "".to_string(),
-            add_at_pos: "".to_string(),
-            add_suffix: "".to_string(),
-            add_word_space: false,
-            add_enabled: false,
-            auto_date_type: "".to_string(),
-            auto_date_format: "".to_string(),
-            auto_date_enabled: false,
-            numbering_mode: "".to_string(),
-            numbering_at: "".to_string(),
-            numbering_start: "".to_string(),
-            numbering_separator: "".to_string(),
-            numbering_increment: "".to_string(),
-            numbering_pad: "".to_string(),
-            numbering_break: "".to_string(),
-            numbering_base: "".to_string(),
-            numbering_base_case: "".to_string(),
-            numbering_enabled: false,
+            command_mutation: CommandMutation::default(),
+            replace_mutation: ReplaceMutation::default(),
+            remove_mutation: RemoveMutation::default(),
+            add_mutation: AddMutation::default(),
+            auto_date_mutation: AutoDateMutation::default(),
+            metadata_mutation: MetadataMutation::default(),
+            template_mutation: TemplateMutation::default(),
+            transliterate_mutation: TransliterateMutation::default(),
+            normalize_mutation: NormalizeMutation::default(),
+            sanitize_mutation: SanitizeMutation::default(),
+            numbering_mutation: NumberingMutation::default(),
+            extension_mutation: ExtensionMutation::default(),
+            mapping_mutation: MappingMutation::default(),
+            truncate_mutation: TruncateMutation::default(),
         }
     }
 }
@@ -109,11 +86,28 @@ impl TemplateApp {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut app: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            app.migrate(storage);
+            return app;
         }
 
         Default::default()
     }
+
+    fn migrate(&mut self, storage: &dyn eframe::Storage) {
+        if self.config_version < 1 {
+            // Loose side panel fields became typed stages
+            let legacy = eframe::get_value::<LegacyState>(storage, eframe::APP_KEY);
+            if let Some(legacy) = legacy.filter(LegacyState::has_loose_fields) {
+                self.replace_mutation = legacy.replace_mutation();
+                self.remove_mutation = legacy.remove_mutation();
+                self.add_mutation = legacy.add_mutation();
+                self.auto_date_mutation = legacy.auto_date_mutation();
+                self.numbering_mutation = legacy.numbering_mutation();
+            }
+        }
+        self.config_version = CONFIG_VERSION;
+    }
 }
 
 impl eframe::App for TemplateApp {
@@ -125,23 +119,39 @@ impl eframe::App for TemplateApp {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut pipeline = MutationPipeline::new();
+        // First, so the table is matched against the names the files have now
+        pipeline.add_mutation(Box::new(self.mapping_mutation.clone()));
         pipeline.add_mutation(Box::new(RegexMutation {
             pattern: self.regex_mutation.pattern.clone(),
             substitution: self.regex_mutation.substitution.clone(),
             enabled: self.regex_mutation.enabled,
         }));
+        pipeline.add_mutation(Box::new(self.replace_mutation.clone()));
         pipeline.add_mutation(Box::new(CaseMutation {
             enabled: self.case_mutation.enabled,
             case_type: self.case_mutation.case_type.clone(),
         }));
+        pipeline.add_mutation(Box::new(self.remove_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.add_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.auto_date_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.metadata_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.template_mutation.clone()));
+        // After the stages that bring in text from the file, so metadata gets spelled out too
+        pipeline.add_mutation(Box::new(self.transliterate_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.normalize_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.sanitize_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.numbering_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.extension_mutation.clone()));
+        // Last of the naming stages, so the limit applies to the finished name
+        pipeline.add_mutation(Box::new(self.truncate_mutation.clone()));
+        pipeline.add_mutation(Box::new(self.command_mutation.clone()));
 
-        let mut new_names:HashMap<FileAbsolutePath, FileNewName> = HashMap::new();
         if let Ok(changing_files) = self.file_browser.selected_files_rx.try_recv() {
-            // do stuff here
-            for (k, v) in changing_files {
-                let new_name = pipeline.apply_mutation(v.as_str());
-                new_names.insert(k, new_name);
-            }
+            // Files arrive in the order chosen in the file browser
+            let (paths, names): (Vec<FileAbsolutePath>, Vec<FileName>) =
+                changing_files.into_iter().unzip();
+            let new_names: HashMap<FileAbsolutePath, FileNewName> =
+                paths.iter().cloned().zip(pipeline.apply_mutations(names, &paths)).collect();
             self.file_browser.selected_files_new_name_tx.try_send(new_names).expect("Cannot send new names to file browser");
         }
 
@@ -156,171 +166,46 @@ impl eframe::App for TemplateApp {
         });
 
         egui::SidePanel::right("editor_panel").show(ctx, |ui| {
-            ui.add_space(8.0);
-            self.regex_mutation.render(ui);
-            ui.add_space(4.0);
-            self.case_mutation.render(ui);
-            ui.add_space(4.0);
-            ui.group(|ui| {
-                Grid::new("replace")
-                    .num_columns(2)
-                    .spacing([40.0, 4.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.checkbox(&mut self.replace_enabled, RichText::new("Replace").strong());
-                        ui.end_row();
-
-                        ui.add(Label::new("Replace"));
-                        ui.text_edit_singleline(&mut self.replace_match);
-                        ui.end_row();
-
-                        ui.add(Label::new("With"));
-                        ui.text_edit_singleline(&mut self.replace_with);
-                        ui.end_row();
-
-                        ui.horizontal(|ui| {
-                            ui.checkbox(&mut self.replace_case_sensitive, "Match case");
-                            ui.checkbox(&mut self.replace_first_only, "First");
-                        });
-                        ui.end_row();
-                    });
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(8.0);
+                self.mapping_mutation.render(ui);
+                ui.add_space(4.0);
+                self.regex_mutation.render(ui);
+                ui.add_space(4.0);
+                self.replace_mutation.render(ui);
+                ui.add_space(4.0);
+                self.case_mutation.render(ui);
+                ui.add_space(4.0);
+                self.remove_mutation.render(ui);
+                ui.add_space(4.0);
+                self.add_mutation.render(ui);
+                ui.add_space(4.0);
+                self.auto_date_mutation.render(ui);
+                ui.add_space(4.0);
+                self.metadata_mutation.render(ui);
+                ui.add_space(4.0);
+                self.template_mutation.render(ui, self.file_browser.hovered_file());
+                ui.add_space(4.0);
+                self.transliterate_mutation.render(ui);
+                ui.add_space(4.0);
+                self.normalize_mutation.render(ui);
+                ui.add_space(4.0);
+                self.sanitize_mutation.render(ui);
+                ui.add_space(4.0);
+                self.numbering_mutation.render(ui);
+                ui.add_space(4.0);
+                self.extension_mutation.render(ui);
+                ui.add_space(4.0);
+                self.truncate_mutation.render(ui);
+                ui.add_space(4.0);
+                self.command_mutation.render(ui);
+
+                ui.add_space(8.0);
             });
-            ui.add_space(4.0);
-            ui.group(|ui| {
-                Grid::new("remove")
-                    .num_columns(4)
-                    .spacing([40.0, 4.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.checkbox(&mut self.remove_enabled, RichText::new("Remove").strong());
-                        ui.end_row();
-
-                        ui.add(Label::new("First n"));
-                        ui.text_edit_singleline(&mut self.remove_first_n);
-                        ui.add(Label::new("Last n"));
-                        ui.text_edit_singleline(&mut self.remove_last_n);
-                        ui.end_row();
-
-                        ui.add(Label::new("From"));
-                        ui.text_edit_singleline(&mut self.remove_from);
-                        ui.add(Label::new("To"));
-                        ui.text_edit_singleline(&mut self.remove_to);
-                        ui.end_row();
-
-                        ui.add(Label::new("Chars"));
-                        ui.text_edit_singleline(&mut self.remove_chars);
-                        ui.add(Label::new("Words"));
-                        ui.text_edit_singleline(&mut self.remove_words);
-                        ui.end_row();
-
-                        ui.checkbox(&mut self.remove_digits, "Digits");
-                        ui.checkbox(&mut self.remove_accents, "Accents");
-                        ui.checkbox(&mut self.remove_trim, "Trim");
-                        ui.end_row();
-                    });
-            });
-            ui.add_space(4.0);
-            ui.group(|ui| {
-                Grid::new("add")
-                    .num_columns(2)
-                    .spacing([40.0, 4.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.checkbox(&mut self.add_enabled, RichText::new("Add").strong());
-                        ui.end_row();
-
-                        ui.add(Label::new("Prefix"));
-                        ui.text_edit_singleline(&mut self.add_prefix);
-                        ui.end_row();
-
-                        ui.add(Label::new("Insert"));
-                        ui.text_edit_singleline(&mut self.add_insert);
-                        ui.end_row();
-
-                        ui.add(Label::new("at pos"));
-                        ui.text_edit_singleline(&mut self.add_at_pos);
-                        ui.end_row();
-
-                        ui.add(Label::new("Suffix"));
-                        ui.text_edit_singleline(&mut self.add_suffix);
-                        ui.end_row();
-
-                        ui.checkbox(&mut self.add_word_space, "Word space");
-                        ui.end_row();
-                    });
-            });
-            ui.add_space(4.0);
-            ui.group(|ui| {
-                Grid::new("auto_date")
-                    .num_columns(2)
-                    .spacing([40.0, 4.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.checkbox(
-                            &mut self.auto_date_enabled,
-                            RichText::new("Auto Date").strong(),
-                        );
-                        ui.end_row();
-
-                        ui.add(Label::new("Date type"));
-                        ui.text_edit_singleline(&mut self.auto_date_type);
-                        ui.end_row();
-
-                        ui.add(Label::new("Format"));
-                        ui.text_edit_singleline(&mut self.auto_date_format);
-                        ui.end_row();
-                    });
-            });
-            ui.add_space(4.0);
-            ui.group(|ui| {
-                Grid::new("numbering")
-                    .num_columns(4)
-                    .spacing([40.0, 4.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.checkbox(
-                            &mut self.numbering_enabled,
-                            RichText::new("Numbering").strong(),
-                        );
-                        ui.end_row();
-
-                        ui.add(Label::new("Mode"));
-                        ui.text_edit_singleline(&mut self.numbering_mode);
-                        ui.add(Label::new("at"));
-                        ui.text_edit_singleline(&mut self.numbering_at);
-                        ui.end_row();
-
-                        ui.add(Label::new("Start"));
-                        ui.text_edit_singleline(&mut self.numbering_start);
-                        ui.add(Label::new("Incr."));
-                        ui.text_edit_singleline(&mut self.numbering_increment);
-                        ui.end_row();
-
-                        ui.add(Label::new("Pad"));
-                        ui.text_edit_singleline(&mut self.numbering_pad);
-                        ui.add(Label::new("Separator"));
-                        ui.text_edit_singleline(&mut self.numbering_separator);
-                        ui.end_row();
-
-                        ui.add(Label::new("Break"));
-                        ui.text_edit_singleline(&mut self.numbering_break);
-                        ui.end_row();
-
-                        ui.add(Label::new(RichText::new("Base").strong()));
-                        ui.end_row();
-
-                        ui.add(Label::new("Base"));
-                        ui.text_edit_singleline(&mut self.numbering_base);
-                        ui.add(Label::new("Case"));
-                        ui.text_edit_singleline(&mut self.numbering_base_case);
-                        ui.end_row();
-                    });
-            });
-
-            ui.add_space(8.0);
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
+            self.file_browser.set_name_limit(self.truncate_mutation.preview_limit());
             self.file_browser.render(ui);
             ctx.request_repaint();
         });
@@ -0,0 +1,107 @@
+use crate::utilities::file_name::{insert_at, split_extension};
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{DragValue, Grid, Label, RichText, Ui};
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AddMutation {
+    pub enabled: bool,
+    pub prefix: String,
+    pub insert: String,
+    // Grapheme position, negative values count from the end
+    pub at_pos: i32,
+    pub suffix: String,
+    pub word_space: bool,
+    // Apply to the stem and leave the extension untouched
+    pub keep_extension: bool,
+}
+
+impl Default for AddMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prefix: "".to_string(),
+            insert: "".to_string(),
+            at_pos: 0,
+            suffix: "".to_string(),
+            word_space: false,
+            keep_extension: true,
+        }
+    }
+}
+
+impl AddMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("add")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Add").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Prefix"));
+                    ui.text_edit_singleline(&mut self.prefix);
+                    ui.end_row();
+
+                    ui.add(Label::new("Insert"));
+                    ui.text_edit_singleline(&mut self.insert);
+                    ui.end_row();
+
+                    ui.add(Label::new("at pos"));
+                    ui.add(DragValue::new(&mut self.at_pos))
+                        .on_hover_text("Negative positions count from the end");
+                    ui.end_row();
+
+                    ui.add(Label::new("Suffix"));
+                    ui.text_edit_singleline(&mut self.suffix);
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.word_space, "Word space");
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+                });
+        });
+    }
+}
+
+// Inserts a space before every capital letter that does not already follow a space,
+// e.g. "MyHolidayPhotos" becomes "My Holiday Photos".
+fn word_space(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut previous: Option<char> = None;
+    for c in input.chars() {
+        if c.is_uppercase() && previous.is_some_and(|previous| !previous.is_whitespace()) {
+            output.push(' ');
+        }
+        output.push(c);
+        previous = Some(c);
+    }
+    output
+}
+
+impl Mutation for AddMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+
+        let mut output = if self.word_space {
+            word_space(stem)
+        } else {
+            stem.to_string()
+        };
+        if !self.insert.is_empty() {
+            output = insert_at(&output, &self.insert, self.at_pos);
+        }
+
+        format!("{}{}{}{}", self.prefix, output, self.suffix, extension)
+    }
+}
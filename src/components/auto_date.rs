@@ -0,0 +1,275 @@
+use crate::utilities::file_cache::FileCache;
+use crate::utilities::file_name::{insert_at, split_extension};
+use crate::utilities::mutation_pipeline::Mutation;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+use egui::{Color32, ComboBox, DragValue, Grid, Label, RichText, Ui};
+use std::fmt;
+use std::fmt::Write;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AutoDateMutation {
+    pub enabled: bool,
+    pub source: DateSource,
+    pub format: String,
+    pub placement: DatePlacement,
+    pub at_pos: i32,
+    pub separator: String,
+    pub timezone: DateTimezone,
+    pub keep_extension: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum DateSource {
+    Created,
+    #[default]
+    Modified,
+    Accessed,
+    Current,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum DatePlacement {
+    #[default]
+    Prefix,
+    Suffix,
+    Insert,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum DateTimezone {
+    #[default]
+    Local,
+    Utc,
+}
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateSource::Created => write!(f, "Created"),
+            DateSource::Modified => write!(f, "Modified"),
+            DateSource::Accessed => write!(f, "Accessed"),
+            DateSource::Current => write!(f, "Current time"),
+        }
+    }
+}
+
+impl fmt::Display for DatePlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatePlacement::Prefix => write!(f, "Prefix"),
+            DatePlacement::Suffix => write!(f, "Suffix"),
+            DatePlacement::Insert => write!(f, "Insert"),
+        }
+    }
+}
+
+impl fmt::Display for DateTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateTimezone::Local => write!(f, "Local time"),
+            DateTimezone::Utc => write!(f, "UTC"),
+        }
+    }
+}
+
+// Dates of a file as first read. The access date stays put while the previews read the file.
+#[derive(Clone)]
+struct FileTimes {
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+impl FileTimes {
+    fn new(metadata: &Metadata) -> Self {
+        Self {
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+        }
+    }
+}
+
+fn cache() -> &'static FileCache<FileTimes> {
+    static CACHE: OnceLock<FileCache<FileTimes>> = OnceLock::new();
+    CACHE.get_or_init(FileCache::new)
+}
+
+impl Default for AutoDateMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: DateSource::Modified,
+            format: "%Y-%m-%d".to_string(),
+            placement: DatePlacement::Prefix,
+            at_pos: 0,
+            separator: "_".to_string(),
+            timezone: DateTimezone::Local,
+            keep_extension: true,
+        }
+    }
+}
+
+impl AutoDateMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("auto_date")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Auto Date").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Date type"));
+                    ComboBox::from_id_salt("auto_date_source")
+                        .selected_text(self.source.to_string())
+                        .show_ui(ui, |ui| {
+                            for source in [
+                                DateSource::Created,
+                                DateSource::Modified,
+                                DateSource::Accessed,
+                                DateSource::Current,
+                            ] {
+                                let text = source.to_string();
+                                ui.selectable_value(&mut self.source, source, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.add(Label::new("Format"));
+                    ui.text_edit_singleline(&mut self.format)
+                        .on_hover_text("chrono strftime format, e.g. %Y-%m-%d_%H%M%S");
+                    ui.end_row();
+
+                    ui.add(Label::new("Example"));
+                    match self.format_date(SystemTime::now()) {
+                        Ok(example) => ui.label(example),
+                        Err(err) => ui.label(RichText::new(err).color(Color32::RED)),
+                    };
+                    ui.end_row();
+
+                    ui.add(Label::new("Time zone"));
+                    ComboBox::from_id_salt("auto_date_timezone")
+                        .selected_text(self.timezone.to_string())
+                        .show_ui(ui, |ui| {
+                            for timezone in [DateTimezone::Local, DateTimezone::Utc] {
+                                let text = timezone.to_string();
+                                ui.selectable_value(&mut self.timezone, timezone, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.add(Label::new("Place as"));
+                    ui.horizontal(|ui| {
+                        ComboBox::from_id_salt("auto_date_placement")
+                            .selected_text(self.placement.to_string())
+                            .show_ui(ui, |ui| {
+                                for placement in [
+                                    DatePlacement::Prefix,
+                                    DatePlacement::Suffix,
+                                    DatePlacement::Insert,
+                                ] {
+                                    let text = placement.to_string();
+                                    ui.selectable_value(&mut self.placement, placement, text);
+                                }
+                            });
+                        if self.placement == DatePlacement::Insert {
+                            ui.add(Label::new("at pos"));
+                            ui.add(DragValue::new(&mut self.at_pos))
+                                .on_hover_text("Negative positions count from the end");
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.add(Label::new("Separator"));
+                    ui.text_edit_singleline(&mut self.separator);
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+                });
+        });
+    }
+
+    fn timestamp(&self, path: &Path) -> Option<SystemTime> {
+        if self.source == DateSource::Current {
+            return Some(SystemTime::now());
+        }
+        // The cache holds regular files only, folders are read directly
+        let times = cache()
+            .get_or_insert_with(path, FileTimes::new)
+            .or_else(|| fs::metadata(path).ok().as_ref().map(FileTimes::new))?;
+        match self.source {
+            DateSource::Created => times.created,
+            DateSource::Modified => times.modified,
+            DateSource::Accessed => times.accessed,
+            DateSource::Current => None,
+        }
+    }
+
+    // Returns an error for an invalid format instead of letting chrono panic while formatting.
+    fn format_date(&self, time: SystemTime) -> Result<String, String> {
+        let items: Vec<Item<'_>> = StrftimeItems::new(&self.format).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+            return Err("Invalid format".to_string());
+        }
+
+        let datetime: DateTime<Utc> = time.into();
+        let mut formatted = String::new();
+        let result = match self.timezone {
+            DateTimezone::Local => write!(
+                formatted,
+                "{}",
+                datetime.with_timezone(&Local).format_with_items(items.iter())
+            ),
+            DateTimezone::Utc => write!(formatted, "{}", datetime.format_with_items(items.iter())),
+        };
+        result.map_err(|_| "Invalid format".to_string())?;
+        // Checked on the output, since %D, %x or %n bring them in too
+        if formatted.contains(['/', '\\']) || formatted.chars().any(char::is_control) {
+            return Err("The date must not contain / or \\ or control characters, e.g. from %D or %n".to_string());
+        }
+        Ok(formatted)
+    }
+}
+
+impl Mutation for AutoDateMutation {
+    fn mutate(&self, input: &str) -> String {
+        input.to_string()
+    }
+
+    fn mutate_file(&self, input: &str, path: &Path) -> String {
+        if !self.enabled || self.format.is_empty() {
+            return input.to_string();
+        }
+        let Some(date) = self.timestamp(path).and_then(|time| self.format_date(time).ok()) else {
+            return input.to_string();
+        };
+
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+        let stem = match self.placement {
+            DatePlacement::Prefix => format!("{}{}{}", date, self.separator, stem),
+            DatePlacement::Suffix => format!("{}{}{}", stem, self.separator, date),
+            DatePlacement::Insert => insert_at(
+                stem,
+                &format!("{}{}{}", self.separator, date, self.separator),
+                self.at_pos,
+            ),
+        };
+        format!("{}{}", stem, extension)
+    }
+}
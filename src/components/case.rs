@@ -78,15 +78,6 @@ impl CaseMutation {
                 });
         });
     }
-
-    fn extract_base_name(filename: &str) -> String {
-        if filename.starts_with('.') {
-            filename.to_string()
-        } else {
-            filename.split('.').next().unwrap().to_string()
-        }
-    }
-
 }
 
 impl Mutation for CaseMutation {
@@ -0,0 +1,314 @@
+use crate::components::file_browser::FileAbsolutePath;
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{Color32, ComboBox, DragValue, Grid, Label, RichText, Ui};
+use std::fmt;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CommandMutation {
+    // Never restored as enabled: a persisted command should not start running on launch.
+    #[serde(skip)]
+    pub enabled: bool,
+    pub command: String,
+    working_command: String,
+    pub separator: CommandSeparator,
+    pub timeout_secs: u64,
+
+    #[serde(skip)]
+    state: Arc<Mutex<CommandState>>,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum CommandSeparator {
+    #[default]
+    Newline,
+    Nul,
+}
+
+impl fmt::Display for CommandSeparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandSeparator::Newline => write!(f, "One per line"),
+            CommandSeparator::Nul => write!(f, "NUL separated"),
+        }
+    }
+}
+
+impl CommandSeparator {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommandSeparator::Newline => "\n",
+            CommandSeparator::Nul => "\0",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct CommandRun {
+    command: String,
+    separator: CommandSeparator,
+    timeout_secs: u64,
+    inputs: Vec<String>,
+}
+
+#[derive(Default)]
+enum CommandStatus {
+    #[default]
+    Idle,
+    Running,
+    Finished(Result<Vec<String>, String>),
+}
+
+#[derive(Default)]
+struct CommandState {
+    run: Option<CommandRun>,
+    status: CommandStatus,
+}
+
+impl Default for CommandMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: "".to_string(),
+            working_command: "".to_string(),
+            separator: CommandSeparator::Newline,
+            timeout_secs: 5,
+            state: Arc::new(Mutex::new(CommandState::default())),
+        }
+    }
+}
+
+impl CommandMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("command")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Command").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Command"));
+                    let command_edit = ui.add(
+                        egui::TextEdit::singleline(&mut self.working_command)
+                            .hint_text("sed -E 's/ +/_/g'"),
+                    );
+                    if command_edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        self.command = self.working_command.clone();
+                    }
+                    ui.end_row();
+
+                    ui.add(Label::new("Names"));
+                    ComboBox::from_id_salt("command_separator")
+                        .selected_text(self.separator.to_string())
+                        .show_ui(ui, |ui| {
+                            for separator in [CommandSeparator::Newline, CommandSeparator::Nul] {
+                                let text = separator.to_string();
+                                ui.selectable_value(&mut self.separator, separator, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.add(Label::new("Timeout"));
+                    ui.add(
+                        DragValue::new(&mut self.timeout_secs)
+                            .range(1..=120)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+                });
+
+            ui.label(
+                RichText::new(format!(
+                    "{} Runs through the system shell with your permissions. Only use commands you trust.",
+                    egui_phosphor::regular::WARNING
+                ))
+                .color(ui.visuals().warn_fg_color),
+            );
+            if self.working_command != self.command {
+                ui.label(RichText::new("Press Enter to apply the command").weak());
+            }
+
+            if self.enabled {
+                match &self.state.lock().unwrap().status {
+                    CommandStatus::Idle => {}
+                    CommandStatus::Running => {
+                        ui.label("Running…");
+                    }
+                    CommandStatus::Finished(Ok(_)) => {}
+                    CommandStatus::Finished(Err(err)) => {
+                        ui.label(RichText::new(err).color(Color32::RED));
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Mutation for CommandMutation {
+    fn mutate(&self, input: &str) -> String {
+        self.mutate_all(vec![input.to_string()], &[]).remove(0)
+    }
+
+    fn mutate_all(&self, inputs: Vec<String>, _paths: &[FileAbsolutePath]) -> Vec<String> {
+        if !self.enabled || self.command.trim().is_empty() || inputs.is_empty() {
+            return inputs;
+        }
+
+        let run = CommandRun {
+            command: self.command.clone(),
+            separator: self.separator,
+            timeout_secs: self.timeout_secs,
+            inputs,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.run.as_ref() != Some(&run) {
+            // Names are passed through unchanged until the command has finished.
+            state.run = Some(run.clone());
+            state.status = CommandStatus::Running;
+
+            let state_handle = self.state.clone();
+            let inputs = run.inputs.clone();
+            thread::spawn(move || {
+                let result = run_command(&run);
+                let mut state = state_handle.lock().unwrap();
+                if state.run.as_ref() == Some(&run) {
+                    state.status = CommandStatus::Finished(result);
+                }
+            });
+            return inputs;
+        }
+
+        let inputs = state.run.as_ref().unwrap().inputs.clone();
+        match &state.status {
+            CommandStatus::Finished(Ok(names)) => names.clone(),
+            _ => inputs,
+        }
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+fn run_command(run: &CommandRun) -> Result<Vec<String>, String> {
+    // A line break would split one name in two and shift every following name onto the wrong file
+    if run.separator == CommandSeparator::Newline {
+        if let Some(name) = run.inputs.iter().find(|name| name.contains(['\n', '\r'])) {
+            return Err(format!(
+                "{:?} contains a line break, use NUL separated names",
+                name
+            ));
+        }
+    }
+
+    let mut child = shell_command(&run.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Cannot start command: {}", err))?;
+
+    // Feed and drain the pipes on their own threads so a large batch cannot deadlock.
+    let separator = run.separator.as_str();
+    let input: String = run
+        .inputs
+        .iter()
+        .flat_map(|name| [name.as_str(), separator])
+        .collect();
+    let mut stdin = child.stdin.take().unwrap();
+    thread::spawn(move || {
+        // The command may exit without reading everything, e.g. `head`.
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let stdout_reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_reader = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    });
+
+    let timeout = Duration::from_secs(run.timeout_secs);
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Command timed out after {}s", run.timeout_secs));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(err) => return Err(format!("Error waiting for command: {}", err)),
+        }
+    };
+
+    let output = stdout_reader
+        .join()
+        .map_err(|_| "Cannot read command output".to_string())?
+        .map_err(|err| format!("Cannot read command output: {}", err))?;
+    if !status.success() {
+        let stderr = stderr_reader.join().unwrap_or_default();
+        return Err(format!("Command failed ({}): {}", status, stderr.trim()));
+    }
+
+    let output =
+        String::from_utf8(output).map_err(|_| "Command output is not valid UTF-8".to_string())?;
+    let names: Vec<String> = match run.separator {
+        CommandSeparator::Newline => output
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect(),
+        CommandSeparator::Nul => {
+            let mut names: Vec<String> = output.split('\0').map(|name| name.to_string()).collect();
+            if names.last().is_some_and(|name| name.is_empty()) {
+                names.pop();
+            }
+            names
+        }
+    };
+
+    if run.separator == CommandSeparator::Newline && names.len() > run.inputs.len() {
+        return Err(format!(
+            "Command returned {} lines for {} files, a name with a line break would land on the wrong file, use NUL separated names",
+            names.len(),
+            run.inputs.len()
+        ));
+    }
+    if names.len() != run.inputs.len() {
+        return Err(format!(
+            "Command returned {} names for {} files",
+            names.len(),
+            run.inputs.len()
+        ));
+    }
+    if let Some(index) = names.iter().position(|name| name.contains(['\n', '\r'])) {
+        return Err(format!("Command returned a name with a line break for {}", run.inputs[index]));
+    }
+    if let Some(index) = names.iter().position(|name| name.is_empty()) {
+        return Err(format!("Command returned an empty name for {}", run.inputs[index]));
+    }
+
+    Ok(names)
+}
@@ -0,0 +1,224 @@
+use crate::utilities::content_type::sniff;
+use crate::utilities::file_name::split_extension;
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{ComboBox, Grid, Label, RichText, Ui};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ExtensionMutation {
+    pub enabled: bool,
+    pub mode: ExtensionMode,
+    // New extension for ExtensionMode::Fixed and ExtensionMode::Append, without the dot
+    pub extension: String,
+    pub append_only_when_missing: bool,
+    pub mappings: Vec<ExtensionMapping>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ExtensionMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum ExtensionMode {
+    #[default]
+    LowerCase,
+    UpperCase,
+    Fixed,
+    Map,
+    Remove,
+    Append,
+    MatchContent,
+}
+
+impl fmt::Display for ExtensionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtensionMode::LowerCase => write!(f, "lower case"),
+            ExtensionMode::UpperCase => write!(f, "UPPER CASE"),
+            ExtensionMode::Fixed => write!(f, "Fixed"),
+            ExtensionMode::Map => write!(f, "Mapping table"),
+            ExtensionMode::Remove => write!(f, "Remove"),
+            ExtensionMode::Append => write!(f, "Append"),
+            ExtensionMode::MatchContent => write!(f, "Match content"),
+        }
+    }
+}
+
+impl Default for ExtensionMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: ExtensionMode::LowerCase,
+            extension: "".to_string(),
+            append_only_when_missing: true,
+            mappings: vec![
+                ExtensionMapping {
+                    from: "jpeg".to_string(),
+                    to: "jpg".to_string(),
+                },
+                ExtensionMapping {
+                    from: "tif".to_string(),
+                    to: "tiff".to_string(),
+                },
+            ],
+        }
+    }
+}
+
+impl ExtensionMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            let mut removed_mapping = None;
+            Grid::new("extension")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Extension").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Mode"));
+                    ComboBox::from_id_salt("extension_mode")
+                        .selected_text(self.mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in [
+                                ExtensionMode::LowerCase,
+                                ExtensionMode::UpperCase,
+                                ExtensionMode::Fixed,
+                                ExtensionMode::Map,
+                                ExtensionMode::Remove,
+                                ExtensionMode::Append,
+                                ExtensionMode::MatchContent,
+                            ] {
+                                let text = mode.to_string();
+                                ui.selectable_value(&mut self.mode, mode, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    match self.mode {
+                        ExtensionMode::Fixed | ExtensionMode::Append => {
+                            ui.add(Label::new("Extension"));
+                            ui.add(egui::TextEdit::singleline(&mut self.extension).hint_text("jpg"));
+                            ui.end_row();
+                            if self.mode == ExtensionMode::Append {
+                                ui.checkbox(
+                                    &mut self.append_only_when_missing,
+                                    "Only files without extension",
+                                );
+                                ui.end_row();
+                            }
+                        }
+                        ExtensionMode::Map => {
+                            let can_remove = self.mappings.len() > 1;
+                            for (index, mapping) in self.mappings.iter_mut().enumerate() {
+                                ui.add(egui::TextEdit::singleline(&mut mapping.from).hint_text("jpeg"));
+                                ui.horizontal(|ui| {
+                                    ui.label(egui_phosphor::regular::ARROW_RIGHT);
+                                    ui.add(egui::TextEdit::singleline(&mut mapping.to).hint_text("jpg"));
+                                    if can_remove
+                                        && ui
+                                            .button(egui_phosphor::regular::TRASH.to_string())
+                                            .on_hover_text("Remove this mapping")
+                                            .clicked()
+                                    {
+                                        removed_mapping = Some(index);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                            if ui
+                                .button(format!("{} Add mapping", egui_phosphor::regular::PLUS))
+                                .clicked()
+                            {
+                                self.mappings.push(ExtensionMapping::default());
+                            }
+                            ui.end_row();
+                        }
+                        ExtensionMode::MatchContent => {
+                            ui.label(
+                                RichText::new("Only files whose content does not match")
+                                    .color(ui.visuals().weak_text_color()),
+                            );
+                            ui.end_row();
+                        }
+                        _ => {}
+                    }
+                });
+
+            if let Some(index) = removed_mapping {
+                self.mappings.remove(index);
+            }
+        });
+    }
+
+    fn new_extension(&self, extension: &str) -> Option<String> {
+        let configured = self.extension.trim().trim_start_matches('.');
+        match self.mode {
+            ExtensionMode::LowerCase => Some(extension.to_lowercase()),
+            ExtensionMode::UpperCase => Some(extension.to_uppercase()),
+            ExtensionMode::Fixed => Some(configured.to_string()),
+            ExtensionMode::Map => Some(
+                self.mappings
+                    .iter()
+                    .find(|mapping| {
+                        mapping
+                            .from
+                            .trim()
+                            .trim_start_matches('.')
+                            .eq_ignore_ascii_case(extension)
+                    })
+                    .map(|mapping| mapping.to.trim().trim_start_matches('.').to_string())
+                    .unwrap_or_else(|| extension.to_string()),
+            ),
+            ExtensionMode::Remove => None,
+            ExtensionMode::Append | ExtensionMode::MatchContent => Some(extension.to_string()),
+        }
+        .filter(|extension| !extension.is_empty())
+    }
+}
+
+impl Mutation for ExtensionMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+
+        let (stem, extension) = split_extension(input);
+        let extension = extension.trim_start_matches('.');
+
+        if self.mode == ExtensionMode::Append {
+            let appended = self.extension.trim().trim_start_matches('.');
+            if appended.is_empty() || (self.append_only_when_missing && !extension.is_empty()) {
+                return input.to_string();
+            }
+            return format!("{}.{}", input, appended);
+        }
+
+        match self.new_extension(extension) {
+            Some(new_extension) => format!("{}.{}", stem, new_extension),
+            None => stem.to_string(),
+        }
+    }
+
+    fn mutate_file(&self, input: &str, path: &Path) -> String {
+        if !self.enabled || self.mode != ExtensionMode::MatchContent {
+            return self.mutate(input);
+        }
+
+        // Extensions that already describe the content, like .jpeg for a JPEG, are kept
+        let (stem, extension) = split_extension(input);
+        match sniff(path) {
+            Some(detected) if !detected.matches_extension(extension) => {
+                format!("{}.{}", stem, detected.canonical_extension())
+            }
+            _ => input.to_string(),
+        }
+    }
+}
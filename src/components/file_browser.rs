@@ -1,6 +1,12 @@
+use crate::components::pipeline_report::PipelineReport;
+use crate::utilities::content_type::sniff_in_background;
+use crate::utilities::file_name::{name_length, LengthUnit};
+use crate::utilities::metadata::file_tokens;
+use crate::utilities::name_list::{self, ListEntry};
+use crate::utilities::tokens::TokenValue;
 use chrono::{DateTime, Utc};
 use crossbeam::channel::{Receiver, Sender};
-use egui::{Align, Layout, Response, RichText, SelectableLabel, Ui};
+use egui::{Align, ComboBox, Layout, Response, RichText, SelectableLabel, Ui};
 use egui_extras::Column;
 use egui_selectable_table::{
     ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
@@ -8,12 +14,13 @@ use egui_selectable_table::{
 use mime_db::lookup;
 use resolve_path::PathResolveExt;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::time::SystemTime;
 use strum::IntoEnumIterator;
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
 use strum_macros::EnumIter;
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -23,6 +30,8 @@ pub struct FileBrowser {
     path_changed: bool,
     working_path: String,
 
+    // Not kept between runs, the table starts out empty
+    #[serde(skip)]
     is_first_load: bool,
     #[serde(skip)]
     file_browser_table: SelectableTable<FileBrowserRow, FileBrowserColumns, FileBrowserConfig>,
@@ -30,28 +39,69 @@ pub struct FileBrowser {
     file_browser_row_path_tx: Sender<String>,
     #[serde(skip)]
     file_browser_path_rx: Receiver<String>,
+    #[serde(skip)]
+    hovered_file_tx: Sender<FileAbsolutePath>,
+    #[serde(skip)]
+    hovered_file_rx: Receiver<FileAbsolutePath>,
+    // (dragged, dropped on) from the order handles in the table
+    #[serde(skip)]
+    order_drop_tx: Sender<(FileAbsolutePath, FileAbsolutePath)>,
+    #[serde(skip)]
+    order_drop_rx: Receiver<(FileAbsolutePath, FileAbsolutePath)>,
+    // Last file under the pointer, kept while the pointer moves on to the side panel
+    #[serde(skip)]
+    hovered_file: Option<FileAbsolutePath>,
+    // New names longer than this are flagged, set by the truncate stage
+    #[serde(skip)]
+    name_limit: Option<(LengthUnit, usize)>,
 
-    // selected files has structure { "absolute_path": { "name", "new_name" } }
-    selected_files: HashMap<FileAbsolutePath, FileName>,
+    // selected files in the chosen order, as (absolute_path, name)
+    #[serde(skip)]
+    selected_files: Vec<(FileAbsolutePath, FileName)>,
+    file_order: FileOrder,
+    show_document_title: bool,
+    // Rows whose kind or title is still being read
+    #[serde(skip)]
+    details_pending: bool,
+    // Columns the table was built with, it is rebuilt when they no longer match
+    #[serde(skip)]
+    table_columns: Vec<FileBrowserColumns>,
+    #[serde(skip)]
+    selection_order: Vec<FileAbsolutePath>,
+    #[serde(skip)]
+    manual_order: Vec<FileAbsolutePath>,
+    #[serde(skip)]
+    selected_files_position: HashMap<FileAbsolutePath, usize>,
 
     #[serde(skip)]
-    selected_files_tx: Sender<HashMap<FileAbsolutePath, FileName>>,
+    selected_files_tx: Sender<Vec<(FileAbsolutePath, FileName)>>,
     #[serde(skip)]
-    pub selected_files_rx: Receiver<HashMap<FileAbsolutePath, FileName>>,
+    pub selected_files_rx: Receiver<Vec<(FileAbsolutePath, FileName)>>,
 
     selected_files_new_name: HashMap<FileAbsolutePath, FileNewName>,
     #[serde(skip)]
     pub selected_files_new_name_tx: Sender<HashMap<FileAbsolutePath, FileNewName>>,
     #[serde(skip)]
     selected_files_new_name_rx: Receiver<HashMap<FileAbsolutePath, FileNewName>>,
+    // New names read back from an edited list, they win over the pipeline
+    #[serde(skip)]
+    imported_names: HashMap<FileAbsolutePath, FileNewName>,
+    // Outcome of the last export or import
+    #[serde(skip)]
+    list_status: Option<Result<String, String>>,
+    // Imported names that were left out or are too long
+    #[serde(skip)]
+    list_report: PipelineReport,
 }
 
 impl Default for FileBrowser {
     fn default() -> Self {
         let home_path = "~".resolve().to_str().unwrap().to_string();
         let (tx, rx) = crossbeam::channel::unbounded::<String>();
-        let (tx2, rx2) = crossbeam::channel::unbounded::<HashMap<FileAbsolutePath, FileName>>();
+        let (tx2, rx2) = crossbeam::channel::unbounded::<Vec<(FileAbsolutePath, FileName)>>();
         let (tx3, rx3) = crossbeam::channel::unbounded::<HashMap<FileAbsolutePath, FileNewName>>();
+        let (tx4, rx4) = crossbeam::channel::unbounded::<FileAbsolutePath>();
+        let (tx5, rx5) = crossbeam::channel::unbounded::<(FileAbsolutePath, FileAbsolutePath)>();
 
         Self {
             is_first_load: true,
@@ -62,14 +112,30 @@ impl Default for FileBrowser {
             file_browser_table: SelectableTable::new(FileBrowserColumns::iter().collect()),
             file_browser_row_path_tx: tx.clone(),
             file_browser_path_rx: rx.clone(),
+            hovered_file_tx: tx4.clone(),
+            hovered_file_rx: rx4.clone(),
+            order_drop_tx: tx5.clone(),
+            order_drop_rx: rx5.clone(),
+            hovered_file: None,
+            name_limit: None,
 
-            selected_files: HashMap::new(),
+            selected_files: Vec::new(),
+            file_order: FileOrder::Display,
+            show_document_title: false,
+            details_pending: false,
+            table_columns: Vec::new(),
+            selection_order: Vec::new(),
+            manual_order: Vec::new(),
+            selected_files_position: HashMap::new(),
             selected_files_tx: tx2.clone(),
             selected_files_rx: rx2.clone(),
 
             selected_files_new_name: HashMap::new(),
             selected_files_new_name_tx: tx3.clone(),
             selected_files_new_name_rx: rx3.clone(),
+            imported_names: HashMap::new(),
+            list_status: None,
+            list_report: PipelineReport::default(),
         }
     }
 }
@@ -106,6 +172,20 @@ impl FileBrowser {
         self.directory_path.clone()
     }
 
+    pub fn hovered_file(&self) -> Option<&FileAbsolutePath> {
+        self.hovered_file.as_ref()
+    }
+
+    pub fn set_name_limit(&mut self, name_limit: Option<(LengthUnit, usize)>) {
+        self.name_limit = name_limit;
+    }
+
+    fn columns(&self) -> Vec<FileBrowserColumns> {
+        FileBrowserColumns::iter()
+            .filter(|column| self.show_document_title || *column != FileBrowserColumns::Title)
+            .collect()
+    }
+
     pub fn render(&mut self, ui: &mut Ui) {
 
         if let Ok(selected_new_path) = self.file_browser_path_rx.try_recv() {
@@ -118,9 +198,22 @@ impl FileBrowser {
             self.selected_files_new_name = new_filenames;
         }
 
+        while let Ok(hovered_file) = self.hovered_file_rx.try_recv() {
+            self.hovered_file = Some(hovered_file);
+        }
+
+        while let Ok((dragged, dropped_on)) = self.order_drop_rx.try_recv() {
+            let from = self.manual_order.iter().position(|path| *path == dragged);
+            let to = self.manual_order.iter().position(|path| *path == dropped_on);
+            if let (Some(from), Some(to)) = (from, to) {
+                let path = self.manual_order.remove(from);
+                self.manual_order.insert(to, path);
+            }
+        }
+
         ui.horizontal_top(|ui| {
             if ui
-                .button(format!("{}", egui_phosphor::regular::ARROW_SQUARE_UP))
+                .button(egui_phosphor::regular::ARROW_SQUARE_UP.to_string())
                 .clicked()
             {
                 match fs::canonicalize(format!("{}/..", &self.directory_path)) {
@@ -158,7 +251,7 @@ impl FileBrowser {
             }
 
             if ui
-                .button(format!("{}", egui_phosphor::regular::FOLDER_OPEN))
+                .button(egui_phosphor::regular::FOLDER_OPEN.to_string())
                 .clicked()
             {
                 if let Some(path) = rfd::FileDialog::new()
@@ -173,8 +266,44 @@ impl FileBrowser {
                 }
             }
         });
+        ui.horizontal(|ui| {
+            ui.label("Order");
+            ComboBox::from_id_salt("file_order")
+                .selected_text(self.file_order.to_string())
+                .show_ui(ui, |ui| {
+                    for file_order in [FileOrder::Display, FileOrder::Selection, FileOrder::Manual] {
+                        let text = file_order.to_string();
+                        ui.selectable_value(&mut self.file_order, file_order, text);
+                    }
+                })
+                .response
+                .on_hover_text("Order used by numbering and other order-sensitive stages");
+            ui.checkbox(&mut self.show_document_title, "Document title")
+                .on_hover_text("Show the title embedded in PDF and office documents");
+            ui.separator();
+            self.render_list_buttons(ui);
+        });
+        match &self.list_status {
+            Some(Ok(message)) => {
+                ui.label(RichText::new(message).weak());
+            }
+            Some(Err(err)) => {
+                ui.label(RichText::new(err).color(egui::Color32::RED));
+            }
+            None => {}
+        }
+        self.list_report.render(ui);
+        if self.file_order == FileOrder::Manual && !self.manual_order.is_empty() {
+            ui.label(RichText::new("Drag the numbers in the # column to change the order").weak());
+        }
         ui.separator();
 
+        let columns = self.columns();
+        if self.table_columns != columns {
+            self.file_browser_table = SelectableTable::new(columns.clone());
+            self.table_columns = columns;
+            self.path_changed = true;
+        }
         self.file_browser_table.set_select_full_row(true);
 
         self.file_browser_table.show_ui(ui, |builder| {
@@ -185,16 +314,24 @@ impl FileBrowser {
                 .drag_to_scroll(true)
                 .auto_shrink([false; 2]);
 
-            for fb_column in FileBrowserColumns::iter() {
+            for fb_column in &self.table_columns {
                 let mut column = Column::initial(250.0);
                 match fb_column {
                     FileBrowserColumns::PathType => {
                         column = column.at_least(25.0);
                         column = column.at_most(25.0);
                     }
+                    FileBrowserColumns::Order => {
+                        column = column.at_least(30.0);
+                        column = column.at_most(40.0);
+                    }
                     FileBrowserColumns::Size => {
                         column = column.at_most(80.0);
                     }
+                    FileBrowserColumns::Form => {
+                        column = column.at_least(40.0);
+                        column = column.at_most(70.0);
+                    }
                     _ => {}
                 }
                 table = table.column(column);
@@ -202,34 +339,50 @@ impl FileBrowser {
             table
         });
 
-        // Ensure that browser is filled properly
-        let paths = fs::read_dir(self.directory_path.as_str()).unwrap();
-        if paths.count() > 0 && self.file_browser_table.total_rows() == 0 {
-            self.is_first_load = true;
-        }
-
         if self.path_changed || self.is_first_load {
             self.file_browser_table.clear_all_rows();
-            let paths = fs::read_dir(self.directory_path.as_str()).unwrap();
+            let paths: Vec<_> = fs::read_dir(self.directory_path.as_str()).unwrap().collect();
+            let names: Vec<FileName> = paths
+                .iter()
+                .flatten()
+                .filter_map(|path| path.file_name().into_string().ok())
+                .collect();
+            let lookalikes = lookalike_names(&names);
             for path in paths {
                 if let Ok(path) = path {
                     self.file_browser_table.add_modify_row(|_| {
                         let mut new_row = FileBrowserRow {
                             name: "".to_string(),
+                            title: "".to_string(),
                             new_name: "".to_string(),
+                            new_name_warning: "".to_string(),
+                            new_name_imported: false,
                             size_ui: "--".to_string(),
                             date_modified: "".to_string(),
                             date_created: "".to_string(),
                             kind: "".to_string(),
+                            kind_mismatch: false,
+                            details_pending: false,
+                            form: "".to_string(),
+                            lookalikes: Vec::new(),
                             path_type: "*".to_string(),
+                            order: "".to_string(),
                             size: 0,
                             directory_absolute_path: self.directory_path.clone(),
+                            absolute_path: "".to_string(),
                             tx: self.file_browser_row_path_tx.clone(),
+                            hover_tx: self.hovered_file_tx.clone(),
+                            order_drop_tx: self.order_drop_tx.clone(),
+                            draggable: false,
                         };
 
                         if let Ok(name) = path.file_name().into_string() {
+                            new_row.form = normalization_form(&name);
+                            new_row.lookalikes = lookalikes.get(&name).cloned().unwrap_or_default();
+                            new_row.absolute_path = format!("{}/{}", self.directory_path, name);
                             new_row.name = name.clone();
                             new_row.new_name = name.clone();
+                            new_row.new_name_warning = length_warning(&name, self.name_limit);
                         }
                         if let Ok(metadata) = path.metadata() {
                             if let Ok(date_created) = metadata.created() {
@@ -242,14 +395,17 @@ impl FileBrowser {
                             new_row.size = metadata.len();
 
                             if metadata.is_dir() {
-                                new_row.path_type = format!("{}", egui_phosphor::regular::FOLDER);
+                                new_row.path_type = egui_phosphor::regular::FOLDER.to_string();
                                 new_row.kind = "Folder".to_string();
                             } else if metadata.is_file() {
-                                new_row.path_type = format!("{}", egui_phosphor::regular::FILE);
-                                new_row.kind = format_file_type(&path.path());
+                                new_row.path_type = egui_phosphor::regular::FILE.to_string();
+                                // Filled in once the background pool has read the file
+                                new_row.kind = "…".to_string();
+                                new_row.details_pending = true;
+                                self.details_pending = true;
                             } else if metadata.is_symlink() {
                                 new_row.path_type =
-                                    format!("{}", egui_phosphor::regular::LINK_SIMPLE_HORIZONTAL);
+                                    egui_phosphor::regular::LINK_SIMPLE_HORIZONTAL.to_string();
                                 new_row.kind = "symlink".to_string();
                             }
                         }
@@ -266,64 +422,302 @@ impl FileBrowser {
             self.path_changed = false;
             self.is_first_load = false;
         } else {
-            // Reset all rows new names
-            self.file_browser_table.modify_shown_row(|formatted_rows, _indexed_ids| {
-                for row in formatted_rows {
-                    let row_data = &row.row_data;
-                    let existing_name = row_data.name.clone();
-                    row.row_data.new_name = existing_name;
+            // Files are already loaded, modify rows only. Sorting reads the rows the table keeps
+            // and the screen shows copies of them, so both get the new names and positions.
+            let selected_files_new_name = &self.selected_files_new_name;
+            let imported_names = &self.imported_names;
+            let selected_files_position = &self.selected_files_position;
+            let name_limit = self.name_limit;
+            let manual_order = self.file_order == FileOrder::Manual;
+            let new_name = |row_data: &FileBrowserRow| {
+                imported_names
+                    .get(&row_data.absolute_path)
+                    .or_else(|| selected_files_new_name.get(&row_data.absolute_path))
+                    .unwrap_or(&row_data.name)
+                    .clone()
+            };
+
+            let mut names: Vec<(FileName, FileName)> = vec![];
+            self.file_browser_table.add_modify_row(|rows| {
+                names = rows
+                    .values()
+                    .map(|row| (row.row_data.name.clone(), new_name(&row.row_data)))
+                    .collect();
+                None
+            });
+            // A new name can look like a name the folder keeps or another new name
+            let lookalikes = if names.iter().any(|(name, new_name)| name != new_name) {
+                let new_names: Vec<FileName> = names.into_iter().map(|(_, new_name)| new_name).collect();
+                lookalike_names(&new_names)
+            } else {
+                HashMap::new()
+            };
+
+            let update = |row_data: &mut FileBrowserRow| {
+                row_data.new_name = new_name(row_data);
+                row_data.new_name_imported = imported_names.contains_key(&row_data.absolute_path);
+                row_data.new_name_warning = length_warning(&row_data.new_name, name_limit);
+                if row_data.new_name != row_data.name {
+                    for lookalike in lookalikes.get(&row_data.new_name).into_iter().flatten() {
+                        if !row_data.new_name_warning.is_empty() {
+                            row_data.new_name_warning.push('\n');
+                        }
+                        row_data.new_name_warning += &format!("Looks the same as \"{}\"", lookalike);
+                    }
+                }
+                row_data.order = match selected_files_position.get(&row_data.absolute_path) {
+                    Some(position) => (position + 1).to_string(),
+                    None => "".to_string(),
+                };
+                row_data.draggable = manual_order && !row_data.order.is_empty();
+            };
+            self.file_browser_table.add_modify_row(|rows| {
+                for row in rows.values_mut() {
+                    update(&mut row.row_data);
                 }
+                None
             });
-            // Files are already loaded, modify rows only
             self.file_browser_table.modify_shown_row(|formatted_rows, _indexed_ids| {
-                for row in formatted_rows {
-                    let row_data = &row.row_data;
-                    let absolute_path =
-                        format!("{}/{}", row_data.directory_absolute_path, row_data.name);
-                    if let Some(new_name) = self.selected_files_new_name.get(&absolute_path) {
-                        row.row_data.new_name = new_name.clone();
-                    }
+                for row in formatted_rows.iter_mut() {
+                    update(&mut row.row_data);
                 }
             });
         }
 
-        self.selected_files.clear();
-        for row in self.file_browser_table.get_selected_rows() {
-            let row_data = row.row_data;
-            self.selected_files.insert(
-                format!("{}/{}", row_data.directory_absolute_path, row_data.name) as FileAbsolutePath,
-                row_data.name as FileName,
-            );
+        if self.details_pending {
+            let show_document_title = self.show_document_title;
+            let mut details_pending = false;
+            // The rows kept for sorting, and the ones on screen which are copies of them
+            self.file_browser_table.add_modify_row(|rows| {
+                for row in rows.values_mut().filter(|row| row.row_data.details_pending) {
+                    row.row_data.details_pending = !fill_details(&mut row.row_data, show_document_title);
+                    details_pending |= row.row_data.details_pending;
+                }
+                None
+            });
+            self.file_browser_table.modify_shown_row(|formatted_rows, _indexed_ids| {
+                for row in formatted_rows.iter_mut().filter(|row| row.row_data.details_pending) {
+                    row.row_data.details_pending = !fill_details(&mut row.row_data, show_document_title);
+                }
+            });
+            self.details_pending = details_pending;
         }
+
+        // Rows come back in display order
+        let displayed_files: Vec<(FileAbsolutePath, FileName)> = self
+            .file_browser_table
+            .get_selected_rows()
+            .into_iter()
+            .map(|row| (row.row_data.absolute_path, row.row_data.name))
+            .collect();
+        sync_order(&mut self.selection_order, &displayed_files);
+        sync_order(&mut self.manual_order, &displayed_files);
+
+        self.selected_files = match self.file_order {
+            FileOrder::Display => displayed_files,
+            FileOrder::Selection | FileOrder::Manual => {
+                let names: HashMap<FileAbsolutePath, FileName> = displayed_files.into_iter().collect();
+                let order = if self.file_order == FileOrder::Selection {
+                    &self.selection_order
+                } else {
+                    &self.manual_order
+                };
+                order
+                    .iter()
+                    .filter_map(|path| Some((path.clone(), names.get(path)?.clone())))
+                    .collect()
+            }
+        };
+        self.selected_files_position = self
+            .selected_files
+            .iter()
+            .enumerate()
+            .map(|(position, (path, _))| (path.clone(), position))
+            .collect();
         self.selected_files_tx.try_send(self.selected_files.clone()).expect("Cannot send selected files to app");
     }
+
+    fn render_list_buttons(&mut self, ui: &mut Ui) {
+        let export = ui
+            .add_enabled(!self.selected_files.is_empty(), egui::Button::new("Export list"))
+            .on_hover_text("Write the selected files with their new names to a CSV file");
+        if export.clicked() {
+            if let Some(file) = rfd::FileDialog::new()
+                .add_filter("Spreadsheet", &["csv", "tsv"])
+                .set_directory(&self.directory_path)
+                .set_file_name("rename_list.csv")
+                .save_file()
+            {
+                let entries = self.list_entries();
+                self.list_status = Some(
+                    name_list::export(&file, &entries, self.show_document_title)
+                        .map(|_| format!("Exported {} files to {}", entries.len(), file.display())),
+                );
+            }
+        }
+
+        if ui
+            .button("Import list")
+            .on_hover_text("Read new names from the new_name column of an edited list, matched by path")
+            .clicked()
+        {
+            if let Some(file) = rfd::FileDialog::new()
+                .add_filter("Spreadsheet", &["csv", "tsv", "tab", "txt"])
+                .set_directory(&self.directory_path)
+                .pick_file()
+            {
+                self.list_report = PipelineReport::default();
+                self.list_status = Some(name_list::import(&file).map(|list| {
+                    let missing = list
+                        .new_names
+                        .keys()
+                        .filter(|path| !Path::new(path).exists())
+                        .count();
+                    let mut message = format!("Imported {} new names", list.new_names.len());
+                    if missing > 0 {
+                        message.push_str(&format!(", {} of their files no longer exist", missing));
+                    }
+                    if list.skipped > 0 {
+                        message.push_str(&format!(", {} rows without a new name skipped", list.skipped));
+                    }
+                    if !list.rejected.is_empty() {
+                        message.push_str(&format!(", {} names cannot be used", list.rejected.len()));
+                    }
+                    let mut too_long: Vec<String> = list
+                        .new_names
+                        .iter()
+                        .map(|(path, new_name)| (path, length_warning(new_name, self.name_limit)))
+                        .filter(|(_, warning)| !warning.is_empty())
+                        .map(|(path, warning)| {
+                            let name = Path::new(path).file_name().unwrap_or_default().to_string_lossy();
+                            format!("{}: {}", name, warning)
+                        })
+                        .collect();
+                    too_long.sort();
+                    self.list_report.warnings = list.rejected;
+                    self.list_report.warnings.extend(too_long);
+                    self.imported_names = list.new_names;
+                    message
+                }));
+            }
+        }
+
+        if !self.imported_names.is_empty() && ui.button("Clear imported").clicked() {
+            self.imported_names.clear();
+            self.list_status = None;
+            self.list_report = PipelineReport::default();
+        }
+    }
+
+    // The selected files in the chosen order, with the new names the table shows
+    fn list_entries(&mut self) -> Vec<ListEntry> {
+        let rows: HashMap<FileAbsolutePath, FileBrowserRow> = self
+            .file_browser_table
+            .get_selected_rows()
+            .into_iter()
+            .map(|row| {
+                let row_data = row.row_data;
+                (row_data.absolute_path.clone(), row_data)
+            })
+            .collect();
+        self.selected_files
+            .iter()
+            .filter_map(|(path, name)| {
+                let row = rows.get(path)?;
+                Some(ListEntry {
+                    path: path.clone(),
+                    name: name.clone(),
+                    new_name: row.new_name.clone(),
+                    kind: row.kind.clone(),
+                    title: row.title.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+// Keeps the order in which files were selected: deselected files are dropped and newly
+// selected ones are appended in display order.
+fn sync_order(order: &mut Vec<FileAbsolutePath>, selected: &[(FileAbsolutePath, FileName)]) {
+    let selected_paths: HashSet<&FileAbsolutePath> = selected.iter().map(|(path, _)| path).collect();
+    order.retain(|path| selected_paths.contains(path));
+    let known: HashSet<FileAbsolutePath> = order.iter().cloned().collect();
+    for (path, _) in selected {
+        if !known.contains(path) {
+            order.push(path.clone());
+        }
+    }
 }
 
 pub type FileAbsolutePath = String;
 pub type FileName = String;
 pub type FileNewName = String;
 
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum FileOrder {
+    // Follow the table's current sort column
+    #[default]
+    Display,
+    // The order in which files were clicked
+    Selection,
+    // Set by dragging the order numbers in the table
+    Manual,
+}
+
+impl fmt::Display for FileOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileOrder::Display => write!(f, "Table sort"),
+            FileOrder::Selection => write!(f, "Selection order"),
+            FileOrder::Manual => write!(f, "Manual"),
+        }
+    }
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct FileBrowserConfig {}
 
 #[derive(Clone, Debug)]
 struct FileBrowserRow {
     name: String,
+    title: String,
     new_name: String,
+    // Empty unless the new name is over the file system limit
+    new_name_warning: String,
+    // The new name comes from an imported list
+    new_name_imported: bool,
     size_ui: String,
     size: u64,
     date_modified: String,
     date_created: String,
     kind: String,
+    kind_mismatch: bool,
+    // The kind and title have not been read yet
+    details_pending: bool,
+    // Empty for names in NFC
+    form: String,
+    // Other names in the folder that look the same
+    lookalikes: Vec<String>,
     path_type: String,
+    order: String,
     tx: Sender<String>,
+    hover_tx: Sender<FileAbsolutePath>,
+    order_drop_tx: Sender<(FileAbsolutePath, FileAbsolutePath)>,
+    // Shows a drag handle in the order column, for the manual order
+    draggable: bool,
     directory_absolute_path: String,
+    // The key of the file in the selection, the pipeline and the lists
+    absolute_path: FileAbsolutePath,
 }
 #[derive(Eq, PartialEq, Debug, Ord, PartialOrd, Clone, Copy, Hash, Default, EnumIter)]
 enum FileBrowserColumns {
     #[default]
     PathType,
+    Order,
     Name,
+    Form,
+    Title,
     NewName,
     Size,
     DateModified,
@@ -331,15 +725,92 @@ enum FileBrowserColumns {
     Kind,
 }
 
-fn format_file_type(path: &Path) -> String {
-    if let Some(file_extension) = path.extension().and_then(|ext| ext.to_str()) {
-        let mime_type = lookup(file_extension);
-        match mime_type {
-            Some(mime_type) => mime_type.to_string(),
-            None => "Unknown".to_string(),
+// Kind and title of a file row, false while the file has not been read yet
+fn fill_details(row: &mut FileBrowserRow, show_document_title: bool) -> bool {
+    let path = Path::new(&row.absolute_path);
+    let Some((kind, kind_mismatch)) = format_file_type(path) else {
+        return false;
+    };
+    let title = if show_document_title {
+        document_title(path)
+    } else {
+        Some("".to_string())
+    };
+    let Some(title) = title else {
+        return false;
+    };
+    (row.kind, row.kind_mismatch, row.title) = (kind, kind_mismatch, title);
+    true
+}
+
+// Prefers the type sniffed from the content, the extension is only a fallback.
+// The flag is set when the content does not match the extension.
+fn format_file_type(path: &Path) -> Option<(String, bool)> {
+    let file_extension = path.extension().and_then(|ext| ext.to_str());
+    if let Some(detected) = sniff_in_background(path)? {
+        let mismatch = !detected.matches_extension(file_extension.unwrap_or(""));
+        if mismatch {
+            return Some((
+                format!("{} {}", egui_phosphor::regular::WARNING, detected.mime),
+                true,
+            ));
         }
+        // The extension is more specific when they agree, e.g. a .docx is also a ZIP archive
+        let mime = file_extension.and_then(lookup).unwrap_or(detected.mime);
+        return Some((mime.to_string(), false));
+    }
+    match file_extension.and_then(lookup) {
+        Some(mime_type) => Some((mime_type.to_string(), false)),
+        None => Some(("Unknown".to_string(), false)),
+    }
+}
+
+fn length_warning(name: &str, limit: Option<(LengthUnit, usize)>) -> String {
+    match limit {
+        Some((unit, max_length)) if name_length(name, unit) > max_length => format!(
+            "The new name is {} {}, over the limit of {}",
+            name_length(name, unit),
+            unit,
+            max_length
+        ),
+        _ => "".to_string(),
+    }
+}
+
+// Names copied from macOS are often decomposed, they look like their NFC form but compare differently
+fn normalization_form(name: &str) -> String {
+    if is_nfc(name) {
+        "".to_string()
+    } else if is_nfd(name) {
+        "NFD".to_string()
     } else {
-        "Unknown".to_string()
+        "Mixed".to_string()
+    }
+}
+
+// Names that normalize to the same NFKC form as another of the names, with those names
+fn lookalike_names(names: &[FileName]) -> HashMap<FileName, Vec<FileName>> {
+    let mut groups: HashMap<String, Vec<FileName>> = HashMap::new();
+    for name in names {
+        let group = groups.entry(name.nfkc().collect()).or_default();
+        if !group.contains(name) {
+            group.push(name.clone());
+        }
+    }
+    let mut lookalikes = HashMap::new();
+    for names in groups.into_values().filter(|names| names.len() > 1) {
+        for name in &names {
+            let others = names.iter().filter(|other| *other != name).cloned().collect();
+            lookalikes.insert(name.clone(), others);
+        }
+    }
+    lookalikes
+}
+
+fn document_title(path: &Path) -> Option<String> {
+    match file_tokens(path)?.get("doc.title") {
+        Some(TokenValue::Text(title)) => Some(title.clone()),
+        _ => Some("".to_string()),
     }
 }
 
@@ -374,7 +845,10 @@ impl ColumnOperations<FileBrowserRow, FileBrowserColumns, FileBrowserConfig>
     ) -> Option<Response> {
         let mut text = match self {
             FileBrowserColumns::PathType => "",
+            FileBrowserColumns::Order => "#",
             FileBrowserColumns::Name => "Name",
+            FileBrowserColumns::Form => "Form",
+            FileBrowserColumns::Title => "Title",
             FileBrowserColumns::NewName => "New Name",
             FileBrowserColumns::Size => "Size",
             FileBrowserColumns::DateModified => "Date Modified",
@@ -423,6 +897,55 @@ impl ColumnOperations<FileBrowserRow, FileBrowserColumns, FileBrowserConfig>
                     SelectableLabel::new(column_selected, &row_text),
                 )
             }
+            FileBrowserColumns::Order if row_data.draggable => {
+                let path = row_data.absolute_path.clone();
+                let handle = ui
+                    .dnd_drag_source(egui::Id::new(("manual_order", &path)), path.clone(), |ui| {
+                        ui.add(SelectableLabel::new(
+                            column_selected,
+                            format!("{} {}", egui_phosphor::regular::DOTS_SIX_VERTICAL, row_text),
+                        ))
+                    })
+                    .response
+                    .on_hover_text("Drag to change the order");
+                if let Some(dragged) = handle.dnd_release_payload::<FileAbsolutePath>() {
+                    let _ = row_data.order_drop_tx.send(((*dragged).clone(), path));
+                }
+                // The table turns the returned response into a selecting drag, so it gets an empty one
+                ui.allocate_response(egui::Vec2::ZERO, egui::Sense::hover())
+            }
+            FileBrowserColumns::Kind if row_data.kind_mismatch => ui
+                .add(SelectableLabel::new(
+                    column_selected,
+                    RichText::new(row_text).color(ui.visuals().warn_fg_color),
+                ))
+                .on_hover_text("The content does not match the file extension"),
+            FileBrowserColumns::NewName if !row_data.new_name_warning.is_empty() => ui
+                .add(SelectableLabel::new(
+                    column_selected,
+                    RichText::new(row_text).color(ui.visuals().warn_fg_color),
+                ))
+                .on_hover_text(&row_data.new_name_warning),
+            FileBrowserColumns::NewName if row_data.new_name_imported => ui
+                .add(SelectableLabel::new(
+                    column_selected,
+                    RichText::new(row_text).color(ui.visuals().hyperlink_color),
+                ))
+                .on_hover_text("From the imported list"),
+            FileBrowserColumns::Form if !row_data.form.is_empty() || !row_data.lookalikes.is_empty() => {
+                let mut hover_text = Vec::new();
+                if !row_data.form.is_empty() {
+                    hover_text.push(format!("The name is {}, not NFC", row_data.form));
+                }
+                for lookalike in &row_data.lookalikes {
+                    hover_text.push(format!("Looks the same as \"{}\"", lookalike));
+                }
+                ui.add(SelectableLabel::new(
+                    column_selected,
+                    RichText::new(row_text).color(ui.visuals().warn_fg_color),
+                ))
+                .on_hover_text(hover_text.join("\n"))
+            }
             // left aligned content
             _ => ui.add(SelectableLabel::new(column_selected, row_text)),
         };
@@ -432,12 +955,25 @@ impl ColumnOperations<FileBrowserRow, FileBrowserColumns, FileBrowserConfig>
                 ui.close_menu();
             }
         });
-        if response.double_clicked() {
-            if row_data.kind == "Folder" {
-                let new_path = FileBrowser::navigate_to(&row_data.name, &row_data.directory_absolute_path);
-                if new_path.get_path().len() > 0 {
-                    let _ = row_data.tx.send(new_path.get_path());
-                }
+        if row_data.draggable {
+            let path = row_data.absolute_path.clone();
+            if response.dnd_hover_payload::<FileAbsolutePath>().is_some() {
+                let rect = ui.max_rect();
+                ui.painter().hline(rect.x_range(), rect.top(), ui.visuals().selection.stroke);
+            }
+            if let Some(dragged) = response.dnd_release_payload::<FileAbsolutePath>() {
+                let _ = row_data.order_drop_tx.send(((*dragged).clone(), path));
+            }
+        }
+        if response.hovered() {
+            let _ = row_data
+                .hover_tx
+                .send(row_data.absolute_path.clone());
+        }
+        if response.double_clicked() && row_data.kind == "Folder" {
+            let new_path = FileBrowser::navigate_to(&row_data.name, &row_data.directory_absolute_path);
+            if !new_path.get_path().is_empty() {
+                let _ = row_data.tx.send(new_path.get_path());
             }
         }
 
@@ -453,7 +989,13 @@ impl FileBrowserColumns {
     fn assign_row_column(&self, row: &FileBrowserRow) -> String {
         match self {
             FileBrowserColumns::PathType => row.path_type.to_string(),
+            FileBrowserColumns::Order => row.order.to_string(),
             FileBrowserColumns::Name => row.name.to_string(),
+            FileBrowserColumns::Form if row.lookalikes.is_empty() => row.form.to_string(),
+            FileBrowserColumns::Form => format!("{} {}", egui_phosphor::regular::WARNING, row.form)
+                .trim_end()
+                .to_string(),
+            FileBrowserColumns::Title => row.title.to_string(),
             FileBrowserColumns::NewName => row.new_name.to_string(),
             FileBrowserColumns::Size => row.size_ui.to_string(),
             FileBrowserColumns::DateModified => row.date_modified.to_string(),
@@ -467,7 +1009,14 @@ impl ColumnOrdering<FileBrowserRow> for FileBrowserColumns {
     fn order_by(&self, row_1: &FileBrowserRow, row_2: &FileBrowserRow) -> Ordering {
         match self {
             FileBrowserColumns::PathType => row_1.path_type.cmp(&row_2.path_type),
+            FileBrowserColumns::Order => {
+                // Unordered rows sort after ordered ones
+                let position = |order: &str| order.parse::<usize>().unwrap_or(usize::MAX);
+                position(&row_1.order).cmp(&position(&row_2.order))
+            }
             FileBrowserColumns::Name => row_1.name.cmp(&row_2.name),
+            FileBrowserColumns::Form => self.column_text(row_1).cmp(&self.column_text(row_2)),
+            FileBrowserColumns::Title => row_1.title.cmp(&row_2.title),
             FileBrowserColumns::NewName => row_1.new_name.cmp(&row_2.new_name),
             FileBrowserColumns::Size => row_1.size.cmp(&row_2.size),
             FileBrowserColumns::DateModified => row_1.date_modified.cmp(&row_2.date_modified),
@@ -476,3 +1025,52 @@ impl ColumnOrdering<FileBrowserRow> for FileBrowserColumns {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<FileName> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn normalization_forms() {
+        assert_eq!(normalization_form("photo.jpg"), "");
+        assert_eq!(normalization_form("caf\u{e9}.txt"), "");
+        assert_eq!(normalization_form("cafe\u{301}.txt"), "NFD");
+        assert_eq!(normalization_form("caf\u{e9} cafe\u{301}.txt"), "Mixed");
+    }
+
+    #[test]
+    fn lookalikes() {
+        let lookalikes = lookalike_names(&names(&[
+            "caf\u{e9}.txt",
+            "cafe\u{301}.txt",
+            "\u{fb01}le.txt",
+            "file.txt",
+            "other.txt",
+        ]));
+        assert_eq!(lookalikes["caf\u{e9}.txt"], ["cafe\u{301}.txt"]);
+        assert_eq!(lookalikes["cafe\u{301}.txt"], ["caf\u{e9}.txt"]);
+        assert_eq!(lookalikes["file.txt"], ["\u{fb01}le.txt"]);
+        assert!(!lookalikes.contains_key("other.txt"));
+        assert_eq!(lookalikes.len(), 4);
+    }
+
+    #[test]
+    fn lookalikes_of_a_larger_group() {
+        let lookalikes = lookalike_names(&names(&["ABC.txt", "\u{ff21}BC.txt", "A\u{ff22}C.txt"]));
+        assert_eq!(lookalikes["ABC.txt"], ["\u{ff21}BC.txt", "A\u{ff22}C.txt"]);
+        assert_eq!(lookalikes["A\u{ff22}C.txt"], ["ABC.txt", "\u{ff21}BC.txt"]);
+    }
+
+    #[test]
+    fn repeated_names_do_not_look_alike() {
+        // The same name twice is one name, not a lookalike
+        let lookalikes = lookalike_names(&names(&["a.txt", "a.txt", "b.txt"]));
+        assert!(lookalikes.is_empty());
+        // Case is not folded
+        assert!(lookalike_names(&names(&["a.txt", "A.txt"])).is_empty());
+    }
+}
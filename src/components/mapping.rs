@@ -0,0 +1,491 @@
+use crate::components::file_browser::FileAbsolutePath;
+use crate::components::pipeline_report::PipelineReport;
+use crate::utilities::csv::{self, Delimiter};
+use crate::utilities::file_name::{name_problem, split_extension};
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{Color32, ComboBox, Grid, Label, RichText, ScrollArea, Ui};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MappingMutation {
+    pub enabled: bool,
+    // The CSV or TSV file with the old and new names
+    pub path: String,
+    pub delimiter: Delimiter,
+    pub has_header: bool,
+    // Zero based columns of the table
+    pub key_column: usize,
+    pub value_column: usize,
+    pub key_by: MappingKey,
+    pub ignore_case: bool,
+    #[serde(skip)]
+    state: Arc<Mutex<MappingState>>,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum MappingKey {
+    #[default]
+    Name,
+    // The new name replaces the stem and the extension is kept
+    Stem,
+    // Matched against the end of the path, so "2023/IMG_0001.jpg" or "./IMG_0001.jpg" both work
+    RelativePath,
+}
+
+impl fmt::Display for MappingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingKey::Name => write!(f, "Name"),
+            MappingKey::Stem => write!(f, "Name without extension"),
+            MappingKey::RelativePath => write!(f, "Relative path"),
+        }
+    }
+}
+
+// What the table was read with, it is read again when any of it changes
+#[derive(Clone, PartialEq)]
+struct MappingSource {
+    path: String,
+    delimiter: Delimiter,
+    modified: Option<SystemTime>,
+}
+
+struct MappingState {
+    source: Option<MappingSource>,
+    table: Result<Vec<Vec<String>>, String>,
+    report: PipelineReport,
+    matches: MappingMatches,
+}
+
+impl Default for MappingState {
+    fn default() -> Self {
+        Self {
+            source: None,
+            table: Ok(Vec::new()),
+            report: PipelineReport::default(),
+            matches: MappingMatches::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MappingMatches {
+    matched: usize,
+    unmatched_files: Vec<String>,
+    // As (row, key), rows counted from 1 as spreadsheets do
+    unused_rows: Vec<(usize, String)>,
+}
+
+impl Default for MappingMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "".to_string(),
+            delimiter: Delimiter::Auto,
+            has_header: true,
+            key_column: 0,
+            value_column: 1,
+            key_by: MappingKey::Name,
+            ignore_case: false,
+            state: Arc::new(Mutex::new(MappingState::default())),
+        }
+    }
+}
+
+// Keys and names compare with forward slashes and without a leading "./"
+fn normalize_key(key: &str, key_by: MappingKey, ignore_case: bool) -> String {
+    let key = key.trim();
+    let key = match key_by {
+        MappingKey::RelativePath => {
+            let key = key.replace('\\', "/");
+            key.trim_start_matches("./").to_string()
+        }
+        _ => key.to_string(),
+    };
+    if ignore_case {
+        key.to_lowercase()
+    } else {
+        key
+    }
+}
+
+impl MappingMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        self.load();
+        ui.group(|ui| {
+            let state = self.state.lock().unwrap();
+            let headers = self.headers(&state.table);
+            Grid::new("mapping")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Mapping table").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("File"));
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.path)
+                            .on_hover_text("A CSV or TSV file with the current and the new names");
+                        if ui.button(egui_phosphor::regular::FOLDER_OPEN.to_string()).clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Spreadsheet", &["csv", "tsv", "tab", "txt"])
+                                .pick_file()
+                            {
+                                self.path = path.display().to_string();
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.add(Label::new("Delimiter"));
+                    ComboBox::from_id_salt("mapping_delimiter")
+                        .selected_text(self.delimiter.to_string())
+                        .show_ui(ui, |ui| {
+                            for delimiter in [Delimiter::Auto, Delimiter::Comma, Delimiter::Semicolon, Delimiter::Tab] {
+                                let text = delimiter.to_string();
+                                ui.selectable_value(&mut self.delimiter, delimiter, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.has_header, "First row is a header");
+                    ui.end_row();
+
+                    ui.add(Label::new("Current name"));
+                    column_combo(ui, "mapping_key_column", &mut self.key_column, &headers);
+                    ui.end_row();
+
+                    ui.add(Label::new("New name"));
+                    column_combo(ui, "mapping_value_column", &mut self.value_column, &headers);
+                    ui.end_row();
+
+                    ui.add(Label::new("Match by"));
+                    ComboBox::from_id_salt("mapping_key_by")
+                        .selected_text(self.key_by.to_string())
+                        .show_ui(ui, |ui| {
+                            for key_by in [MappingKey::Name, MappingKey::Stem, MappingKey::RelativePath] {
+                                let text = key_by.to_string();
+                                ui.selectable_value(&mut self.key_by, key_by, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.ignore_case, "Ignore case");
+                    ui.end_row();
+                });
+
+            if let Err(err) = &state.table {
+                ui.label(RichText::new(err).color(Color32::RED));
+            } else if self.enabled && !self.path.is_empty() {
+                render_matches(ui, &state.matches);
+                state.report.render(ui);
+            }
+        });
+    }
+
+    fn headers(&self, table: &Result<Vec<Vec<String>>, String>) -> Vec<String> {
+        let rows = table.as_deref().unwrap_or_default();
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0).max(2);
+        (0..columns)
+            .map(|column| match rows.first().and_then(|row| row.get(column)) {
+                Some(header) if self.has_header && !header.trim().is_empty() => header.trim().to_string(),
+                _ => format!("Column {}", column + 1),
+            })
+            .collect()
+    }
+
+    // Reads the table again when the file, its delimiter or its modification time changed
+    fn load(&self) {
+        let path = Path::new(&self.path);
+        let source = MappingSource {
+            path: self.path.clone(),
+            delimiter: self.delimiter,
+            modified: fs::metadata(path).and_then(|metadata| metadata.modified()).ok(),
+        };
+        let mut state = self.state.lock().unwrap();
+        if state.source.as_ref() == Some(&source) {
+            return;
+        }
+        state.table = if self.path.is_empty() {
+            Ok(Vec::new())
+        } else {
+            fs::read(path)
+                .map_err(|err| format!("Cannot read {}: {}", self.path, err))
+                .and_then(|bytes| {
+                    String::from_utf8(bytes).map_err(|_| "The mapping file is not valid UTF-8".to_string())
+                })
+                .and_then(|text| csv::parse(&text, self.delimiter.resolve(path, &text)))
+        };
+        state.source = Some(source);
+    }
+}
+
+fn column_combo(ui: &mut Ui, id: &str, column: &mut usize, headers: &[String]) {
+    let selected = headers
+        .get(*column)
+        .cloned()
+        .unwrap_or_else(|| format!("Column {}", *column + 1));
+    ComboBox::from_id_salt(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (index, header) in headers.iter().enumerate() {
+                ui.selectable_value(column, index, header);
+            }
+        });
+}
+
+fn render_matches(ui: &mut Ui, matches: &MappingMatches) {
+    ui.label(format!(
+        "{} matched, {} files without a row, {} rows unused",
+        matches.matched,
+        matches.unmatched_files.len(),
+        matches.unused_rows.len()
+    ));
+    if matches.unmatched_files.is_empty() && matches.unused_rows.is_empty() {
+        return;
+    }
+    ui.collapsing("Report", |ui| {
+        ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+            if !matches.unmatched_files.is_empty() {
+                ui.label(RichText::new("Files without a row").strong());
+                for name in &matches.unmatched_files {
+                    ui.label(name);
+                }
+            }
+            if !matches.unused_rows.is_empty() {
+                ui.label(RichText::new("Rows matching no selected file").strong());
+                for (row, key) in &matches.unused_rows {
+                    ui.label(format!("Row {}: {}", row, key));
+                }
+            }
+        });
+    });
+}
+
+impl Mutation for MappingMutation {
+    fn mutate(&self, input: &str) -> String {
+        self.mutate_all(vec![input.to_string()], &[]).remove(0)
+    }
+
+    fn mutate_all(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
+        if !self.enabled || self.path.is_empty() {
+            return inputs;
+        }
+        self.load();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let rows = match &state.table {
+            Ok(rows) => rows,
+            Err(_) => return inputs,
+        };
+
+        // Key to (row index, new name), the first row of a key wins
+        let skipped = if self.has_header { 1 } else { 0 };
+        let mut mapping: HashMap<String, (usize, &str)> = HashMap::new();
+        let mut report = PipelineReport::default();
+        for (index, row) in rows.iter().enumerate().skip(skipped) {
+            let (Some(key), Some(value)) = (row.get(self.key_column), row.get(self.value_column)) else {
+                continue;
+            };
+            let key = normalize_key(key, self.key_by, self.ignore_case);
+            if key.is_empty() {
+                continue;
+            }
+            match mapping.entry(key) {
+                Entry::Occupied(entry) => report.warnings.push(format!(
+                    "Row {}: {} is listed before, the first row is used",
+                    index + 1,
+                    entry.key()
+                )),
+                Entry::Vacant(entry) => {
+                    entry.insert((index, value.trim()));
+                }
+            }
+        }
+
+        let mut used = vec![false; rows.len()];
+        let mut matches = MappingMatches::default();
+        let outputs = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let (stem, extension) = split_extension(&input);
+                // The extension is only added back when the stem matched
+                let (found, stem_matched) = match self.key_by {
+                    MappingKey::Name => (mapping.get(&normalize_key(&input, self.key_by, self.ignore_case)), false),
+                    // Sheets often list the whole name even when only the stem should change
+                    MappingKey::Stem => match mapping.get(&normalize_key(stem, self.key_by, self.ignore_case)) {
+                        Some(found) => (Some(found), true),
+                        None => (mapping.get(&normalize_key(&input, self.key_by, self.ignore_case)), false),
+                    },
+                    MappingKey::RelativePath => {
+                        let path = paths.get(index).map(|path| path.as_str()).unwrap_or(&input);
+                        let path = normalize_key(path, self.key_by, self.ignore_case);
+                        // The longest key ending the path at a folder boundary, the whole path first
+                        let found = iter::once(0)
+                            .chain(path.match_indices('/').map(|(slash, _)| slash + 1))
+                            .find_map(|start| mapping.get(&path[start..]));
+                        (found, false)
+                    }
+                };
+                match found {
+                    // An empty new name keeps the file as it is
+                    Some((row, "")) => {
+                        used[*row] = true;
+                        matches.matched += 1;
+                        input
+                    }
+                    Some((row, value)) => {
+                        used[*row] = true;
+                        let renamed = if stem_matched {
+                            format!("{}{}", value, extension)
+                        } else {
+                            value.to_string()
+                        };
+                        // A cell like "../x" would move the file, the file keeps its name instead
+                        if let Some(problem) = name_problem(&renamed) {
+                            report.errors.push(format!(
+                                "Row {}: \"{}\" {}",
+                                row + 1,
+                                renamed.escape_debug(),
+                                problem
+                            ));
+                            return input;
+                        }
+                        matches.matched += 1;
+                        renamed
+                    }
+                    None => {
+                        matches.unmatched_files.push(input.clone());
+                        input
+                    }
+                }
+            })
+            .collect();
+
+        matches.unused_rows = mapping
+            .iter()
+            .filter(|(_, (row, _))| !used[*row])
+            .map(|(_, (row, _))| (*row + 1, rows[*row][self.key_column].trim().to_string()))
+            .collect();
+        matches.unused_rows.sort();
+        state.report = report;
+        state.matches = matches;
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // The table file, removed when the test is done with it
+    struct Table(PathBuf);
+
+    impl Drop for Table {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn table(name: &str, text: &str) -> (Table, MappingMutation) {
+        let path = std::env::temp_dir().join(format!("bulkrename_mapping_{}_{}.csv", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let mutation = MappingMutation {
+            enabled: true,
+            path: path.display().to_string(),
+            ..Default::default()
+        };
+        (Table(path), mutation)
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn by_name() {
+        let (_table, mutation) = table("by_name", "old,new\nIMG_1.jpg,Beach.jpg\nIMG_2.jpg,\n");
+        let outputs = mutation.mutate_all(names(&["IMG_1.jpg", "img_1.jpg", "IMG_2.jpg"]), &[]);
+        assert_eq!(outputs, names(&["Beach.jpg", "img_1.jpg", "IMG_2.jpg"]));
+        let state = mutation.state.lock().unwrap();
+        assert_eq!(state.matches.matched, 2);
+        assert_eq!(state.matches.unmatched_files, names(&["img_1.jpg"]));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let (_table, mut mutation) = table("ignore_case", "old,new\nIMG_1.JPG,Beach.jpg\n");
+        mutation.ignore_case = true;
+        assert_eq!(mutation.mutate_all(names(&["img_1.jpg"]), &[]), names(&["Beach.jpg"]));
+    }
+
+    #[test]
+    fn columns_and_header() {
+        let (_table, mut mutation) = table("columns", "IMG_1.jpg;x;Beach.jpg\n");
+        mutation.has_header = false;
+        mutation.value_column = 2;
+        assert_eq!(mutation.mutate_all(names(&["IMG_1.jpg"]), &[]), names(&["Beach.jpg"]));
+        mutation.has_header = true;
+        assert_eq!(mutation.mutate_all(names(&["IMG_1.jpg"]), &[]), names(&["IMG_1.jpg"]));
+    }
+
+    #[test]
+    fn stem() {
+        let (_table, mut mutation) = table("stem", "old,new\nIMG_1,Beach\nIMG_2.jpg,Sea\n");
+        mutation.key_by = MappingKey::Stem;
+        let outputs = mutation.mutate_all(names(&["IMG_1.jpg", "IMG_1.png", "IMG_2.jpg"]), &[]);
+        // The whole name matched for IMG_2.jpg, so the extension is not added again
+        assert_eq!(outputs, names(&["Beach.jpg", "Beach.png", "Sea"]));
+    }
+
+    #[test]
+    fn relative_path() {
+        let text = "old,new\na.txt,x.txt\n2023/a.txt,y.txt\n./b\\c.txt,z.txt\n";
+        let (_table, mut mutation) = table("relative_path", text);
+        mutation.key_by = MappingKey::RelativePath;
+        let paths = names(&["/home/2023/a.txt", "/home/2024/a.txt", "/x/b/c.txt", "/x/bb/c.txt", "/x/ba.txt"]);
+        let outputs = mutation.mutate_all(names(&["a.txt", "a.txt", "c.txt", "c.txt", "ba.txt"]), &paths);
+        // The longest key wins, keys only match at a folder boundary
+        assert_eq!(outputs, names(&["y.txt", "x.txt", "z.txt", "c.txt", "ba.txt"]));
+    }
+
+    #[test]
+    fn duplicate_and_unused_rows() {
+        let (_table, mutation) = table("duplicates", "old,new\na.txt,first.txt\na.txt,second.txt\nb.txt,c.txt\n");
+        assert_eq!(mutation.mutate_all(names(&["a.txt"]), &[]), names(&["first.txt"]));
+        let state = mutation.state.lock().unwrap();
+        assert_eq!(state.report.warnings, names(&["Row 3: a.txt is listed before, the first row is used"]));
+        assert_eq!(state.matches.unused_rows, vec![(4, "b.txt".to_string())]);
+    }
+
+    #[test]
+    fn unusable_new_names() {
+        let text = "old,new\na.txt,../a.txt\nb.txt,sub/b.txt\nc.txt,\"tab\there\"\nd.txt,CON.txt\n";
+        let (_table, mutation) = table("unusable", text);
+        let inputs = names(&["a.txt", "b.txt", "c.txt", "d.txt"]);
+        assert_eq!(mutation.mutate_all(inputs.clone(), &[]), inputs);
+        let state = mutation.state.lock().unwrap();
+        assert_eq!(state.report.errors.len(), 4);
+        assert_eq!(state.report.errors[0], "Row 2: \"../a.txt\" contains a slash");
+        assert_eq!(state.matches.matched, 0);
+    }
+
+    #[test]
+    fn unreadable_table() {
+        let (_table, mutation) = table("unclosed", "old,new\n\"a.txt,b.txt\n");
+        assert_eq!(mutation.mutate_all(names(&["a.txt"]), &[]), names(&["a.txt"]));
+        assert!(mutation.state.lock().unwrap().table.is_err());
+    }
+}
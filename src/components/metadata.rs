@@ -0,0 +1,227 @@
+use crate::components::file_browser::FileAbsolutePath;
+use crate::components::pipeline_report::PipelineReport;
+use crate::utilities::file_name::split_extension;
+use crate::utilities::hashes::{self, HashAlgorithm, HashStatus};
+use crate::utilities::metadata::{
+    file_tokens, AUDIO_TOKENS, DOCUMENT_TOKENS, EXIF_TOKENS, FILE_TOKENS, HASH_TOKENS, VIDEO_TOKENS,
+};
+use crate::utilities::mutation_pipeline::Mutation;
+use crate::utilities::tokens::{expand, parse, Segment, TokenValue};
+use egui::{Color32, ComboBox, Grid, Label, RichText, Ui};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MetadataMutation {
+    pub enabled: bool,
+    pub template: String,
+    pub placement: MetadataPlacement,
+    pub separator: String,
+    // Photos without EXIF dates still sort by when they were last written
+    pub fallback_to_modified: bool,
+    pub keep_extension: bool,
+    #[serde(skip)]
+    report: Arc<Mutex<PipelineReport>>,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum MetadataPlacement {
+    #[default]
+    Replace,
+    Prefix,
+    Suffix,
+}
+
+impl fmt::Display for MetadataPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataPlacement::Replace => write!(f, "Replace name"),
+            MetadataPlacement::Prefix => write!(f, "Prefix"),
+            MetadataPlacement::Suffix => write!(f, "Suffix"),
+        }
+    }
+}
+
+impl Default for MetadataMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "{exif.date:%Y-%m-%d_%H%M%S}_{exif.model}".to_string(),
+            placement: MetadataPlacement::Replace,
+            separator: "_".to_string(),
+            fallback_to_modified: true,
+            keep_extension: true,
+            report: Arc::new(Mutex::new(PipelineReport::default())),
+        }
+    }
+}
+
+impl MetadataMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            let segments = parse(&self.template);
+            Grid::new("metadata")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Metadata").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Template"));
+                    ui.text_edit_singleline(&mut self.template)
+                        .on_hover_text("Tokens in braces, with an optional format after a colon");
+                    ui.end_row();
+
+                    if let Err(err) = &segments {
+                        ui.label("");
+                        ui.label(RichText::new(err.to_string()).color(Color32::RED));
+                        ui.end_row();
+                    }
+
+                    ui.add(Label::new("Place as"));
+                    ComboBox::from_id_salt("metadata_placement")
+                        .selected_text(self.placement.to_string())
+                        .show_ui(ui, |ui| {
+                            for placement in [
+                                MetadataPlacement::Replace,
+                                MetadataPlacement::Prefix,
+                                MetadataPlacement::Suffix,
+                            ] {
+                                let text = placement.to_string();
+                                ui.selectable_value(&mut self.placement, placement, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    if self.placement != MetadataPlacement::Replace {
+                        ui.add(Label::new("Separator"));
+                        ui.text_edit_singleline(&mut self.separator);
+                        ui.end_row();
+                    }
+
+                    ui.checkbox(&mut self.fallback_to_modified, "Modified date without EXIF")
+                        .on_hover_text("Use the file modification date when exif.date is missing");
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+                });
+
+            ui.collapsing("Tokens", |ui| {
+                Grid::new("metadata_tokens")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (name, description) in FILE_TOKENS
+                            .iter()
+                            .chain(EXIF_TOKENS)
+                            .chain(AUDIO_TOKENS)
+                            .chain(VIDEO_TOKENS)
+                            .chain(DOCUMENT_TOKENS)
+                            .chain(HASH_TOKENS)
+                        {
+                            ui.monospace(format!("{{{}}}", name));
+                            ui.label(*description);
+                            ui.end_row();
+                        }
+                    });
+                ui.label(
+                    RichText::new("Dates take a strftime format like {exif.date:%Y%m%d}, numbers a zero padded width like {exif.image_number:04} and text or hashes a length like {sha256:12}")
+                        .weak(),
+                );
+            });
+
+            if self.enabled {
+                self.report.lock().unwrap().render(ui);
+            }
+        });
+    }
+
+    // None while the file is still being read or hashed
+    fn fill_in(&self, segments: &[Segment], path: &Path) -> Result<Option<String>, String> {
+        // Asked for before the hashes, so both are read meanwhile
+        let tokens = file_tokens(path);
+        let algorithms = hashes::used_by(segments);
+        let digests = if algorithms.is_empty() {
+            Default::default()
+        } else {
+            match hashes::digests(path, &algorithms) {
+                HashStatus::Ready(digests) => digests,
+                HashStatus::Pending => return Ok(None),
+                HashStatus::Failed(err) => return Err(err),
+            }
+        };
+
+        let Some(tokens) = tokens else {
+            return Ok(None);
+        };
+        expand(segments, |name| {
+            if let Some(algorithm) = HashAlgorithm::from_name(name) {
+                return digests.get(&algorithm).cloned().map(TokenValue::Text);
+            }
+            let value = tokens.get(name);
+            if name == "exif.date" && self.fallback_to_modified {
+                return value.or_else(|| tokens.get("file.modified")).cloned();
+            }
+            value.cloned()
+        })
+        .map(Some)
+    }
+
+    fn rename(&self, segments: &[Segment], input: &str, path: &Path) -> Result<Option<String>, String> {
+        let Some(filled_in) = self.fill_in(segments, path)? else {
+            return Ok(None);
+        };
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+        let stem = match self.placement {
+            MetadataPlacement::Replace => filled_in,
+            MetadataPlacement::Prefix => format!("{}{}{}", filled_in, self.separator, stem),
+            MetadataPlacement::Suffix => format!("{}{}{}", stem, self.separator, filled_in),
+        };
+        Ok(Some(format!("{}{}", stem, extension)))
+    }
+}
+
+impl Mutation for MetadataMutation {
+    fn mutate(&self, input: &str) -> String {
+        input.to_string()
+    }
+
+    fn mutate_all(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
+        let mut report = self.report.lock().unwrap();
+        *report = PipelineReport::default();
+        if !self.enabled || self.template.is_empty() {
+            return inputs;
+        }
+        let Ok(segments) = parse(&self.template) else {
+            return inputs;
+        };
+
+        inputs
+            .into_iter()
+            .zip(paths)
+            .map(|(input, path)| {
+                let path = Path::new(path);
+                match self.rename(&segments, &input, path) {
+                    Ok(Some(renamed)) => renamed,
+                    Ok(None) => {
+                        report.pending += 1;
+                        input
+                    }
+                    Err(err) => {
+                        report.fail(path, err);
+                        input
+                    }
+                }
+            })
+            .collect()
+    }
+}
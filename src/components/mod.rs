@@ -1,3 +1,19 @@
 pub mod file_browser;
 pub mod regex;
 pub mod case;
+pub mod command;
+pub mod replace;
+pub mod remove;
+pub mod add;
+pub mod auto_date;
+pub mod numbering;
+pub mod extension;
+
+pub mod metadata;
+pub mod template;
+pub mod transliterate;
+pub mod normalize;
+pub mod sanitize;
+pub mod truncate;
+pub mod mapping;
+pub mod pipeline_report;
\ No newline at end of file
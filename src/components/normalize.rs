@@ -0,0 +1,150 @@
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{ComboBox, Grid, Label, RichText, Ui};
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct NormalizeMutation {
+    pub enabled: bool,
+    pub form: NormalizationForm,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum NormalizationForm {
+    // Composed, what Linux and Windows tools expect
+    #[default]
+    Nfc,
+    // Decomposed, as older macOS file systems store names
+    Nfd,
+    // The compatibility forms also fold lookalikes such as "ﬁ" or full width letters
+    Nfkc,
+    Nfkd,
+}
+
+impl fmt::Display for NormalizationForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizationForm::Nfc => write!(f, "NFC (composed)"),
+            NormalizationForm::Nfd => write!(f, "NFD (decomposed)"),
+            NormalizationForm::Nfkc => write!(f, "NFKC (compatibility composed)"),
+            NormalizationForm::Nfkd => write!(f, "NFKD (compatibility decomposed)"),
+        }
+    }
+}
+
+impl Default for NormalizeMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            form: NormalizationForm::Nfc,
+        }
+    }
+}
+
+impl NormalizeMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("normalize")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Unicode normalization").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Form"));
+                    ComboBox::from_id_salt("normalization_form")
+                        .selected_text(self.form.to_string())
+                        .show_ui(ui, |ui| {
+                            for form in [
+                                NormalizationForm::Nfc,
+                                NormalizationForm::Nfd,
+                                NormalizationForm::Nfkc,
+                                NormalizationForm::Nfkd,
+                            ] {
+                                let text = form.to_string();
+                                ui.selectable_value(&mut self.form, form, text);
+                            }
+                        });
+                    ui.end_row();
+                });
+        });
+    }
+}
+
+impl Mutation for NormalizeMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+        match self.form {
+            NormalizationForm::Nfc => input.nfc().collect(),
+            NormalizationForm::Nfd => input.nfd().collect(),
+            NormalizationForm::Nfkc => input.nfkc().collect(),
+            NormalizationForm::Nfkd => input.nfkd().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSED: &str = "caf\u{e9}.txt";
+    const DECOMPOSED: &str = "cafe\u{301}.txt";
+
+    fn normalize(form: NormalizationForm) -> NormalizeMutation {
+        NormalizeMutation { enabled: true, form }
+    }
+
+    #[test]
+    fn nfc_composes() {
+        let mutation = normalize(NormalizationForm::Nfc);
+        assert_eq!(mutation.mutate(DECOMPOSED), COMPOSED);
+        assert_eq!(mutation.mutate(COMPOSED), COMPOSED);
+        // Compatibility characters are left alone
+        assert_eq!(mutation.mutate("\u{fb01}le.txt"), "\u{fb01}le.txt");
+    }
+
+    #[test]
+    fn nfd_decomposes() {
+        let mutation = normalize(NormalizationForm::Nfd);
+        assert_eq!(mutation.mutate(COMPOSED), DECOMPOSED);
+        assert_eq!(mutation.mutate(DECOMPOSED), DECOMPOSED);
+        // Hangul syllables split into their letters
+        assert_eq!(mutation.mutate("\u{d55c}"), "\u{1112}\u{1161}\u{11ab}");
+    }
+
+    #[test]
+    fn compatibility_forms_fold_lookalikes() {
+        let mutation = normalize(NormalizationForm::Nfkc);
+        assert_eq!(mutation.mutate("\u{fb01}le.txt"), "file.txt");
+        assert_eq!(mutation.mutate("\u{ff21}\u{ff22}\u{ff23}.txt"), "ABC.txt");
+        assert_eq!(mutation.mutate(DECOMPOSED), COMPOSED);
+        let mutation = normalize(NormalizationForm::Nfkd);
+        assert_eq!(mutation.mutate("\u{fb01}l\u{e9}.txt"), "file\u{301}.txt");
+    }
+
+    #[test]
+    fn ascii_is_unchanged() {
+        for form in [
+            NormalizationForm::Nfc,
+            NormalizationForm::Nfd,
+            NormalizationForm::Nfkc,
+            NormalizationForm::Nfkd,
+        ] {
+            assert_eq!(normalize(form).mutate("photo_01.jpg"), "photo_01.jpg");
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = NormalizeMutation {
+            enabled: false,
+            form: NormalizationForm::Nfd,
+        };
+        assert_eq!(mutation.mutate(COMPOSED), COMPOSED);
+    }
+}
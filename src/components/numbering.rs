@@ -0,0 +1,913 @@
+use crate::components::file_browser::FileAbsolutePath;
+use crate::utilities::file_name::{insert_at, split_extension};
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{Color32, ComboBox, DragValue, Grid, Label, RichText, Ui};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct NumberingMutation {
+    pub enabled: bool,
+    pub mode: NumberingMode,
+    pub at_pos: i32,
+    pub start: i64,
+    pub increment: i64,
+    // Minimum number of digits, ignored when auto_pad is set
+    pub pad: usize,
+    // Pad to the width of the largest number in the batch
+    pub auto_pad: bool,
+    pub separator: String,
+    pub keep_extension: bool,
+
+    // Restart the counter whenever this key of the original name changes
+    pub break_key: NumberingKey,
+    // Keep a separate counter for every value of this key
+    pub group_key: NumberingKey,
+
+    pub base: NumberingBase,
+    pub radix: u32,
+    pub base_case: BaseCase,
+    // Digits for NumberingBase::Custom, the first one acts as zero
+    pub alphabet: String,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum NumberingMode {
+    Prefix,
+    #[default]
+    Suffix,
+    Insert,
+}
+
+impl fmt::Display for NumberingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberingMode::Prefix => write!(f, "Prefix"),
+            NumberingMode::Suffix => write!(f, "Suffix"),
+            NumberingMode::Insert => write!(f, "Insert"),
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct NumberingKey {
+    pub source: KeySource,
+    // 1-based grapheme range for KeySource::CharacterRange
+    pub from: usize,
+    pub to: usize,
+    pub pattern: String,
+    pub group: usize,
+    pub date_bucket: DateBucket,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum KeySource {
+    #[default]
+    None,
+    ParentFolder,
+    Extension,
+    CharacterRange,
+    RegexCapture,
+    ModifiedDate,
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::None => write!(f, "None"),
+            KeySource::ParentFolder => write!(f, "Parent folder"),
+            KeySource::Extension => write!(f, "Extension"),
+            KeySource::CharacterRange => write!(f, "Characters"),
+            KeySource::RegexCapture => write!(f, "Regex capture"),
+            KeySource::ModifiedDate => write!(f, "Date modified"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum DateBucket {
+    #[default]
+    Day,
+    Month,
+    Year,
+}
+
+impl fmt::Display for DateBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateBucket::Day => write!(f, "Day"),
+            DateBucket::Month => write!(f, "Month"),
+            DateBucket::Year => write!(f, "Year"),
+        }
+    }
+}
+
+impl Default for NumberingKey {
+    fn default() -> Self {
+        Self {
+            source: KeySource::None,
+            from: 1,
+            to: 1,
+            pattern: "".to_string(),
+            group: 1,
+            date_bucket: DateBucket::Day,
+        }
+    }
+}
+
+impl NumberingKey {
+    // Adds the key rows to a 4 column grid.
+    fn render(&mut self, ui: &mut Ui, label: &str, hover_text: &str) {
+        ui.add(Label::new(label));
+        ComboBox::from_id_salt(("numbering_key", label))
+            .selected_text(self.source.to_string())
+            .show_ui(ui, |ui| {
+                for source in [
+                    KeySource::None,
+                    KeySource::ParentFolder,
+                    KeySource::Extension,
+                    KeySource::CharacterRange,
+                    KeySource::RegexCapture,
+                    KeySource::ModifiedDate,
+                ] {
+                    let text = source.to_string();
+                    ui.selectable_value(&mut self.source, source, text);
+                }
+            })
+            .response
+            .on_hover_text(hover_text);
+
+        match self.source {
+            KeySource::CharacterRange => {
+                ui.end_row();
+                ui.add(Label::new("From"));
+                ui.add(DragValue::new(&mut self.from).range(1..=usize::MAX));
+                ui.add(Label::new("To"));
+                ui.add(DragValue::new(&mut self.to).range(1..=usize::MAX));
+            }
+            KeySource::RegexCapture => {
+                ui.end_row();
+                ui.add(Label::new("Pattern"));
+                ui.text_edit_singleline(&mut self.pattern);
+                ui.add(Label::new("Group"));
+                ui.add(DragValue::new(&mut self.group));
+            }
+            KeySource::ModifiedDate => {
+                ui.add(Label::new("Per"));
+                ComboBox::from_id_salt(("numbering_key_bucket", label))
+                    .selected_text(self.date_bucket.to_string())
+                    .show_ui(ui, |ui| {
+                        for date_bucket in [DateBucket::Day, DateBucket::Month, DateBucket::Year] {
+                            let text = date_bucket.to_string();
+                            ui.selectable_value(&mut self.date_bucket, date_bucket, text);
+                        }
+                    });
+            }
+            _ => {}
+        }
+        ui.end_row();
+    }
+
+    fn regex(&self) -> Result<Option<Regex>, String> {
+        if self.source != KeySource::RegexCapture || self.pattern.is_empty() {
+            return Ok(None);
+        }
+        Regex::new(&self.pattern)
+            .map(Some)
+            .map_err(|err| format!("Invalid pattern: {}", err))
+    }
+
+    fn key(&self, path: &Path, regex: Option<&Regex>) -> Option<String> {
+        let original_name = path.file_name()?.to_str()?;
+        match self.source {
+            KeySource::None => None,
+            KeySource::ParentFolder => Some(path.parent()?.to_str()?.to_string()),
+            KeySource::Extension => Some(split_extension(original_name).1.to_lowercase()),
+            KeySource::CharacterRange => {
+                let from = self.from.max(1) - 1;
+                let to = self.to.max(from + 1);
+                Some(original_name.graphemes(true).skip(from).take(to - from).collect())
+            }
+            KeySource::RegexCapture => {
+                let captures = regex?.captures(original_name)?;
+                Some(captures.get(self.group)?.as_str().to_string())
+            }
+            KeySource::ModifiedDate => {
+                let modified: DateTime<Local> = fs::metadata(path).ok()?.modified().ok()?.into();
+                let format = match self.date_bucket {
+                    DateBucket::Day => "%Y-%m-%d",
+                    DateBucket::Month => "%Y-%m",
+                    DateBucket::Year => "%Y",
+                };
+                Some(modified.format(format).to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum NumberingBase {
+    #[default]
+    Positional,
+    Roman,
+    Alphabetic,
+    Custom,
+}
+
+impl fmt::Display for NumberingBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberingBase::Positional => write!(f, "Base n"),
+            NumberingBase::Roman => write!(f, "Roman"),
+            NumberingBase::Alphabetic => write!(f, "a, b, …, z, aa"),
+            NumberingBase::Custom => write!(f, "Custom digits"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum BaseCase {
+    #[default]
+    Lower,
+    Upper,
+}
+
+impl fmt::Display for BaseCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseCase::Lower => write!(f, "lower"),
+            BaseCase::Upper => write!(f, "UPPER"),
+        }
+    }
+}
+
+impl Default for NumberingMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: NumberingMode::Suffix,
+            at_pos: 0,
+            start: 1,
+            increment: 1,
+            pad: 0,
+            auto_pad: true,
+            separator: "_".to_string(),
+            keep_extension: true,
+            break_key: NumberingKey::default(),
+            group_key: NumberingKey::default(),
+            base: NumberingBase::Positional,
+            radix: 10,
+            base_case: BaseCase::Lower,
+            alphabet: "".to_string(),
+        }
+    }
+}
+
+impl NumberingMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("numbering")
+                .num_columns(4)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Numbering").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Mode"));
+                    ComboBox::from_id_salt("numbering_mode")
+                        .selected_text(self.mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in [
+                                NumberingMode::Prefix,
+                                NumberingMode::Suffix,
+                                NumberingMode::Insert,
+                            ] {
+                                let text = mode.to_string();
+                                ui.selectable_value(&mut self.mode, mode, text);
+                            }
+                        });
+                    ui.add(Label::new("at"));
+                    ui.add_enabled(
+                        self.mode == NumberingMode::Insert,
+                        DragValue::new(&mut self.at_pos),
+                    )
+                    .on_hover_text("Negative positions count from the end");
+                    ui.end_row();
+
+                    ui.add(Label::new("Start"));
+                    ui.add(DragValue::new(&mut self.start));
+                    ui.add(Label::new("Incr."));
+                    ui.add(DragValue::new(&mut self.increment));
+                    ui.end_row();
+
+                    ui.add(Label::new("Pad"));
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            !self.auto_pad,
+                            DragValue::new(&mut self.pad).range(0..=20),
+                        );
+                        ui.checkbox(&mut self.auto_pad, "Auto");
+                    });
+                    ui.add(Label::new("Separator"));
+                    ui.text_edit_singleline(&mut self.separator);
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+
+                    self.break_key.render(
+                        ui,
+                        "Break",
+                        "Restart the counter when this part of the original name changes",
+                    );
+                    self.group_key.render(
+                        ui,
+                        "Group by",
+                        "Count every value of this part of the original name separately",
+                    );
+                });
+
+            for key in [&self.break_key, &self.group_key] {
+                if let Err(error) = key.regex() {
+                    ui.label(RichText::new(error).color(Color32::RED));
+                }
+            }
+
+            Grid::new("numbering_base")
+                .num_columns(4)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.add(Label::new(RichText::new("Base").strong()));
+                    ui.end_row();
+
+                    ui.add(Label::new("Base"));
+                    ui.horizontal(|ui| {
+                        ComboBox::from_id_salt("numbering_base")
+                            .selected_text(self.base.to_string())
+                            .show_ui(ui, |ui| {
+                                for base in [
+                                    NumberingBase::Positional,
+                                    NumberingBase::Roman,
+                                    NumberingBase::Alphabetic,
+                                    NumberingBase::Custom,
+                                ] {
+                                    let text = base.to_string();
+                                    ui.selectable_value(&mut self.base, base, text);
+                                }
+                            });
+                        if self.base == NumberingBase::Positional {
+                            ui.add(DragValue::new(&mut self.radix).range(2..=36));
+                        }
+                    });
+                    ui.add(Label::new("Case"));
+                    ui.add_enabled_ui(self.base != NumberingBase::Custom, |ui| {
+                        ComboBox::from_id_salt("numbering_base_case")
+                            .selected_text(self.base_case.to_string())
+                            .show_ui(ui, |ui| {
+                                for base_case in [BaseCase::Lower, BaseCase::Upper] {
+                                    let text = base_case.to_string();
+                                    ui.selectable_value(&mut self.base_case, base_case, text);
+                                }
+                            });
+                    });
+                    ui.end_row();
+
+                    if self.base == NumberingBase::Custom {
+                        ui.add(Label::new("Digits"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.alphabet)
+                                .hint_text("0123456789ABCDEF"),
+                        );
+                        ui.end_row();
+                    }
+                });
+
+            if let Err(error) = self.custom_digits() {
+                ui.label(RichText::new(error).color(Color32::RED));
+            }
+            if matches!(self.base, NumberingBase::Roman | NumberingBase::Alphabetic) {
+                ui.label(
+                    RichText::new("Roman and alphabetic numbers have no zero digit and are not padded")
+                        .weak(),
+                );
+            }
+        });
+    }
+
+    // Position of each file within its group, starting again at 0 after every break.
+    fn indices(&self, count: usize, paths: &[FileAbsolutePath]) -> Vec<usize> {
+        let keyed = |key: &NumberingKey| key.source != KeySource::None;
+        if !(keyed(&self.break_key) || keyed(&self.group_key)) || paths.len() != count {
+            return (0..count).collect();
+        }
+
+        let break_regex = self.break_key.regex().ok().flatten();
+        let group_regex = self.group_key.regex().ok().flatten();
+        // Next index and last break key of every group
+        let mut groups: HashMap<Option<String>, (usize, Option<String>)> = HashMap::new();
+        paths
+            .iter()
+            .map(|path| {
+                let path = Path::new(path);
+                let group = self.group_key.key(path, group_regex.as_ref());
+                let break_key = self.break_key.key(path, break_regex.as_ref());
+                let (next_index, previous_break_key) = groups
+                    .entry(group)
+                    .or_insert_with(|| (0, break_key.clone()));
+                if *previous_break_key != break_key {
+                    *next_index = 0;
+                    *previous_break_key = break_key;
+                }
+                *next_index += 1;
+                *next_index - 1
+            })
+            .collect()
+    }
+
+    fn number(&self, index: usize) -> i64 {
+        self.start
+            .saturating_add(self.increment.saturating_mul(index as i64))
+    }
+
+    fn custom_digits(&self) -> Result<Vec<&str>, String> {
+        if self.base != NumberingBase::Custom {
+            return Ok(vec![]);
+        }
+        let digits: Vec<&str> = self.alphabet.graphemes(true).collect();
+        if digits.len() < 2 {
+            return Err("Custom digits need at least two characters".to_string());
+        }
+        if digits.iter().enumerate().any(|(index, digit)| digits[..index].contains(digit)) {
+            return Err("Custom digits must not repeat".to_string());
+        }
+        Ok(digits)
+    }
+
+    // Digits of the absolute value, most significant first, and whether leading zeros are allowed.
+    // Values a base cannot represent (e.g. Roman zero) fall back to decimal.
+    fn digits(&self, value: u64) -> (Vec<String>, bool) {
+        let decimal = |paddable| (value.to_string().chars().map(String::from).collect(), paddable);
+        let digits = match self.base {
+            NumberingBase::Positional => {
+                let symbols: Vec<String> = (0..self.radix.clamp(2, 36))
+                    .map(|digit| char::from_digit(digit, 36).unwrap().to_string())
+                    .collect();
+                (positional_digits(value, &symbols), true)
+            }
+            NumberingBase::Custom => match self.custom_digits() {
+                Ok(symbols) => {
+                    let symbols: Vec<String> = symbols.into_iter().map(String::from).collect();
+                    return (positional_digits(value, &symbols), true);
+                }
+                Err(_) => return decimal(true),
+            },
+            NumberingBase::Roman => match roman_digits(value) {
+                Some(digits) => (digits, false),
+                None => return decimal(false),
+            },
+            NumberingBase::Alphabetic => match alphabetic_digits(value) {
+                Some(digits) => (digits, false),
+                None => return decimal(false),
+            },
+        };
+
+        match self.base_case {
+            BaseCase::Lower => (digits.0.iter().map(|d| d.to_lowercase()).collect(), digits.1),
+            BaseCase::Upper => (digits.0.iter().map(|d| d.to_uppercase()).collect(), digits.1),
+        }
+    }
+
+    fn digit_count(&self, number: i64) -> usize {
+        self.digits(number.unsigned_abs()).0.len()
+    }
+
+    fn format_number(&self, number: i64, width: usize) -> String {
+        let sign = if number < 0 { "-" } else { "" };
+        let (digits, paddable) = self.digits(number.unsigned_abs());
+        let padding = if paddable {
+            let zero = match self.base {
+                NumberingBase::Custom => self.custom_digits().ok().map(|d| d[0].to_string()),
+                _ => None,
+            }
+            .unwrap_or_else(|| "0".to_string());
+            zero.repeat(width.saturating_sub(digits.len()))
+        } else {
+            "".to_string()
+        };
+        format!("{}{}{}", sign, padding, digits.concat())
+    }
+
+    fn place(&self, input: &str, number: &str) -> String {
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+        let stem = match self.mode {
+            NumberingMode::Prefix => format!("{}{}{}", number, self.separator, stem),
+            NumberingMode::Suffix => format!("{}{}{}", stem, self.separator, number),
+            NumberingMode::Insert => insert_at(
+                stem,
+                &format!("{}{}{}", self.separator, number, self.separator),
+                self.at_pos,
+            ),
+        };
+        format!("{}{}", stem, extension)
+    }
+}
+
+impl Mutation for NumberingMutation {
+    fn mutate(&self, input: &str) -> String {
+        self.mutate_all(vec![input.to_string()], &[]).remove(0)
+    }
+
+    fn mutate_all(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
+        if !self.enabled || inputs.is_empty() {
+            return inputs;
+        }
+
+        let indices = self.indices(inputs.len(), paths);
+        let width = if self.auto_pad {
+            // Numbers run monotonically, so the widest one is at either end of the longest run
+            let first = self.digit_count(self.number(0));
+            let last = self.digit_count(self.number(*indices.iter().max().unwrap()));
+            first.max(last)
+        } else {
+            self.pad
+        };
+
+        inputs
+            .iter()
+            .zip(indices)
+            .map(|(input, index)| {
+                let number = self.format_number(self.number(index), width);
+                self.place(input, &number)
+            })
+            .collect()
+    }
+}
+
+fn positional_digits(mut value: u64, symbols: &[String]) -> Vec<String> {
+    let base = symbols.len() as u64;
+    let mut digits = vec![];
+    loop {
+        digits.push(symbols[(value % base) as usize].clone());
+        value /= base;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+// Bijective base 26: 1 is "a", 26 is "z", 27 is "aa".
+fn alphabetic_digits(mut value: u64) -> Option<Vec<String>> {
+    if value == 0 {
+        return None;
+    }
+    let mut digits = vec![];
+    while value > 0 {
+        value -= 1;
+        digits.push(((b'a' + (value % 26) as u8) as char).to_string());
+        value /= 26;
+    }
+    digits.reverse();
+    Some(digits)
+}
+
+fn roman_digits(mut value: u64) -> Option<Vec<String>> {
+    if value == 0 || value > 3999 {
+        return None;
+    }
+    let numerals = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut digits = vec![];
+    for (amount, numeral) in numerals {
+        while value >= amount {
+            digits.push(numeral.to_string());
+            value -= amount;
+        }
+    }
+    Some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbering() -> NumberingMutation {
+        NumberingMutation {
+            enabled: true,
+            auto_pad: false,
+            ..Default::default()
+        }
+    }
+
+    fn key(source: KeySource) -> NumberingKey {
+        NumberingKey {
+            source,
+            ..Default::default()
+        }
+    }
+
+    fn paths(paths: &[&str]) -> Vec<FileAbsolutePath> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn parent_folder_groups() {
+        let mutation = NumberingMutation {
+            group_key: key(KeySource::ParentFolder),
+            ..numbering()
+        };
+        let paths = paths(&["/a/1.jpg", "/b/2.jpg", "/a/3.jpg", "/b/4.jpg"]);
+        assert_eq!(mutation.indices(4, &paths), [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn parent_folder_breaks() {
+        let mutation = NumberingMutation {
+            break_key: key(KeySource::ParentFolder),
+            ..numbering()
+        };
+        let paths = paths(&["/a/1.jpg", "/a/2.jpg", "/b/3.jpg", "/a/4.jpg"]);
+        assert_eq!(mutation.indices(4, &paths), [0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn parent_folder_is_the_whole_path() {
+        let mutation = NumberingMutation {
+            group_key: key(KeySource::ParentFolder),
+            ..numbering()
+        };
+        let paths = paths(&["/a/photos/1.jpg", "/b/photos/2.jpg"]);
+        assert_eq!(mutation.indices(2, &paths), [0, 0]);
+    }
+
+    #[test]
+    fn break_restarts_on_every_change() {
+        let mutation = NumberingMutation {
+            break_key: key(KeySource::Extension),
+            ..numbering()
+        };
+        let paths = paths(&["/a/1.jpg", "/a/2.JPG", "/a/3.png", "/a/4.png", "/a/5.jpg"]);
+        assert_eq!(mutation.indices(5, &paths), [0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn group_and_break_together() {
+        let mutation = NumberingMutation {
+            group_key: key(KeySource::ParentFolder),
+            break_key: key(KeySource::Extension),
+            ..numbering()
+        };
+        let paths = paths(&["/a/1.jpg", "/b/2.jpg", "/a/3.jpg", "/a/4.png", "/b/5.jpg"]);
+        assert_eq!(mutation.indices(5, &paths), [0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn character_range_key() {
+        let mutation = NumberingMutation {
+            break_key: NumberingKey {
+                source: KeySource::CharacterRange,
+                from: 1,
+                to: 4,
+                ..NumberingKey::default()
+            },
+            ..numbering()
+        };
+        let paths = paths(&["/a/2023_1.jpg", "/a/2023_2.jpg", "/a/2024_1.jpg", "/a/2024_2.jpg"]);
+        assert_eq!(mutation.indices(4, &paths), [0, 1, 0, 1]);
+        let names: Vec<String> = paths.iter().map(|path| path[3..].to_string()).collect();
+        assert_eq!(
+            mutation.mutate_all(names, &paths),
+            ["2023_1_1.jpg", "2023_2_2.jpg", "2024_1_1.jpg", "2024_2_2.jpg"]
+        );
+    }
+
+    #[test]
+    fn character_range_counts_graphemes() {
+        let key = NumberingKey {
+            source: KeySource::CharacterRange,
+            from: 2,
+            to: 3,
+            ..NumberingKey::default()
+        };
+        assert_eq!(key.key(Path::new("/a/xe\u{301}y.txt"), None).as_deref(), Some("e\u{301}y"));
+        assert_eq!(key.key(Path::new("/a/x"), None).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn regex_capture_key() {
+        let key = NumberingKey {
+            source: KeySource::RegexCapture,
+            pattern: r"^(\w+)-\d+".to_string(),
+            group: 1,
+            ..NumberingKey::default()
+        };
+        let mutation = NumberingMutation { break_key: key, ..numbering() };
+        let paths = paths(&["/a/cat-1.jpg", "/a/cat-2.jpg", "/a/dog-1.jpg", "/a/other.jpg", "/a/misc.jpg"]);
+        // Names without a match share the missing key
+        assert_eq!(mutation.indices(5, &paths), [0, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn invalid_regex_never_breaks() {
+        let key = NumberingKey {
+            source: KeySource::RegexCapture,
+            pattern: "(".to_string(),
+            ..NumberingKey::default()
+        };
+        assert!(key.regex().is_err());
+        let mutation = NumberingMutation { break_key: key, ..numbering() };
+        let paths = paths(&["/a/1.jpg", "/a/2.png"]);
+        assert_eq!(mutation.indices(2, &paths), [0, 1]);
+    }
+
+    #[test]
+    fn breaks_need_the_paths() {
+        let mutation = NumberingMutation {
+            break_key: key(KeySource::Extension),
+            ..numbering()
+        };
+        assert_eq!(mutation.indices(3, &paths(&["/a/1.jpg"])), [0, 1, 2]);
+        assert_eq!(mutation.indices(2, &[]), [0, 1]);
+    }
+
+    fn numbers(mutation: &NumberingMutation, count: usize) -> Vec<String> {
+        let inputs = vec!["a".to_string(); count];
+        mutation
+            .mutate_all(inputs, &[])
+            .into_iter()
+            .map(|name| name.trim_start_matches("a_").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn suffix_keeps_the_extension() {
+        assert_eq!(numbering().mutate("photo.jpg"), "photo_1.jpg");
+        let mutation = NumberingMutation { keep_extension: false, ..numbering() };
+        assert_eq!(mutation.mutate("photo.jpg"), "photo.jpg_1");
+    }
+
+    #[test]
+    fn prefix_and_insert() {
+        let mutation = NumberingMutation { mode: NumberingMode::Prefix, ..numbering() };
+        assert_eq!(mutation.mutate("photo.jpg"), "1_photo.jpg");
+        let mutation = NumberingMutation {
+            mode: NumberingMode::Insert,
+            at_pos: 2,
+            ..numbering()
+        };
+        assert_eq!(mutation.mutate("photo.jpg"), "ph_1_oto.jpg");
+    }
+
+    #[test]
+    fn roman() {
+        let mutation = NumberingMutation {
+            base: NumberingBase::Roman,
+            start: 3998,
+            ..numbering()
+        };
+        assert_eq!(numbers(&mutation, 3), ["mmmcmxcviii", "mmmcmxcix", "4000"]);
+        let mutation = NumberingMutation { start: 0, base_case: BaseCase::Upper, ..mutation };
+        assert_eq!(numbers(&mutation, 5), ["0", "I", "II", "III", "IV"]);
+    }
+
+    #[test]
+    fn roman_is_not_padded() {
+        let mutation = NumberingMutation {
+            base: NumberingBase::Roman,
+            start: 8,
+            pad: 3,
+            ..numbering()
+        };
+        assert_eq!(numbers(&mutation, 2), ["viii", "ix"]);
+    }
+
+    #[test]
+    fn alphabetic_is_bijective() {
+        let mutation = NumberingMutation {
+            base: NumberingBase::Alphabetic,
+            base_case: BaseCase::Upper,
+            start: 25,
+            ..numbering()
+        };
+        assert_eq!(numbers(&mutation, 3), ["Y", "Z", "AA"]);
+        let mutation = NumberingMutation { start: 702, ..mutation };
+        assert_eq!(numbers(&mutation, 2), ["ZZ", "AAA"]);
+        let mutation = NumberingMutation { start: 0, ..mutation };
+        assert_eq!(numbers(&mutation, 2), ["0", "A"]);
+    }
+
+    #[test]
+    fn positional_radix() {
+        let mutation = NumberingMutation {
+            radix: 16,
+            start: 9,
+            ..numbering()
+        };
+        assert_eq!(numbers(&mutation, 3), ["9", "a", "b"]);
+        let mutation = NumberingMutation { radix: 2, start: 0, ..numbering() };
+        assert_eq!(numbers(&mutation, 3), ["0", "1", "10"]);
+    }
+
+    #[test]
+    fn custom_digits() {
+        let mutation = NumberingMutation {
+            base: NumberingBase::Custom,
+            alphabet: "oi".to_string(),
+            start: 0,
+            pad: 3,
+            ..numbering()
+        };
+        assert_eq!(numbers(&mutation, 4), ["ooo", "ooi", "oio", "oii"]);
+        let mutation = NumberingMutation { alphabet: "ωe\u{301}".to_string(), start: 2, pad: 0, ..mutation };
+        assert_eq!(numbers(&mutation, 1), ["e\u{301}ω"]);
+    }
+
+    #[test]
+    fn invalid_custom_digits_fall_back_to_decimal() {
+        for alphabet in ["", "x", "xyx"] {
+            let mutation = NumberingMutation {
+                base: NumberingBase::Custom,
+                alphabet: alphabet.to_string(),
+                start: 10,
+                ..numbering()
+            };
+            assert!(mutation.custom_digits().is_err());
+            assert_eq!(numbers(&mutation, 1), ["10"]);
+        }
+    }
+
+    #[test]
+    fn zero_padding() {
+        let mutation = NumberingMutation { pad: 3, start: 9, ..numbering() };
+        assert_eq!(numbers(&mutation, 2), ["009", "010"]);
+        let mutation = NumberingMutation { pad: 3, start: -5, ..numbering() };
+        assert_eq!(numbers(&mutation, 1), ["-005"]);
+        let mutation = NumberingMutation { pad: 2, start: 123, ..numbering() };
+        assert_eq!(numbers(&mutation, 1), ["123"]);
+    }
+
+    #[test]
+    fn auto_padding() {
+        let mutation = NumberingMutation { auto_pad: true, start: 8, ..numbering() };
+        assert_eq!(numbers(&mutation, 3), ["08", "09", "10"]);
+        let mutation = NumberingMutation { increment: -1, start: 10, ..mutation };
+        assert_eq!(numbers(&mutation, 3), ["10", "09", "08"]);
+    }
+
+    #[test]
+    fn steps_saturate() {
+        let mutation = NumberingMutation {
+            start: i64::MAX - 1,
+            increment: i64::MAX,
+            ..numbering()
+        };
+        let max = i64::MAX.to_string();
+        assert_eq!(numbers(&mutation, 3), [(i64::MAX - 1).to_string(), max.clone(), max]);
+        let mutation = NumberingMutation {
+            start: i64::MIN,
+            increment: -1,
+            auto_pad: true,
+            ..numbering()
+        };
+        let min = i64::MIN.to_string();
+        assert_eq!(numbers(&mutation, 2), [min.clone(), min]);
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = NumberingMutation { enabled: false, ..numbering() };
+        assert_eq!(mutation.mutate("photo.jpg"), "photo.jpg");
+    }
+}
@@ -0,0 +1,53 @@
+use crate::utilities::hashes;
+use egui::{Color32, ProgressBar, RichText, Ui};
+use std::fmt::Display;
+use std::path::Path;
+
+// Failed files and warnings are listed up to this many, the rest are counted
+const SHOWN_LINES: usize = 5;
+
+// What a stage ran into on the last run of the pipeline
+#[derive(Default)]
+pub struct PipelineReport {
+    // Files keeping their name, with why
+    pub errors: Vec<String>,
+    // Things worth a look that do not stop a rename
+    pub warnings: Vec<String>,
+    // Files keeping their name until they have been read or hashed
+    pub pending: usize,
+}
+
+impl PipelineReport {
+    pub fn fail(&mut self, path: &Path, err: impl Display) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.errors.push(format!("{}: {}", name, err));
+    }
+
+    pub fn render(&self, ui: &mut Ui) {
+        if self.pending > 0 {
+            ui.label(format!("Reading {} files", self.pending));
+            if let Some((done, total)) = hashes::progress() {
+                let fraction = if total > 0 { done as f32 / total as f32 } else { 0.0 };
+                ui.add(ProgressBar::new(fraction).show_percentage());
+            }
+        }
+        let warn = ui.visuals().warn_fg_color;
+        render_lines(ui, &self.warnings, warn);
+        if !self.errors.is_empty() {
+            ui.label(
+                RichText::new(format!("{} files keep their name:", self.errors.len()))
+                    .color(Color32::RED),
+            );
+            render_lines(ui, &self.errors, Color32::RED);
+        }
+    }
+}
+
+fn render_lines(ui: &mut Ui, lines: &[String], color: Color32) {
+    for line in lines.iter().take(SHOWN_LINES) {
+        ui.label(RichText::new(line).color(color));
+    }
+    if lines.len() > SHOWN_LINES {
+        ui.label(format!("and {} more", lines.len() - SHOWN_LINES));
+    }
+}
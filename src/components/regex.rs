@@ -1,5 +1,5 @@
 
-use egui::{Grid, Label, RichText, Ui};
+use egui::{Color32, Grid, Label, RichText, Ui};
 use regex;
 use crate::utilities::mutation_pipeline::Mutation;
 
@@ -39,13 +39,19 @@ impl RegexMutation {
                     ui.text_edit_singleline(&mut self.substitution);
                     ui.end_row();
                 });
+
+            if !self.pattern.is_empty() {
+                if let Err(err) = regex::Regex::new(&self.pattern) {
+                    ui.label(RichText::new(format!("Invalid pattern: {}", err)).color(Color32::RED));
+                }
+            }
         });
     }
 }
 
 impl Mutation for RegexMutation {
     fn mutate(&self, input: &str) -> String {
-        if self.enabled && self.pattern.len() > 0 {
+        if self.enabled && !self.pattern.is_empty() {
             if let Ok(regex) = regex::Regex::new(&self.pattern) {
                 match regex.replace_all(input, &self.substitution).parse() {
                     Ok(replaced) => replaced,
@@ -0,0 +1,216 @@
+use crate::utilities::file_name::split_extension;
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{Color32, DragValue, Grid, Label, RichText, Ui};
+use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Positions are counted in graphemes (user-perceived characters) and are 1-based like the UI.
+// A value of 0 leaves the corresponding rule off.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RemoveMutation {
+    pub enabled: bool,
+    pub first_n: usize,
+    pub last_n: usize,
+    pub from: usize,
+    pub to: usize,
+    pub chars: String,
+    pub words: String,
+    pub digits: bool,
+    pub accents: bool,
+    pub trim: bool,
+    pub keep_extension: bool,
+}
+
+impl Default for RemoveMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            first_n: 0,
+            last_n: 0,
+            from: 0,
+            to: 0,
+            chars: "".to_string(),
+            words: "".to_string(),
+            digits: false,
+            accents: false,
+            trim: false,
+            keep_extension: true,
+        }
+    }
+}
+
+impl RemoveMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("remove")
+                .num_columns(4)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Remove").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("First n"));
+                    ui.add(DragValue::new(&mut self.first_n));
+                    ui.add(Label::new("Last n"));
+                    ui.add(DragValue::new(&mut self.last_n));
+                    ui.end_row();
+
+                    ui.add(Label::new("From"));
+                    ui.add(DragValue::new(&mut self.from));
+                    ui.add(Label::new("To"));
+                    ui.add(DragValue::new(&mut self.to));
+                    ui.end_row();
+
+                    ui.add(Label::new("Chars"));
+                    ui.text_edit_singleline(&mut self.chars);
+                    ui.add(Label::new("Words"));
+                    ui.text_edit_singleline(&mut self.words);
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.digits, "Digits");
+                    ui.checkbox(&mut self.accents, "Accents");
+                    ui.checkbox(&mut self.trim, "Trim");
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+                });
+
+            if let Some(error) = self.range_error() {
+                ui.label(RichText::new(error).color(Color32::RED));
+            }
+        });
+    }
+
+    fn range_error(&self) -> Option<&'static str> {
+        match (self.from, self.to) {
+            (0, 0) => None,
+            (0, _) => Some("From must be set when To is set"),
+            (_, 0) => Some("To must be set when From is set"),
+            (from, to) if from > to => Some("From must not be after To"),
+            _ => None,
+        }
+    }
+
+    fn remove_words(&self, input: &str) -> String {
+        self.words
+            .split_whitespace()
+            .fold(input.to_string(), |acc, word| {
+                // Take the whitespace on one side of the word with it so no double spaces are left behind
+                let word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                let start = if word_char(word.chars().next()) { r"\b" } else { "" };
+                let end = if word_char(word.chars().last()) { r"\b" } else { "" };
+                let word = format!("{}{}{}", start, regex::escape(word), end);
+                match Regex::new(&format!(r"\s+{0}|{0}\s*", word)) {
+                    Ok(regex) => regex.replace_all(&acc, "").to_string(),
+                    Err(err) => {
+                        eprintln!("Error building word pattern: {:#?}", err);
+                        acc
+                    }
+                }
+            })
+    }
+}
+
+impl Mutation for RemoveMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+
+        let mut graphemes: Vec<&str> = stem.graphemes(true).collect();
+
+        let first_n = self.first_n.min(graphemes.len());
+        graphemes.drain(..first_n);
+
+        let last_n = self.last_n.min(graphemes.len());
+        graphemes.truncate(graphemes.len() - last_n);
+
+        if self.from > 0 && self.range_error().is_none() {
+            let from = (self.from - 1).min(graphemes.len());
+            let to = self.to.min(graphemes.len());
+            graphemes.drain(from..to);
+        }
+
+        if !self.chars.is_empty() {
+            let chars: Vec<&str> = self.chars.graphemes(true).collect();
+            graphemes.retain(|grapheme| !chars.contains(grapheme));
+        }
+
+        if self.digits {
+            graphemes.retain(|grapheme| !grapheme.chars().next().is_some_and(char::is_numeric));
+        }
+
+        let mut output = self.remove_words(&graphemes.concat());
+
+        if self.accents {
+            output = output.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect();
+        }
+
+        if self.trim {
+            output = output.trim().to_string();
+        }
+
+        output + extension
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remove() -> RemoveMutation {
+        RemoveMutation {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn digits_leave_the_extension() {
+        let mutation = RemoveMutation { digits: true, ..remove() };
+        assert_eq!(mutation.mutate("clip01.mp4"), "clip.mp4");
+    }
+
+    #[test]
+    fn last_n_counts_from_the_end_of_the_stem() {
+        let mutation = RemoveMutation { last_n: 2, ..remove() };
+        assert_eq!(mutation.mutate("clip01.mp4"), "clip.mp4");
+    }
+
+    #[test]
+    fn chars_leave_the_extension() {
+        let mutation = RemoveMutation { chars: "p".to_string(), ..remove() };
+        assert_eq!(mutation.mutate("photo.jpg"), "hoto.jpg");
+    }
+
+    #[test]
+    fn whole_name_without_keep_extension() {
+        let mutation = RemoveMutation {
+            digits: true,
+            keep_extension: false,
+            ..remove()
+        };
+        assert_eq!(mutation.mutate("clip01.mp4"), "clip.mp");
+    }
+
+    #[test]
+    fn graphemes_stay_whole() {
+        let mutation = RemoveMutation { first_n: 1, ..remove() };
+        assert_eq!(mutation.mutate("e\u{301}te\u{301}.txt"), "te\u{301}.txt");
+    }
+
+    #[test]
+    fn dotfiles_have_no_extension() {
+        let mutation = RemoveMutation { first_n: 1, ..remove() };
+        assert_eq!(mutation.mutate(".bashrc"), "bashrc");
+    }
+}
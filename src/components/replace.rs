@@ -0,0 +1,195 @@
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{Grid, Label, RichText, Ui};
+use regex::{NoExpand, Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+// Every keystroke in a search field is a new pattern, so the cache starts over past this many
+const CACHED_PATTERNS: usize = 256;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ReplaceMutation {
+    pub enabled: bool,
+    pub pairs: Vec<ReplacePair>,
+    pub case_sensitive: bool,
+    pub first_only: bool,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ReplacePair {
+    pub find: String,
+    pub replace_with: String,
+}
+
+impl Default for ReplaceMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pairs: vec![ReplacePair::default()],
+            case_sensitive: false,
+            first_only: false,
+        }
+    }
+}
+
+impl ReplaceMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            let mut removed_pair = None;
+            Grid::new("replace")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Replace").strong());
+                    ui.end_row();
+
+                    let can_remove = self.pairs.len() > 1;
+                    for (index, pair) in self.pairs.iter_mut().enumerate() {
+                        ui.add(Label::new("Replace"));
+                        ui.text_edit_singleline(&mut pair.find);
+                        ui.end_row();
+
+                        ui.add(Label::new("With"));
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut pair.replace_with);
+                            if can_remove
+                                && ui
+                                    .button(egui_phosphor::regular::TRASH.to_string())
+                                    .on_hover_text("Remove this pair")
+                                    .clicked()
+                            {
+                                removed_pair = Some(index);
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.case_sensitive, "Match case");
+                        ui.checkbox(&mut self.first_only, "First");
+                    });
+                    if ui
+                        .button(format!("{} Add pair", egui_phosphor::regular::PLUS))
+                        .clicked()
+                    {
+                        self.pairs.push(ReplacePair::default());
+                    }
+                    ui.end_row();
+                });
+
+            if let Some(index) = removed_pair {
+                self.pairs.remove(index);
+            }
+        });
+    }
+
+    fn replace_pair(&self, input: &str, pair: &ReplacePair) -> String {
+        if pair.find.is_empty() {
+            return input.to_string();
+        }
+
+        match literal_regex(&pair.find, self.case_sensitive) {
+            Ok(regex) => {
+                let limit = if self.first_only { 1 } else { 0 };
+                regex
+                    .replacen(input, limit, NoExpand(&pair.replace_with))
+                    .to_string()
+            }
+            Err(err) => {
+                eprintln!("Error building replace pattern: {:#?}", err);
+                input.to_string()
+            }
+        }
+    }
+}
+
+// The search text is literal, the regex engine only provides case folding. Each pattern is built
+// once and shared by every name on every frame after that.
+fn literal_regex(find: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    static PATTERNS: OnceLock<Mutex<HashMap<(String, bool), Regex>>> = OnceLock::new();
+    let mut patterns = PATTERNS.get_or_init(Default::default).lock().unwrap();
+    let key = (find.to_string(), case_sensitive);
+    if let Some(regex) = patterns.get(&key) {
+        return Ok(regex.clone());
+    }
+    let regex = RegexBuilder::new(&regex::escape(find))
+        .case_insensitive(!case_sensitive)
+        .build()?;
+    if patterns.len() >= CACHED_PATTERNS {
+        patterns.clear();
+    }
+    patterns.insert(key, regex.clone());
+    Ok(regex)
+}
+
+impl Mutation for ReplaceMutation {
+    fn mutate(&self, input: &str) -> String {
+        if self.enabled {
+            self.pairs
+                .iter()
+                .fold(input.to_string(), |acc, pair| self.replace_pair(&acc, pair))
+        } else {
+            input.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(find: &str, replace_with: &str) -> ReplaceMutation {
+        ReplaceMutation {
+            enabled: true,
+            pairs: vec![ReplacePair {
+                find: find.to_string(),
+                replace_with: replace_with.to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn search_text_is_literal() {
+        assert_eq!(replace(".", "_").mutate("a.b.txt"), "a_b_txt");
+        assert_eq!(replace("(1)", "$1").mutate("photo (1).jpg"), "photo $1.jpg");
+    }
+
+    #[test]
+    fn case_and_first_only() {
+        let mutation = replace("img", "photo");
+        assert_eq!(mutation.mutate("IMG_img.jpg"), "photo_photo.jpg");
+        let mutation = ReplaceMutation { case_sensitive: true, ..mutation };
+        assert_eq!(mutation.mutate("IMG_img.jpg"), "IMG_photo.jpg");
+        let mutation = ReplaceMutation { case_sensitive: false, first_only: true, ..mutation };
+        assert_eq!(mutation.mutate("IMG_img.jpg"), "photo_img.jpg");
+    }
+
+    #[test]
+    fn pairs_apply_in_order() {
+        let mut mutation = replace("a", "b");
+        mutation.pairs.push(ReplacePair {
+            find: "b".to_string(),
+            replace_with: "c".to_string(),
+        });
+        mutation.pairs.push(ReplacePair::default());
+        assert_eq!(mutation.mutate("ab.txt"), "cc.txt");
+    }
+
+    #[test]
+    fn patterns_are_cached_by_case() {
+        let sensitive = literal_regex("Cache", true).unwrap();
+        let insensitive = literal_regex("Cache", false).unwrap();
+        assert!(!sensitive.is_match("cache") && insensitive.is_match("cache"));
+        assert!(!literal_regex("Cache", true).unwrap().is_match("cache"));
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = ReplaceMutation { enabled: false, ..replace("a", "b") };
+        assert_eq!(mutation.mutate("a.txt"), "a.txt");
+    }
+}
@@ -0,0 +1,325 @@
+use crate::utilities::file_name::{reserved_stem, split_extension};
+use crate::utilities::mutation_pipeline::Mutation;
+use crate::utilities::tokens::slug;
+use egui::{Color32, ComboBox, Grid, Label, RichText, Ui};
+use std::fmt;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+// Used when the replacement is empty but something has to be added
+const FALLBACK: &str = "_";
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SanitizeMutation {
+    pub enabled: bool,
+    pub profile: SanitizeProfile,
+    // Empty removes forbidden characters instead
+    pub replacement: String,
+    // One replacement for a run of forbidden characters
+    pub collapse: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum SanitizeProfile {
+    Posix,
+    #[default]
+    Windows,
+    Fat,
+    Slug,
+    S3,
+}
+
+impl fmt::Display for SanitizeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanitizeProfile::Posix => write!(f, "POSIX"),
+            SanitizeProfile::Windows => write!(f, "Windows / NTFS / SMB"),
+            SanitizeProfile::Fat => write!(f, "FAT32 / exFAT"),
+            SanitizeProfile::Slug => write!(f, "URL-safe slug"),
+            SanitizeProfile::S3 => write!(f, "S3 key"),
+        }
+    }
+}
+
+impl SanitizeProfile {
+    fn description(&self) -> &'static str {
+        match self {
+            SanitizeProfile::Posix => "Replaces slashes and control characters",
+            SanitizeProfile::Windows | SanitizeProfile::Fat => {
+                "Replaces < > : \" / \\ | ? * and control characters, drops trailing dots and spaces and renames reserved names like CON or NUL"
+            }
+            SanitizeProfile::Slug => "Lower case ASCII letters and digits joined by dashes, the extension is kept",
+            SanitizeProfile::S3 => "Keeps letters, digits and ! - _ . * ' ( ), as recommended for S3 object keys",
+        }
+    }
+
+    fn is_forbidden(&self, c: char) -> bool {
+        if c.is_control() {
+            return true;
+        }
+        match self {
+            SanitizeProfile::Posix => c == '/',
+            // FAT32 long names and exFAT forbid the same characters as Windows does
+            SanitizeProfile::Windows | SanitizeProfile::Fat => {
+                matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+            }
+            SanitizeProfile::Slug | SanitizeProfile::S3 => {
+                !(c.is_ascii_alphanumeric() || "!-_.*'()".contains(c))
+            }
+        }
+    }
+}
+
+impl Default for SanitizeMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            profile: SanitizeProfile::Windows,
+            replacement: "_".to_string(),
+            collapse: true,
+        }
+    }
+}
+
+impl SanitizeMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("sanitize")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Sanitize").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Profile"));
+                    ComboBox::from_id_salt("sanitize_profile")
+                        .selected_text(self.profile.to_string())
+                        .show_ui(ui, |ui| {
+                            for profile in [
+                                SanitizeProfile::Posix,
+                                SanitizeProfile::Windows,
+                                SanitizeProfile::Fat,
+                                SanitizeProfile::Slug,
+                                SanitizeProfile::S3,
+                            ] {
+                                let text = profile.to_string();
+                                ui.selectable_value(&mut self.profile, profile, text)
+                                    .on_hover_text(profile.description());
+                            }
+                        });
+                    ui.end_row();
+
+                    if self.profile != SanitizeProfile::Slug {
+                        ui.add(Label::new("Replace with"));
+                        ui.text_edit_singleline(&mut self.replacement)
+                            .on_hover_text("Leave empty to remove forbidden characters");
+                        ui.end_row();
+
+                        if self.replacement.chars().any(|c| self.profile.is_forbidden(c)) {
+                            ui.label("");
+                            ui.label(
+                                RichText::new("The replacement has characters this profile forbids, they are left out")
+                                    .color(Color32::RED),
+                            );
+                            ui.end_row();
+                        }
+
+                        ui.checkbox(&mut self.collapse, "Collapse repeats")
+                            .on_hover_text("Replace a run of forbidden characters only once");
+                        ui.end_row();
+                    }
+                });
+            ui.label(RichText::new(self.profile.description()).weak());
+        });
+    }
+
+    // The replacement without the characters the profile forbids itself
+    fn safe_replacement(&self) -> String {
+        self.replacement
+            .chars()
+            .filter(|c| !self.profile.is_forbidden(*c))
+            .collect()
+    }
+
+    // For when something has to be added, even with an empty replacement
+    fn filler(&self) -> String {
+        match self.safe_replacement() {
+            replacement if replacement.is_empty() => FALLBACK.to_string(),
+            replacement => replacement,
+        }
+    }
+
+    fn replace_forbidden(&self, input: &str) -> String {
+        let replacement = self.safe_replacement();
+        let mut sanitized = String::new();
+        let mut replaced = false;
+        for c in input.chars() {
+            if !self.profile.is_forbidden(c) {
+                sanitized.push(c);
+                replaced = false;
+            } else if !(self.collapse && replaced) {
+                sanitized.push_str(&replacement);
+                replaced = true;
+            }
+        }
+        sanitized
+    }
+}
+
+impl Mutation for SanitizeMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+        let mut sanitized = match self.profile {
+            SanitizeProfile::Slug => {
+                let (stem, extension) = split_extension(input);
+                // A stem without ASCII letters or digits would leave a hidden file like ".txt"
+                let stem = match slug(stem) {
+                    stem if stem.is_empty() => self.filler(),
+                    stem => stem,
+                };
+                match slug(extension) {
+                    extension if extension.is_empty() => stem,
+                    extension => format!("{}.{}", stem, extension),
+                }
+            }
+            // Accented letters keep their base letter rather than being replaced
+            SanitizeProfile::S3 => {
+                let folded: String = input.nfkd().filter(|c| !is_combining_mark(*c)).collect();
+                self.replace_forbidden(&folded)
+            }
+            _ => self.replace_forbidden(input),
+        };
+
+        if matches!(self.profile, SanitizeProfile::Windows | SanitizeProfile::Fat) {
+            // Windows drops them silently, so "report." and "report" would collide
+            sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+            if let Some(stem) = reserved_stem(&sanitized) {
+                let stem_len = stem.len();
+                sanitized.insert_str(stem_len, &self.filler());
+            }
+        }
+
+        // Names no file system accepts
+        match sanitized.as_str() {
+            "" | "." | ".." => self.filler(),
+            _ => sanitized,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(profile: SanitizeProfile) -> SanitizeMutation {
+        SanitizeMutation {
+            enabled: true,
+            profile,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn posix() {
+        let mutation = sanitize(SanitizeProfile::Posix);
+        assert_eq!(mutation.mutate("a/b\tc:d.txt"), "a_b_c:d.txt");
+        assert_eq!(mutation.mutate("name. "), "name. ");
+        assert_eq!(mutation.mutate("CON.txt"), "CON.txt");
+    }
+
+    #[test]
+    fn collapse() {
+        let mutation = sanitize(SanitizeProfile::Posix);
+        assert_eq!(mutation.mutate("a//b"), "a_b");
+        let mutation = SanitizeMutation { collapse: false, ..mutation };
+        assert_eq!(mutation.mutate("a//b"), "a__b");
+    }
+
+    #[test]
+    fn windows_and_fat() {
+        for profile in [SanitizeProfile::Windows, SanitizeProfile::Fat] {
+            let mutation = sanitize(profile);
+            assert_eq!(mutation.mutate("a<b>:c?.txt"), "a_b_c_.txt");
+            assert_eq!(mutation.mutate("AC/DC \"Live\".mp3"), "AC_DC _Live_.mp3");
+        }
+    }
+
+    #[test]
+    fn windows_trailing_dots_and_spaces() {
+        let mutation = sanitize(SanitizeProfile::Windows);
+        assert_eq!(mutation.mutate("report. . "), "report");
+        assert_eq!(mutation.mutate("..."), "_");
+        assert_eq!(mutation.mutate(".."), "_");
+    }
+
+    #[test]
+    fn windows_reserved_names() {
+        let mutation = sanitize(SanitizeProfile::Windows);
+        assert_eq!(mutation.mutate("CON.txt"), "CON_.txt");
+        assert_eq!(mutation.mutate("nul"), "nul_");
+        assert_eq!(mutation.mutate("com1.tar.gz"), "com1_.tar.gz");
+        assert_eq!(mutation.mutate("LPT1 .txt"), "LPT1_ .txt");
+        assert_eq!(mutation.mutate("CONSOLE.txt"), "CONSOLE.txt");
+    }
+
+    #[test]
+    fn empty_replacement() {
+        let mutation = SanitizeMutation {
+            replacement: "".to_string(),
+            ..sanitize(SanitizeProfile::Windows)
+        };
+        assert_eq!(mutation.mutate("a:b?.txt"), "ab.txt");
+        // Something still has to be added or be left
+        assert_eq!(mutation.mutate("CON.txt"), "CON_.txt");
+        assert_eq!(mutation.mutate("???"), "_");
+    }
+
+    #[test]
+    fn forbidden_replacement() {
+        let mutation = SanitizeMutation {
+            replacement: ":".to_string(),
+            ..sanitize(SanitizeProfile::Windows)
+        };
+        assert_eq!(mutation.mutate("a?b.txt"), "ab.txt");
+        assert_eq!(mutation.mutate("?"), "_");
+    }
+
+    #[test]
+    fn slug() {
+        let mutation = sanitize(SanitizeProfile::Slug);
+        assert_eq!(mutation.mutate("Hello, World!.JPG"), "hello-world.jpg");
+        assert_eq!(mutation.mutate("Crème Brûlée.txt"), "creme-brulee.txt");
+        assert_eq!(mutation.mutate(".bashrc"), "bashrc");
+    }
+
+    #[test]
+    fn slug_without_ascii_letters() {
+        let mutation = sanitize(SanitizeProfile::Slug);
+        assert_eq!(mutation.mutate("Привет.txt"), "_.txt");
+        assert_eq!(mutation.mutate("北京.jpg"), "_.jpg");
+        assert_eq!(mutation.mutate("😀"), "_");
+        let mutation = SanitizeMutation {
+            replacement: "".to_string(),
+            ..mutation
+        };
+        assert_eq!(mutation.mutate("😀.png"), "_.png");
+    }
+
+    #[test]
+    fn s3() {
+        let mutation = sanitize(SanitizeProfile::S3);
+        assert_eq!(mutation.mutate("Café & Crème.txt"), "Cafe_Creme.txt");
+        assert_eq!(mutation.mutate("(draft)!.txt"), "(draft)!.txt");
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = SanitizeMutation::default();
+        assert_eq!(mutation.mutate("a:b/c.txt"), "a:b/c.txt");
+    }
+}
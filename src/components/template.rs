@@ -0,0 +1,509 @@
+use crate::components::file_browser::FileAbsolutePath;
+use crate::components::pipeline_report::PipelineReport;
+use crate::utilities::file_name::split_extension;
+use crate::utilities::hashes::{self, HashAlgorithm, HashStatus};
+use crate::utilities::metadata::{
+    file_tokens, AUDIO_TOKENS, DOCUMENT_TOKENS, EXIF_TOKENS, FILE_TOKENS, HASH_TOKENS, VIDEO_TOKENS,
+};
+use crate::utilities::mutation_pipeline::Mutation;
+use crate::utilities::tokens::{expand, parse, Filter, Segment, TemplateError, TokenValue, Tokens};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, DragValue, Grid, Label, RichText, ScrollArea, Stroke, TextStyle, Ui};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Long values like hashes are cut in the token list
+const SHOWN_VALUE_CHARS: usize = 40;
+
+// Tokens only this stage knows, they come from the name and the position in the selection
+const TEMPLATE_TOKENS: &[(&str, &str)] = &[
+    ("name", "Name as it reaches this stage, after the stages above"),
+    ("stem", "Name without the extension"),
+    ("ext", "Extension with its dot"),
+    ("original", "Name of the file on disk"),
+    ("original_stem", "Name on disk without the extension"),
+    ("parent", "Parent folder"),
+    ("n", "Counter"),
+    ("total", "Number of files"),
+    ("mtime", "Modification date"),
+    ("ctime", "Creation date"),
+    ("size", "Size in bytes"),
+    ("re.0", "Text matched by the regex"),
+    ("re.1", "First regex group, named groups go by name"),
+];
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TemplateMutation {
+    pub enabled: bool,
+    pub template: String,
+    // Matched against the name for the `re.` tokens
+    pub pattern: String,
+    pub start: i64,
+    pub increment: i64,
+    #[serde(skip)]
+    status: Arc<Mutex<TemplateStatus>>,
+}
+
+#[derive(Default)]
+struct TemplateStatus {
+    report: PipelineReport,
+    // Name each file reached this stage with and its position, for the token values
+    inputs: HashMap<FileAbsolutePath, (String, usize)>,
+    total: usize,
+}
+
+impl Default for TemplateMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "{parent}_{mtime:%Y%m%d}_{n:03}{ext}".to_string(),
+            pattern: "".to_string(),
+            start: 1,
+            increment: 1,
+            status: Arc::new(Mutex::new(TemplateStatus::default())),
+        }
+    }
+}
+
+// Everything a template can use for one file.
+struct FileValues {
+    local: Tokens,
+    file: Arc<Tokens>,
+    digests: BTreeMap<HashAlgorithm, String>,
+}
+
+impl FileValues {
+    fn get(&self, name: &str) -> Option<TokenValue> {
+        if let Some(algorithm) = HashAlgorithm::from_name(name) {
+            return self.digests.get(&algorithm).cloned().map(TokenValue::Text);
+        }
+        self.local.get(name).or_else(|| self.file.get(name)).cloned()
+    }
+}
+
+// Tokens read from the file contents, a template without them does not wait for the file
+fn uses_file_tokens(segments: &[Segment]) -> bool {
+    segments.iter().any(|segment| match segment {
+        Segment::Token { name, .. } => {
+            matches!(name.as_str(), "mtime" | "ctime")
+                || FILE_TOKENS
+                    .iter()
+                    .chain(EXIF_TOKENS)
+                    .chain(AUDIO_TOKENS)
+                    .chain(VIDEO_TOKENS)
+                    .chain(DOCUMENT_TOKENS)
+                    .any(|(known, _)| known == name)
+        }
+        Segment::Literal(_) => false,
+    })
+}
+
+fn is_known(name: &str) -> bool {
+    name.starts_with("re.")
+        || TEMPLATE_TOKENS
+            .iter()
+            .chain(FILE_TOKENS)
+            .chain(EXIF_TOKENS)
+            .chain(AUDIO_TOKENS)
+            .chain(VIDEO_TOKENS)
+            .chain(DOCUMENT_TOKENS)
+            .chain(HASH_TOKENS)
+            .any(|(known, _)| *known == name)
+}
+
+// Parses the template and rejects tokens no file could have, so typos show up in the editor.
+fn check(template: &str) -> Result<Vec<Segment>, TemplateError> {
+    let segments = parse(template)?;
+    for segment in &segments {
+        if let Segment::Token { name, span, .. } = segment {
+            if !is_known(name) {
+                return Err(TemplateError::new(format!("Unknown token {{{}}}", name), span.clone()));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+// Tokens stand out from the literal text, the part an error is about is underlined in red.
+fn highlight(ui: &Ui, template: &str) -> LayoutJob {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let error = check(template).err().map(|err| err.span);
+    let format = |color: Color32, is_error: bool| TextFormat {
+        font_id: font_id.clone(),
+        color: if is_error { Color32::RED } else { color },
+        underline: if is_error { Stroke::new(1.0, Color32::RED) } else { Stroke::NONE },
+        ..Default::default()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut in_token = false;
+    let mut chars = template.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let mut end = index + c.len_utf8();
+        let mut color = if in_token {
+            ui.visuals().hyperlink_color
+        } else {
+            ui.visuals().text_color()
+        };
+        match c {
+            '{' | '}' if !in_token && chars.peek().map(|(_, next)| *next) == Some(c) => {
+                chars.next();
+                end += 1;
+            }
+            '{' => {
+                in_token = true;
+                color = ui.visuals().hyperlink_color;
+            }
+            '}' => in_token = false,
+            _ => {}
+        }
+        let is_error = error
+            .as_ref()
+            .is_some_and(|span| index < span.end && end > span.start);
+        job.append(&template[index..end], 0.0, format(color, is_error));
+    }
+    job
+}
+
+impl TemplateMutation {
+    pub fn render(&mut self, ui: &mut Ui, hovered_file: Option<&FileAbsolutePath>) {
+        ui.group(|ui| {
+            let segments = check(&self.template);
+            Grid::new("template")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Template").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Name"));
+                    let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+                        let mut job = highlight(ui, text);
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|fonts| fonts.layout_job(job))
+                    };
+                    ui.add(egui::TextEdit::singleline(&mut self.template).layouter(&mut layouter))
+                        .on_hover_text("Tokens in braces, like {stem[0:5]}, {mtime:%Y%m%d} or {artist|slug}");
+                    ui.end_row();
+
+                    if let Err(err) = &segments {
+                        ui.label("");
+                        ui.label(RichText::new(err.to_string()).color(Color32::RED));
+                        ui.end_row();
+                    }
+
+                    ui.add(Label::new("Regex"));
+                    ui.text_edit_singleline(&mut self.pattern)
+                        .on_hover_text("Matched against the name, its groups are {re.1}, {re.2} or {re.<name>}");
+                    ui.end_row();
+
+                    if !self.pattern.is_empty() {
+                        if let Err(err) = Regex::new(&self.pattern) {
+                            ui.label("");
+                            ui.label(RichText::new(format!("Invalid pattern: {}", err)).color(Color32::RED));
+                            ui.end_row();
+                        }
+                    }
+
+                    ui.add(Label::new("Counter"));
+                    ui.horizontal(|ui| {
+                        ui.label("Start");
+                        ui.add(DragValue::new(&mut self.start));
+                        ui.label("Step");
+                        ui.add(DragValue::new(&mut self.increment));
+                    });
+                    ui.end_row();
+                });
+
+            ui.collapsing("Tokens", |ui| {
+                self.render_tokens(ui, hovered_file, segments.as_deref().ok());
+            });
+
+            if self.enabled {
+                self.status.lock().unwrap().report.render(ui);
+            }
+        });
+    }
+
+    // Lists every token with its value for the file last hovered in the browser.
+    fn render_tokens(&self, ui: &mut Ui, hovered_file: Option<&FileAbsolutePath>, segments: Option<&[Segment]>) {
+        let values = hovered_file.map(|hovered_file| {
+            let status = self.status.lock().unwrap();
+            let path = Path::new(hovered_file);
+            let selected = status.inputs.get(hovered_file);
+            let (input, position) = match selected {
+                Some((input, position)) => (input.clone(), Some((*position, status.total))),
+                None => (path.file_name().unwrap_or_default().to_string_lossy().to_string(), None),
+            };
+            // Only selected files are hashed, hovering should not start reading large files
+            let digests = match (selected, segments) {
+                (Some(_), Some(segments)) => match hashes::digests(path, &hashes::used_by(segments)) {
+                    HashStatus::Ready(digests) => digests,
+                    _ => BTreeMap::new(),
+                },
+                _ => BTreeMap::new(),
+            };
+            let regex = Regex::new(&self.pattern).ok().filter(|_| !self.pattern.is_empty());
+            let file = file_tokens(path).unwrap_or_default();
+            (input.clone(), self.values(&input, path, position, regex.as_ref(), file, digests))
+        });
+
+        match &values {
+            Some((input, _)) => ui.label(RichText::new(format!("Values for {}", input)).weak()),
+            None => ui.label(RichText::new("Hover a file to see its values").weak()),
+        };
+        ScrollArea::vertical()
+            .id_salt("template_tokens")
+            .max_height(240.0)
+            .show(ui, |ui| {
+                Grid::new("template_tokens").num_columns(3).striped(true).show(ui, |ui| {
+                    for (name, description) in TEMPLATE_TOKENS
+                        .iter()
+                        .chain(FILE_TOKENS)
+                        .chain(EXIF_TOKENS)
+                        .chain(AUDIO_TOKENS)
+                        .chain(VIDEO_TOKENS)
+                        .chain(DOCUMENT_TOKENS)
+                        .chain(HASH_TOKENS)
+                    {
+                        ui.monospace(format!("{{{}}}", name));
+                        ui.label(*description);
+                        let value = values
+                            .as_ref()
+                            .and_then(|(_, values)| values.get(name))
+                            .and_then(|value| value.format(None).ok())
+                            .unwrap_or_default();
+                        let mut shown: String = value.chars().take(SHOWN_VALUE_CHARS).collect();
+                        if shown.len() < value.len() {
+                            shown.push('…');
+                        }
+                        ui.label(RichText::new(shown).weak());
+                        ui.end_row();
+                    }
+                    for filter in Filter::ALL {
+                        ui.monospace(format!("|{}", filter.name()));
+                        ui.label(filter.description());
+                        ui.label("");
+                        ui.end_row();
+                    }
+                });
+            });
+        ui.label(
+            RichText::new("A range like {stem[0:5]} takes graphemes, negative bounds count from the end. Formats go after a colon and filters last, like {exif.model[0:3]|upper}")
+                .weak(),
+        );
+    }
+
+    fn values(
+        &self,
+        input: &str,
+        path: &Path,
+        position: Option<(usize, usize)>,
+        regex: Option<&Regex>,
+        file: Arc<Tokens>,
+        digests: BTreeMap<HashAlgorithm, String>,
+    ) -> FileValues {
+        let mut local = Tokens::new();
+        let (stem, ext) = split_extension(input);
+        local.insert("name".to_string(), TokenValue::Text(input.to_string()));
+        local.insert("stem".to_string(), TokenValue::Text(stem.to_string()));
+        local.insert("ext".to_string(), TokenValue::Text(ext.to_string()));
+        let original = path.file_name().unwrap_or_default().to_string_lossy();
+        let (original_stem, _) = split_extension(&original);
+        local.insert("original_stem".to_string(), TokenValue::Text(original_stem.to_string()));
+        local.insert("original".to_string(), TokenValue::Text(original.to_string()));
+        if let Some(parent) = path.parent().and_then(|parent| parent.file_name()) {
+            local.insert("parent".to_string(), TokenValue::Text(parent.to_string_lossy().to_string()));
+        }
+        if let Some((position, total)) = position {
+            let n = self.start.saturating_add(self.increment.saturating_mul(position as i64));
+            local.insert("n".to_string(), TokenValue::Number(n as f64));
+            local.insert("total".to_string(), TokenValue::Number(total as f64));
+        }
+        for (alias, name) in [("mtime", "file.modified"), ("ctime", "file.created")] {
+            if let Some(value) = file.get(name) {
+                local.insert(alias.to_string(), value.clone());
+            }
+        }
+        if let Ok(metadata) = path.metadata() {
+            local.insert("size".to_string(), TokenValue::Number(metadata.len() as f64));
+        }
+        if let Some(captures) = regex.and_then(|regex| regex.captures(input)) {
+            for (index, name) in regex.into_iter().flat_map(Regex::capture_names).enumerate() {
+                // Groups that took no part in the match are empty
+                let text = captures.get(index).map_or("", |capture| capture.as_str());
+                local.insert(format!("re.{}", index), TokenValue::Text(text.to_string()));
+                if let Some(name) = name {
+                    local.insert(format!("re.{}", name), TokenValue::Text(text.to_string()));
+                }
+            }
+        }
+        FileValues { local, file, digests }
+    }
+}
+
+impl Mutation for TemplateMutation {
+    fn mutate(&self, input: &str) -> String {
+        input.to_string()
+    }
+
+    fn mutate_all(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
+        let mut status = self.status.lock().unwrap();
+        *status = TemplateStatus::default();
+        if !self.enabled || self.template.is_empty() {
+            return inputs;
+        }
+        let Ok(segments) = check(&self.template) else {
+            return inputs;
+        };
+        let regex = match self.pattern.as_str() {
+            "" => None,
+            pattern => match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(_) => return inputs,
+            },
+        };
+        let algorithms = hashes::used_by(&segments);
+        let reads_file = uses_file_tokens(&segments);
+        status.total = inputs.len();
+        let total = inputs.len();
+
+        inputs
+            .into_iter()
+            .zip(paths)
+            .enumerate()
+            .map(|(position, (input, absolute_path))| {
+                status.inputs.insert(absolute_path.clone(), (input.clone(), position));
+                let path = Path::new(absolute_path);
+                // Asked for before the hashes, so both are read meanwhile
+                let file = if reads_file { file_tokens(path) } else { Some(Arc::default()) };
+                let digests = if algorithms.is_empty() {
+                    BTreeMap::new()
+                } else {
+                    match hashes::digests(path, &algorithms) {
+                        HashStatus::Ready(digests) => digests,
+                        HashStatus::Pending => {
+                            status.report.pending += 1;
+                            return input;
+                        }
+                        HashStatus::Failed(err) => {
+                            status.report.fail(path, err);
+                            return input;
+                        }
+                    }
+                };
+                let Some(file) = file else {
+                    status.report.pending += 1;
+                    return input;
+                };
+                let values = self.values(&input, path, Some((position, total)), regex.as_ref(), file, digests);
+                match expand(&segments, |name| values.get(name)) {
+                    Ok(renamed) if !renamed.is_empty() => renamed,
+                    Ok(_) => {
+                        status.report.fail(path, "The new name is empty");
+                        input
+                    }
+                    Err(err) => {
+                        status.report.fail(path, err);
+                        input
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(template: &str) -> TemplateMutation {
+        TemplateMutation {
+            enabled: true,
+            template: template.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn rename(mutation: &TemplateMutation, inputs: &[&str], paths: &[&str]) -> Vec<String> {
+        let inputs = inputs.iter().map(|input| input.to_string()).collect();
+        let paths: Vec<FileAbsolutePath> = paths.iter().map(|path| path.to_string()).collect();
+        mutation.mutate_all(inputs, &paths)
+    }
+
+    #[test]
+    fn names_and_counter() {
+        let mutation = template("{parent}_{n:02}_of_{total}{ext}");
+        let renamed = rename(&mutation, &["a.jpg", "b.png"], &["/photos/trip/a.jpg", "/photos/trip/b.png"]);
+        assert_eq!(renamed, vec!["trip_01_of_2.jpg", "trip_02_of_2.png"]);
+    }
+
+    #[test]
+    fn counter_saturates() {
+        let mutation = TemplateMutation {
+            start: 1,
+            increment: i64::MAX,
+            ..template("{n}")
+        };
+        let renamed = rename(&mutation, &["a", "b", "c"], &["/a", "/b", "/c"]);
+        assert_eq!(renamed, vec!["1".to_string(), i64::MAX.to_string(), i64::MAX.to_string()]);
+        let mutation = TemplateMutation {
+            start: i64::MIN,
+            increment: i64::MIN,
+            ..template("{n}")
+        };
+        assert_eq!(rename(&mutation, &["a", "b"], &["/a", "/b"])[1], i64::MIN.to_string());
+    }
+
+    #[test]
+    fn stem_is_the_current_name() {
+        // An earlier stage already renamed IMG_1.jpg to beach.jpg
+        let mutation = template("{stem}_{original_stem}_{original}");
+        let renamed = rename(&mutation, &["beach.jpg"], &["/photos/IMG_1.jpg"]);
+        assert_eq!(renamed, vec!["beach_IMG_1_IMG_1.jpg"]);
+    }
+
+    #[test]
+    fn regex_groups() {
+        let mutation = TemplateMutation {
+            pattern: r"(?<year>\d{4})-(\d+)".to_string(),
+            ..template("{re.2}_{re.year}{ext}")
+        };
+        let renamed = rename(&mutation, &["2024-05.jpg", "none.jpg"], &["/2024-05.jpg", "/none.jpg"]);
+        assert_eq!(renamed[0], "05_2024.jpg");
+        // Without a match the groups have no value and the file keeps its name
+        assert_eq!(renamed[1], "none.jpg");
+        assert_eq!(mutation.status.lock().unwrap().report.errors, vec!["none.jpg: No value for {re.2}"]);
+    }
+
+    #[test]
+    fn empty_result_keeps_the_name() {
+        let mutation = template("{stem[5:]}");
+        assert_eq!(rename(&mutation, &["abc"], &["/abc"]), vec!["abc"]);
+        assert_eq!(mutation.status.lock().unwrap().report.errors, vec!["abc: The new name is empty"]);
+    }
+
+    #[test]
+    fn unknown_tokens() {
+        assert_eq!(
+            check("{stem}_{exif.modle}"),
+            Err(TemplateError::new("Unknown token {exif.modle}", 7..19))
+        );
+        assert!(check("{re.anything}_{sha256:8}_{original}").is_ok());
+        let mutation = template("{nope}");
+        assert_eq!(rename(&mutation, &["a"], &["/a"]), vec!["a"]);
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = TemplateMutation {
+            enabled: false,
+            ..template("{n}")
+        };
+        assert_eq!(rename(&mutation, &["a"], &["/a"]), vec!["a"]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::utilities::mutation_pipeline::Mutation;
+use crate::utilities::transliteration::{
+    transliterate, CyrillicScheme, GreekScheme, HangulScheme, KanaScheme, LatinScheme, Options,
+    PinyinStyle,
+};
+use egui::{ComboBox, Grid, Label, RichText, Ui};
+use std::fmt::Display;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TransliterateMutation {
+    pub enabled: bool,
+    pub cyrillic: CyrillicScheme,
+    pub greek: GreekScheme,
+    pub chinese: PinyinStyle,
+    pub kana: KanaScheme,
+    pub hangul: HangulScheme,
+    pub latin: LatinScheme,
+    pub syllable_separator: String,
+    pub approximate: bool,
+    // Stands in for characters no scheme could spell
+    pub replacement: String,
+}
+
+impl Default for TransliterateMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cyrillic: CyrillicScheme::Iso9,
+            greek: GreekScheme::Elot743,
+            chinese: PinyinStyle::Plain,
+            kana: KanaScheme::Hepburn,
+            hangul: HangulScheme::Revised,
+            latin: LatinScheme::StripMarks,
+            syllable_separator: " ".to_string(),
+            approximate: true,
+            replacement: "_".to_string(),
+        }
+    }
+}
+
+// A combo box row choosing one of the schemes of a script
+fn scheme_row<T: PartialEq + Copy + Display>(ui: &mut Ui, label: &str, value: &mut T, schemes: &[T]) {
+    ui.add(Label::new(label));
+    ComboBox::from_id_salt(("transliterate", label))
+        .selected_text(value.to_string())
+        .show_ui(ui, |ui| {
+            for scheme in schemes {
+                ui.selectable_value(value, *scheme, scheme.to_string());
+            }
+        });
+    ui.end_row();
+}
+
+impl TransliterateMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("transliterate")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Transliterate").strong());
+                    ui.end_row();
+
+                    scheme_row(
+                        ui,
+                        "Cyrillic",
+                        &mut self.cyrillic,
+                        &[CyrillicScheme::Keep, CyrillicScheme::Iso9, CyrillicScheme::Bgn, CyrillicScheme::Icao],
+                    );
+                    scheme_row(
+                        ui,
+                        "Greek",
+                        &mut self.greek,
+                        &[GreekScheme::Keep, GreekScheme::Elot743, GreekScheme::Classical],
+                    );
+                    scheme_row(
+                        ui,
+                        "Chinese",
+                        &mut self.chinese,
+                        &[PinyinStyle::Keep, PinyinStyle::Plain, PinyinStyle::ToneNumbers, PinyinStyle::ToneMarks],
+                    );
+                    if self.chinese != PinyinStyle::Keep {
+                        ui.add(Label::new("Syllable separator"));
+                        ui.text_edit_singleline(&mut self.syllable_separator);
+                        ui.end_row();
+                    }
+                    scheme_row(ui, "Japanese kana", &mut self.kana, &[KanaScheme::Keep, KanaScheme::Hepburn]);
+                    scheme_row(ui, "Korean", &mut self.hangul, &[HangulScheme::Keep, HangulScheme::Revised]);
+                    scheme_row(
+                        ui,
+                        "Accented Latin",
+                        &mut self.latin,
+                        &[LatinScheme::Keep, LatinScheme::StripMarks, LatinScheme::Telex, LatinScheme::Vni],
+                    );
+
+                    ui.checkbox(&mut self.approximate, "Approximate other scripts")
+                        .on_hover_text("Spell other scripts and symbols as close as ASCII allows");
+                    ui.end_row();
+
+                    ui.add(Label::new("Replace the rest with"));
+                    ui.text_edit_singleline(&mut self.replacement)
+                        .on_hover_text("Used for characters no scheme can spell, leave empty to drop them");
+                    ui.end_row();
+                });
+        });
+    }
+}
+
+impl Mutation for TransliterateMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled {
+            return input.to_string();
+        }
+        transliterate(
+            input,
+            Options {
+                cyrillic: self.cyrillic,
+                greek: self.greek,
+                pinyin: self.chinese,
+                kana: self.kana,
+                hangul: self.hangul,
+                latin: self.latin,
+                syllable_separator: &self.syllable_separator,
+                approximate: self.approximate,
+                replacement: &self.replacement,
+            },
+        )
+    }
+}
@@ -0,0 +1,330 @@
+use crate::utilities::file_name::{name_length, split_extension, LengthUnit};
+use crate::utilities::mutation_pipeline::Mutation;
+use egui::{ComboBox, DragValue, Grid, Label, RichText, Ui};
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TruncateMutation {
+    pub enabled: bool,
+    pub max_length: usize,
+    pub unit: LengthUnit,
+    pub style: TruncateStyle,
+    pub ellipsis: String,
+    pub keep_extension: bool,
+    // Counters like "_003" or " (2)" at the end of the name survive the cut
+    pub keep_counter: bool,
+    // Marks new names over the limit in the file browser, even with the stage off
+    pub flag_long_names: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum TruncateStyle {
+    Cut,
+    #[default]
+    Ellipsis,
+    // Keeps the start and the end, with the ellipsis in between
+    HeadTail,
+}
+
+impl fmt::Display for TruncateStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruncateStyle::Cut => write!(f, "Cut"),
+            TruncateStyle::Ellipsis => write!(f, "Cut with ellipsis"),
+            TruncateStyle::HeadTail => write!(f, "Keep start and end"),
+        }
+    }
+}
+
+impl Default for TruncateMutation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_length: 255,
+            unit: LengthUnit::Bytes,
+            style: TruncateStyle::Ellipsis,
+            ellipsis: "…".to_string(),
+            keep_extension: true,
+            keep_counter: true,
+            flag_long_names: true,
+        }
+    }
+}
+
+fn counter_regex() -> &'static Regex {
+    static COUNTER: OnceLock<Regex> = OnceLock::new();
+    COUNTER.get_or_init(|| Regex::new(r"(?:[ _.-]*\(\d+\)|[ _.-]+\d+)$").unwrap())
+}
+
+// Number of graphemes from the start that fit in the budget
+fn fitting_head(graphemes: &[&str], budget: usize, unit: LengthUnit) -> usize {
+    let mut used = 0;
+    graphemes
+        .iter()
+        .take_while(|grapheme| {
+            used += name_length(grapheme, unit);
+            used <= budget
+        })
+        .count()
+}
+
+impl TruncateMutation {
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            Grid::new("truncate")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut self.enabled, RichText::new("Truncate").strong());
+                    ui.end_row();
+
+                    ui.add(Label::new("Limit"));
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut self.max_length).range(1..=4096));
+                        ComboBox::from_id_salt("truncate_unit")
+                            .selected_text(self.unit.to_string())
+                            .show_ui(ui, |ui| {
+                                for unit in [LengthUnit::Bytes, LengthUnit::Graphemes] {
+                                    let text = unit.to_string();
+                                    ui.selectable_value(&mut self.unit, unit, text);
+                                }
+                            });
+                    })
+                    .response
+                    .on_hover_text("ext4 allows 255 bytes, archive formats often 100 characters");
+                    ui.end_row();
+
+                    ui.add(Label::new("Style"));
+                    ComboBox::from_id_salt("truncate_style")
+                        .selected_text(self.style.to_string())
+                        .show_ui(ui, |ui| {
+                            for style in [TruncateStyle::Cut, TruncateStyle::Ellipsis, TruncateStyle::HeadTail] {
+                                let text = style.to_string();
+                                ui.selectable_value(&mut self.style, style, text);
+                            }
+                        });
+                    ui.end_row();
+
+                    if self.style != TruncateStyle::Cut {
+                        ui.add(Label::new("Ellipsis"));
+                        ui.text_edit_singleline(&mut self.ellipsis);
+                        ui.end_row();
+                    }
+
+                    ui.checkbox(&mut self.keep_extension, "Keep extension");
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.keep_counter, "Keep trailing counter")
+                        .on_hover_text("Numbers like \"_003\" or \" (2)\" at the end of the name");
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.flag_long_names, "Flag long names")
+                        .on_hover_text("Mark new names over the limit in the file list");
+                    ui.end_row();
+                });
+        });
+    }
+
+    // The limit new names are checked against in the file browser
+    pub fn preview_limit(&self) -> Option<(LengthUnit, usize)> {
+        self.flag_long_names.then_some((self.unit, self.max_length))
+    }
+
+    fn shorten(&self, text: &str, budget: usize) -> String {
+        let graphemes: Vec<&str> = text.graphemes(true).collect();
+        let ellipsis_length = name_length(&self.ellipsis, self.unit);
+        let style = match self.style {
+            // Without room for the ellipsis a plain cut is the best that fits
+            _ if ellipsis_length > budget => TruncateStyle::Cut,
+            style => style,
+        };
+        match style {
+            TruncateStyle::Cut => graphemes[..fitting_head(&graphemes, budget, self.unit)].concat().trim_end().to_string(),
+            TruncateStyle::Ellipsis => {
+                let head = graphemes[..fitting_head(&graphemes, budget - ellipsis_length, self.unit)].concat();
+                format!("{}{}", head.trim_end(), self.ellipsis)
+            }
+            TruncateStyle::HeadTail => {
+                let budget = budget - ellipsis_length;
+                let reversed: Vec<&str> = graphemes.iter().rev().copied().collect();
+                let tail_count = fitting_head(&reversed, budget / 2, self.unit);
+                let tail = graphemes[graphemes.len() - tail_count..].concat();
+                let head_budget = budget - name_length(&tail, self.unit);
+                let head = graphemes[..fitting_head(&graphemes, head_budget, self.unit)].concat();
+                format!("{}{}{}", head.trim_end(), self.ellipsis, tail.trim_start())
+            }
+        }
+    }
+}
+
+impl Mutation for TruncateMutation {
+    fn mutate(&self, input: &str) -> String {
+        if !self.enabled || name_length(input, self.unit) <= self.max_length {
+            return input.to_string();
+        }
+        let (stem, extension) = if self.keep_extension {
+            split_extension(input)
+        } else {
+            (input, "")
+        };
+        let (stem, counter) = match counter_regex().find(stem) {
+            Some(counter) if self.keep_counter => (&stem[..counter.start()], counter.as_str()),
+            _ => (stem, ""),
+        };
+        let kept = name_length(extension, self.unit) + name_length(counter, self.unit);
+        if kept >= self.max_length {
+            // Not even the kept parts fit, the whole name is cut instead
+            return TruncateMutation {
+                keep_extension: false,
+                keep_counter: false,
+                ..self.clone()
+            }
+            .shorten(input, self.max_length);
+        }
+        format!("{}{}{}", self.shorten(stem, self.max_length - kept), counter, extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn truncate(max_length: usize, style: TruncateStyle) -> TruncateMutation {
+        TruncateMutation {
+            enabled: true,
+            max_length,
+            style,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn short_names_are_kept() {
+        let mutation = truncate(14, TruncateStyle::Cut);
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abcdefghij.txt");
+    }
+
+    #[test]
+    fn cut_keeps_the_extension() {
+        let mutation = truncate(8, TruncateStyle::Cut);
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abcd.txt");
+        let mutation = TruncateMutation { keep_extension: false, ..mutation };
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abcdefgh");
+    }
+
+    #[test]
+    fn ellipsis_counts_in_bytes() {
+        // The ellipsis takes 3 of the 10 bytes
+        let mutation = truncate(10, TruncateStyle::Ellipsis);
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abc….txt");
+    }
+
+    #[test]
+    fn ellipsis_counts_in_graphemes() {
+        let mutation = TruncateMutation {
+            unit: LengthUnit::Graphemes,
+            ..truncate(10, TruncateStyle::Ellipsis)
+        };
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abcde….txt");
+        assert_eq!(mutation.mutate("ééééééééé.txt"), "ééééé….txt");
+    }
+
+    #[test]
+    fn multi_byte_graphemes_stay_whole() {
+        // Two bytes each, the third one would go over by one byte
+        let mutation = truncate(9, TruncateStyle::Cut);
+        assert_eq!(mutation.mutate("ééééé.txt"), "éé.txt");
+        // Three bytes each, decomposed
+        let mutation = truncate(10, TruncateStyle::Cut);
+        assert_eq!(mutation.mutate("e\u{301}e\u{301}e\u{301}e\u{301}.txt"), "e\u{301}e\u{301}.txt");
+        // One grapheme of 18 bytes does not fit in 10
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(mutation.mutate(&format!("{}{}.txt", family, family)), ".txt");
+    }
+
+    #[test]
+    fn one_grapheme_of_many_bytes() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let mutation = TruncateMutation {
+            unit: LengthUnit::Graphemes,
+            ..truncate(6, TruncateStyle::Cut)
+        };
+        let name = format!("{}{}{}.txt", family, family, family);
+        assert_eq!(mutation.mutate(&name), format!("{}{}.txt", family, family));
+    }
+
+    #[test]
+    fn head_and_tail() {
+        let mutation = TruncateMutation {
+            keep_extension: false,
+            keep_counter: false,
+            ..truncate(11, TruncateStyle::HeadTail)
+        };
+        assert_eq!(mutation.mutate("abcdefghijklmnop"), "abcd…mnop");
+        // The head gets the byte the tail cannot use
+        let mutation = TruncateMutation { max_length: 12, ..mutation };
+        assert_eq!(mutation.mutate("abcdefghijklmnop"), "abcde…mnop");
+        let mutation = TruncateMutation { max_length: 10, ..mutation };
+        assert_eq!(mutation.mutate("abcdefghijklmnop"), "abcd…nop");
+    }
+
+    #[test]
+    fn head_and_tail_trim_spaces_at_the_ellipsis() {
+        let mutation = TruncateMutation {
+            keep_extension: false,
+            keep_counter: false,
+            ..truncate(11, TruncateStyle::HeadTail)
+        };
+        assert_eq!(mutation.mutate("abc defghijkl mno"), "abc…mno");
+    }
+
+    #[test]
+    fn spaces_before_the_ellipsis_are_trimmed() {
+        let mutation = truncate(11, TruncateStyle::Ellipsis);
+        assert_eq!(mutation.mutate("abc defghij.txt"), "abc….txt");
+    }
+
+    #[test]
+    fn counters_survive() {
+        let mutation = truncate(20, TruncateStyle::Ellipsis);
+        assert_eq!(mutation.mutate("a very long holiday name_003.jpg"), "a very lo…_003.jpg");
+        assert_eq!(mutation.mutate("a very long holiday name (2).jpg"), "a very lo… (2).jpg");
+        let mutation = TruncateMutation { keep_counter: false, ..mutation };
+        assert_eq!(mutation.mutate("a very long holiday name_003.jpg"), "a very long h….jpg");
+    }
+
+    #[test]
+    fn whole_name_is_cut_when_the_kept_parts_do_not_fit() {
+        // ".tiff" and "_001" take 9 of the 6 bytes
+        let mutation = truncate(6, TruncateStyle::Ellipsis);
+        assert_eq!(mutation.mutate("abcdefgh_001.tiff"), "abc…");
+        let mutation = truncate(9, TruncateStyle::Cut);
+        assert_eq!(mutation.mutate("abcdefgh_001.tiff"), "abcdefgh_");
+    }
+
+    #[test]
+    fn ellipsis_wider_than_the_room_left() {
+        let mutation = TruncateMutation {
+            ellipsis: "[...]".to_string(),
+            ..truncate(7, TruncateStyle::Ellipsis)
+        };
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abc.txt");
+        let mutation = TruncateMutation { style: TruncateStyle::HeadTail, ..mutation };
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abc.txt");
+    }
+
+    #[test]
+    fn disabled() {
+        let mutation = TruncateMutation { enabled: false, ..truncate(4, TruncateStyle::Cut) };
+        assert_eq!(mutation.mutate("abcdefghij.txt"), "abcdefghij.txt");
+        assert_eq!(mutation.preview_limit(), Some((LengthUnit::Bytes, 4)));
+        let mutation = TruncateMutation { flag_long_names: false, ..mutation };
+        assert_eq!(mutation.preview_limit(), None);
+    }
+}
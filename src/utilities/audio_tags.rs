@@ -0,0 +1,642 @@
+use crate::utilities::bmff;
+use crate::utilities::content_type::{ContentType, FLAC, M4A, MP3, MP4, OGG};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Tags are read whole, cover art included, so very large tags are skipped rather than loaded
+const TAG_LIMIT: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AudioTags {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub title: Option<String>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+}
+
+pub fn read(path: &Path, content_type: ContentType) -> Option<AudioTags> {
+    let mut file = File::open(path).ok()?;
+    let mut tags = AudioTags::default();
+    if content_type == MP3 {
+        id3v2(&mut file, &mut tags);
+        id3v1(&mut file, &mut tags);
+    } else if content_type == FLAC {
+        flac(&mut file, &mut tags);
+    } else if content_type == OGG {
+        ogg(&mut file, &mut tags);
+    } else if content_type == M4A || content_type == MP4 {
+        mp4(&mut file, &mut tags);
+    }
+    Some(tags).filter(|tags| *tags != AudioTags::default())
+}
+
+impl AudioTags {
+    // Earlier sources win, so ID3v2 values are not replaced by the truncated ID3v1 ones
+    fn set_text(field: &mut Option<String>, value: &str) {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if field.is_none() && !value.is_empty() {
+            *field = Some(value.to_string());
+        }
+    }
+
+    fn set_number(field: &mut Option<u32>, value: Option<u32>) {
+        if field.is_none() {
+            *field = value.filter(|value| *value > 0);
+        }
+    }
+
+    // Track and disc numbers are written as "3" or "3/12"
+    fn set_position(number: &mut Option<u32>, total: &mut Option<u32>, value: &str) {
+        let (position, of) = match value.split_once('/') {
+            Some((position, of)) => (position, Some(of)),
+            None => (value, None),
+        };
+        Self::set_number(number, position.trim().parse().ok());
+        Self::set_number(total, of.and_then(|of| of.trim().parse().ok()));
+    }
+
+    fn set_year(&mut self, value: &str) {
+        let year = value.trim().get(..4).and_then(|year| year.parse().ok());
+        Self::set_number(&mut self.year, year);
+    }
+
+    // ID3 genres can be a number into the ID3v1 genre list, written as "17" or "(17)"
+    fn set_genre(&mut self, value: &str) {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        let index = value.trim_start_matches('(').split(')').next().unwrap_or("");
+        match index.parse::<usize>().ok().and_then(|index| GENRES.get(index)) {
+            Some(genre) => Self::set_text(&mut self.genre, genre),
+            None => Self::set_text(&mut self.genre, value),
+        }
+    }
+
+    fn set_vorbis_comment(&mut self, comment: &str) {
+        let Some((key, value)) = comment.split_once('=') else {
+            return;
+        };
+        match key.to_uppercase().as_str() {
+            "ARTIST" => Self::set_text(&mut self.artist, value),
+            "ALBUM" => Self::set_text(&mut self.album, value),
+            "ALBUMARTIST" | "ALBUM ARTIST" | "ALBUM_ARTIST" => {
+                Self::set_text(&mut self.album_artist, value)
+            }
+            "TRACKNUMBER" => Self::set_position(&mut self.track, &mut self.track_total, value),
+            "TRACKTOTAL" | "TOTALTRACKS" => {
+                Self::set_number(&mut self.track_total, value.trim().parse().ok())
+            }
+            "DISCNUMBER" => Self::set_position(&mut self.disc, &mut self.disc_total, value),
+            "DISCTOTAL" | "TOTALDISCS" => {
+                Self::set_number(&mut self.disc_total, value.trim().parse().ok())
+            }
+            "TITLE" => Self::set_text(&mut self.title, value),
+            "DATE" | "YEAR" => self.set_year(value),
+            "GENRE" => self.set_genre(value),
+            _ => {}
+        }
+    }
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut data = Vec::new();
+    file.take(len).read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn syncsafe(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(bytes.iter().fold(0, |value, byte| value << 7 | u64::from(byte & 0x7f)))
+}
+
+// Unsynchronisation inserts a zero after every 0xff byte
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut resynchronised = Vec::with_capacity(data.len());
+    for (index, byte) in data.iter().enumerate() {
+        if !(*byte == 0 && index > 0 && data[index - 1] == 0xff) {
+            resynchronised.push(*byte);
+        }
+    }
+    resynchronised
+}
+
+fn id3_text(frame: &[u8]) -> Option<String> {
+    let (encoding, text) = frame.split_first()?;
+    let text = match encoding {
+        0 => text.iter().map(|byte| char::from(*byte)).collect(),
+        1 | 2 => {
+            let big_endian = match text.get(..2) {
+                Some([0xfe, 0xff]) => true,
+                Some([0xff, 0xfe]) => false,
+                _ => *encoding == 2,
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|unit| match big_endian {
+                    true => u16::from_be_bytes([unit[0], unit[1]]),
+                    false => u16::from_le_bytes([unit[0], unit[1]]),
+                })
+                .filter(|unit| *unit != 0xfeff)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).to_string(),
+    };
+    // Version 2.4 separates multiple values with nul, only the first one is kept
+    text.split('\0').next().map(str::to_string)
+}
+
+fn id3v2(file: &mut File, tags: &mut AudioTags) -> Option<()> {
+    let header = read_at(file, 0, 10)?;
+    if header.len() < 10 || !header.starts_with(b"ID3") {
+        return None;
+    }
+    let version = header[3];
+    let flags = header[5];
+    let size = syncsafe(&header, 6)?;
+    if size > TAG_LIMIT {
+        return None;
+    }
+    let mut data = read_at(file, 10, size)?;
+    if flags & 0x80 != 0 && version < 4 {
+        data = resynchronise(&data);
+    }
+
+    let mut offset = 0;
+    if flags & 0x40 != 0 && version >= 3 {
+        // Extended header, its size field counts itself only in version 2.4
+        offset = match version {
+            3 => bmff::uint(&data, 0, 4)? as usize + 4,
+            _ => syncsafe(&data, 0)? as usize,
+        };
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    while offset + header_len <= data.len() {
+        let id = &data[offset..offset + id_len];
+        if id[0] == 0 {
+            // Padding
+            break;
+        }
+        let frame_size = match version {
+            2 => bmff::uint(&data, offset + 3, 3)?,
+            3 => bmff::uint(&data, offset + 4, 4)?,
+            _ => syncsafe(&data, offset + 4)?,
+        } as usize;
+        let frame_flags = if version == 2 { 0 } else { data[offset + 9] };
+        let Some(frame) = data.get(offset + header_len..offset + header_len + frame_size) else {
+            break;
+        };
+        offset += header_len + frame_size;
+
+        // Compressed or encrypted frames are skipped
+        let (skipped, unsynchronised, length_prefixed) = match version {
+            3 => (frame_flags & 0xc0 != 0, false, false),
+            4 => (frame_flags & 0x0c != 0, frame_flags & 0x02 != 0, frame_flags & 0x01 != 0),
+            _ => (false, false, false),
+        };
+        if skipped {
+            continue;
+        }
+        let mut frame = frame.to_vec();
+        if unsynchronised {
+            frame = resynchronise(&frame);
+        }
+        if length_prefixed {
+            frame.drain(..4.min(frame.len()));
+        }
+        let Some(text) = id3_text(&frame) else {
+            continue;
+        };
+        match id {
+            b"TPE1" | b"TP1" => AudioTags::set_text(&mut tags.artist, &text),
+            b"TALB" | b"TAL" => AudioTags::set_text(&mut tags.album, &text),
+            b"TPE2" | b"TP2" => AudioTags::set_text(&mut tags.album_artist, &text),
+            b"TRCK" | b"TRK" => AudioTags::set_position(&mut tags.track, &mut tags.track_total, &text),
+            b"TPOS" | b"TPA" => AudioTags::set_position(&mut tags.disc, &mut tags.disc_total, &text),
+            b"TIT2" | b"TT2" => AudioTags::set_text(&mut tags.title, &text),
+            b"TYER" | b"TYE" | b"TDRC" | b"TDOR" => tags.set_year(&text),
+            b"TCON" | b"TCO" => tags.set_genre(&text),
+            _ => {}
+        }
+    }
+    Some(())
+}
+
+// The fixed 128 byte block at the end of the file, older than ID3v2 and often both are present
+fn id3v1(file: &mut File, tags: &mut AudioTags) -> Option<()> {
+    let len = file.metadata().ok()?.len();
+    let tag = read_at(file, len.checked_sub(128)?, 128)?;
+    if !tag.starts_with(b"TAG") {
+        return None;
+    }
+    let text = |range: std::ops::Range<usize>| -> String {
+        tag[range].iter().map(|byte| char::from(*byte)).collect()
+    };
+    AudioTags::set_text(&mut tags.title, &text(3..33));
+    AudioTags::set_text(&mut tags.artist, &text(33..63));
+    AudioTags::set_text(&mut tags.album, &text(63..93));
+    tags.set_year(&text(93..97));
+    // Version 1.1 stores the track in the last byte of the comment
+    if tag[125] == 0 {
+        AudioTags::set_number(&mut tags.track, Some(u32::from(tag[126])));
+    }
+    if let Some(genre) = GENRES.get(usize::from(tag[127])) {
+        AudioTags::set_text(&mut tags.genre, genre);
+    }
+    Some(())
+}
+
+// Vorbis comments are little endian, unlike the rest of FLAC and Ogg framing
+fn vorbis_comments(data: &[u8], tags: &mut AudioTags) -> Option<()> {
+    let u32_at = |offset: usize| -> Option<usize> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let mut offset = 4 + u32_at(0)?;
+    let count = u32_at(offset)?;
+    offset += 4;
+    for _ in 0..count {
+        let len = u32_at(offset)?;
+        let comment = data.get(offset + 4..offset + 4 + len)?;
+        tags.set_vorbis_comment(&String::from_utf8_lossy(comment));
+        offset += 4 + len;
+    }
+    Some(())
+}
+
+fn flac(file: &mut File, tags: &mut AudioTags) -> Option<()> {
+    let mut offset = 4;
+    loop {
+        let header = read_at(file, offset, 4)?;
+        let last = header.first()? & 0x80 != 0;
+        let block_type = header[0] & 0x7f;
+        let len = bmff::uint(&header, 1, 3)?;
+        if block_type == 4 {
+            return vorbis_comments(&read_at(file, offset + 4, len)?, tags);
+        }
+        if last {
+            return None;
+        }
+        offset += 4 + len;
+    }
+}
+
+// The comment header is the second packet of the first logical stream
+fn ogg(file: &mut File, tags: &mut AudioTags) -> Option<()> {
+    let data = read_at(file, 0, TAG_LIMIT)?;
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut offset = 0;
+    let mut serial = None;
+    while packets.len() < 3 && data.get(offset..offset + 4) == Some(b"OggS") {
+        let page_serial = data.get(offset + 14..offset + 18)?;
+        let segment_count = usize::from(*data.get(offset + 26)?);
+        let lacing = data.get(offset + 27..offset + 27 + segment_count)?;
+        let mut body = offset + 27 + segment_count;
+        offset = body + lacing.iter().map(|len| usize::from(*len)).sum::<usize>();
+        if *serial.get_or_insert(page_serial) != page_serial {
+            continue;
+        }
+        for len in lacing {
+            let segment = data.get(body..body + usize::from(*len))?;
+            packets.last_mut()?.extend_from_slice(segment);
+            body += usize::from(*len);
+            // A segment shorter than 255 bytes ends the packet
+            if *len < 255 {
+                packets.push(Vec::new());
+            }
+        }
+    }
+
+    let comments = packets.get(1)?;
+    if let Some(comments) = comments.strip_prefix(b"\x03vorbis") {
+        vorbis_comments(comments, tags)
+    } else if let Some(comments) = comments.strip_prefix(b"OpusTags") {
+        vorbis_comments(comments, tags)
+    } else {
+        None
+    }
+}
+
+// iTunes style tags live in `moov/udta/meta/ilst`, every item wraps its value in a `data` box
+fn mp4(file: &mut File, tags: &mut AudioTags) -> Option<()> {
+    let movie = bmff::read_top_level(file, b"moov", TAG_LIMIT)?;
+    let meta = bmff::find(bmff::find(&movie, b"udta")?, b"meta")?;
+    // `meta` is a full box in MP4 files but a plain one in QuickTime files
+    let meta = if meta.get(4..8) == Some(b"hdlr") { meta } else { meta.get(4..)? };
+    for (item, body) in bmff::boxes(bmff::find(meta, b"ilst")?) {
+        let Some(value) = bmff::find(body, b"data").and_then(|data| data.get(8..)) else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(value);
+        let pair = |index: usize| bmff::uint(value, index, 2).map(|number| number as u32);
+        match item {
+            b"\xa9ART" => AudioTags::set_text(&mut tags.artist, &text),
+            b"\xa9alb" => AudioTags::set_text(&mut tags.album, &text),
+            b"aART" => AudioTags::set_text(&mut tags.album_artist, &text),
+            b"trkn" => {
+                AudioTags::set_number(&mut tags.track, pair(2));
+                AudioTags::set_number(&mut tags.track_total, pair(4));
+            }
+            b"disk" => {
+                AudioTags::set_number(&mut tags.disc, pair(2));
+                AudioTags::set_number(&mut tags.disc_total, pair(4));
+            }
+            b"\xa9nam" => AudioTags::set_text(&mut tags.title, &text),
+            b"\xa9day" => tags.set_year(&text),
+            b"\xa9gen" => AudioTags::set_text(&mut tags.genre, &text),
+            // Numeric genre, one past the ID3v1 index
+            b"gnre" => {
+                let index = pair(0).and_then(|index| index.checked_sub(1));
+                if let Some(genre) = index.and_then(|index| GENRES.get(index as usize)) {
+                    AudioTags::set_text(&mut tags.genre, genre);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(())
+}
+
+const GENRES: &[&str] = &[
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop", "Jazz",
+    "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap", "Reggae", "Rock", "Techno",
+    "Industrial", "Alternative", "Ska", "Death Metal", "Pranks", "Soundtrack", "Euro-Techno",
+    "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance", "Classical", "Instrumental",
+    "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise", "AlternRock", "Bass", "Soul", "Punk",
+    "Space", "Meditative", "Instrumental Pop", "Instrumental Rock", "Ethnic", "Gothic", "Darkwave",
+    "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream", "Southern Rock", "Comedy",
+    "Cult", "Gangsta", "Top 40", "Christian Rap", "Pop/Funk", "Jungle", "Native American",
+    "Cabaret", "New Wave", "Psychadelic", "Rave", "Showtunes", "Trailer", "Lo-Fi", "Tribal",
+    "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock",
+    // Winamp extensions
+    "Folk", "Folk-Rock", "National Folk", "Swing", "Fast Fusion", "Bebop", "Latin", "Revival",
+    "Celtic", "Bluegrass", "Avantgarde", "Gothic Rock", "Progressive Rock", "Psychedelic Rock",
+    "Symphonic Rock", "Slow Rock", "Big Band", "Chorus", "Easy Listening", "Acoustic", "Humour",
+    "Speech", "Chanson", "Opera", "Chamber Music", "Sonata", "Symphony", "Booty Bass", "Primus",
+    "Porn Groove", "Satire", "Slow Jam", "Club", "Tango", "Samba", "Folklore", "Ballad",
+    "Power Ballad", "Rhythmic Soul", "Freestyle", "Duet", "Punk Rock", "Drum Solo", "A capella",
+    "Euro-House", "Dance Hall", "Goa", "Drum & Bass", "Club-House", "Hardcore Techno", "Terror",
+    "Indie", "BritPop", "Afro-Punk", "Polsk Punk", "Beat", "Christian Gangsta Rap", "Heavy Metal",
+    "Black Metal", "Crossover", "Contemporary Christian", "Christian Rock", "Merengue", "Salsa",
+    "Thrash Metal", "Anime", "Jpop", "Synthpop", "Abstract", "Art Rock", "Baroque", "Bhangra",
+    "Big Beat", "Breakbeat", "Chillout", "Downtempo", "Dub", "EBM", "Eclectic", "Electro",
+    "Electroclash", "Emo", "Experimental", "Garage", "Global", "IDM", "Illbient", "Industro-Goth",
+    "Jam Band", "Krautrock", "Leftfield", "Lounge", "Math Rock", "New Romantic", "Nu-Breakz",
+    "Post-Punk", "Post-Rock", "Psytrance", "Shoegaze", "Space Rock", "Trop Rock", "World Music",
+    "Neoclassical", "Audiobook", "Audio Theatre", "Neue Deutsche Welle", "Podcast", "Indie Rock",
+    "G-Funk", "Dubstep", "Garage Rock", "Psybient",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_file(name: &str, data: &[u8]) -> File {
+        let path = std::env::temp_dir().join(format!("bulkrename_audio_tags_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let file = File::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        file
+    }
+
+    fn syncsafe_bytes(size: usize) -> [u8; 4] {
+        [(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]
+    }
+
+    fn id3v2_tag(version: u8, flags: u8, frames: &[u8]) -> Vec<u8> {
+        [b"ID3".to_vec(), vec![version, 0, flags], syncsafe_bytes(frames.len()).to_vec(), frames.to_vec()].concat()
+    }
+
+    // A version 2.3 frame, the size is a plain big endian number
+    fn frame_v3(id: &[u8], body: &[u8]) -> Vec<u8> {
+        [id.to_vec(), (body.len() as u32).to_be_bytes().to_vec(), vec![0, 0], body.to_vec()].concat()
+    }
+
+    fn frame_v4(id: &[u8], flags: u8, body: &[u8]) -> Vec<u8> {
+        [id.to_vec(), syncsafe_bytes(body.len()).to_vec(), vec![0, flags], body.to_vec()].concat()
+    }
+
+    fn read_id3v2(name: &str, data: &[u8]) -> AudioTags {
+        let mut tags = AudioTags::default();
+        id3v2(&mut temp_file(name, data), &mut tags);
+        tags
+    }
+
+    fn vorbis_block(comments: &[&str]) -> Vec<u8> {
+        let mut data = 6u32.to_le_bytes().to_vec();
+        data.extend_from_slice(b"vendor");
+        data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            data.extend_from_slice(comment.as_bytes());
+        }
+        data
+    }
+
+    fn bmff_box(box_type: &[u8], body: &[u8]) -> Vec<u8> {
+        [(8 + body.len() as u32).to_be_bytes().to_vec(), box_type.to_vec(), body.to_vec()].concat()
+    }
+
+    fn ilst_item(item: &[u8], value: &[u8]) -> Vec<u8> {
+        bmff_box(item, &bmff_box(b"data", &[&[0, 0, 0, 1, 0, 0, 0, 0], value].concat()))
+    }
+
+    #[test]
+    fn genres_include_the_winamp_extensions() {
+        assert_eq!(GENRES.len(), 192);
+        assert_eq!(GENRES[79], "Hard Rock");
+        assert_eq!(GENRES[80], "Folk");
+        assert_eq!(GENRES[191], "Psybient");
+    }
+
+    #[test]
+    fn id3v2_3_frames() {
+        let title: Vec<u8> = [1, 0xff, 0xfe].into_iter().chain("Été".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let frames = [
+            frame_v3(b"TPE1", b"\0Artist"),
+            frame_v3(b"TIT2", &title),
+            frame_v3(b"TRCK", b"\x003/12"),
+            frame_v3(b"TYER", b"\x001999"),
+            frame_v3(b"TCON", b"\0(17)"),
+            vec![0; 16],
+        ]
+        .concat();
+        let tags = read_id3v2("v3", &id3v2_tag(3, 0, &frames));
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.title.as_deref(), Some("Été"));
+        assert_eq!((tags.track, tags.track_total), (Some(3), Some(12)));
+        assert_eq!(tags.year, Some(1999));
+        assert_eq!(tags.genre.as_deref(), Some("Rock"));
+    }
+
+    #[test]
+    fn id3v2_4_frames() {
+        let frames = [
+            frame_v4(b"TPE1", 0, "\x03Artïst\0Other".as_bytes()),
+            frame_v4(b"TDRC", 0, b"\x032021-06-01"),
+            frame_v4(b"TCON", 0, b"\x03189"),
+            // Compressed, skipped
+            frame_v4(b"TALB", 0x08, b"\x03Album"),
+        ]
+        .concat();
+        let tags = read_id3v2("v4", &id3v2_tag(4, 0, &frames));
+        assert_eq!(tags.artist.as_deref(), Some("Artïst"));
+        assert_eq!(tags.year, Some(2021));
+        assert_eq!(tags.genre.as_deref(), Some("Dubstep"));
+        assert_eq!(tags.album, None);
+    }
+
+    #[test]
+    fn id3v2_2_frames() {
+        let frame = [b"TT2".to_vec(), vec![0, 0, 6], b"\0Title".to_vec()].concat();
+        let tags = read_id3v2("v2", &id3v2_tag(2, 0, &frame));
+        assert_eq!(tags.title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn id3v2_unsynchronised_tag() {
+        // A zero goes after every 0xff of the tag, the frame size does not count it
+        let frames: Vec<u8> = frame_v3(b"TIT2", b"\0\xffTitle")
+            .into_iter()
+            .flat_map(|byte| if byte == 0xff { vec![byte, 0] } else { vec![byte] })
+            .collect();
+        let tags = read_id3v2("unsynchronised", &id3v2_tag(3, 0x80, &frames));
+        assert_eq!(tags.title.as_deref(), Some("ÿTitle"));
+    }
+
+    #[test]
+    fn id3v2_truncated() {
+        let frames = [frame_v3(b"TPE1", b"\0Artist"), frame_v3(b"TIT2", b"\0Title")].concat();
+        let data = id3v2_tag(3, 0, &frames);
+        for length in 0..data.len() {
+            read_id3v2("truncated", &data[..length]);
+        }
+        // The second frame runs past the end of the file
+        let tags = read_id3v2("truncated", &data[..data.len() - 2]);
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.title, None);
+    }
+
+    #[test]
+    fn id3v2_oversized_frame() {
+        let mut frame = frame_v3(b"TPE1", b"\0Artist");
+        frame[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        let tags = read_id3v2("oversized", &id3v2_tag(3, 0, &frame));
+        assert_eq!(tags, AudioTags::default());
+    }
+
+    #[test]
+    fn id3v1_fills_what_id3v2_lacks() {
+        let mut block = vec![0; 128];
+        block[..3].copy_from_slice(b"TAG");
+        block[3..8].copy_from_slice(b"Short");
+        block[33..39].copy_from_slice(b"Artist");
+        block[126] = 7;
+        block[127] = 80;
+        let data = [id3v2_tag(3, 0, &frame_v3(b"TIT2", b"\0Long title")), vec![0xff; 32], block].concat();
+        let mut file = temp_file("id3v1", &data);
+        let mut tags = AudioTags::default();
+        id3v2(&mut file, &mut tags);
+        id3v1(&mut file, &mut tags);
+        assert_eq!(tags.title.as_deref(), Some("Long title"));
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.track, Some(7));
+        assert_eq!(tags.genre.as_deref(), Some("Folk"));
+    }
+
+    #[test]
+    fn vorbis_comment_fields() {
+        let data = vorbis_block(&["artist=Artist", "TRACKNUMBER=4", "TRACKTOTAL=10", "Date=2003-01-01", "GENRE=Jazz", "broken"]);
+        let mut tags = AudioTags::default();
+        assert!(vorbis_comments(&data, &mut tags).is_some());
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!((tags.track, tags.track_total), (Some(4), Some(10)));
+        assert_eq!(tags.year, Some(2003));
+        assert_eq!(tags.genre.as_deref(), Some("Jazz"));
+    }
+
+    #[test]
+    fn vorbis_comment_truncated_or_oversized() {
+        let data = vorbis_block(&["ARTIST=Artist", "TITLE=Title"]);
+        for length in 0..data.len() {
+            vorbis_comments(&data[..length], &mut AudioTags::default());
+        }
+        let mut tags = AudioTags::default();
+        assert!(vorbis_comments(&data[..data.len() - 1], &mut tags).is_none());
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.title, None);
+
+        let mut oversized = data.clone();
+        oversized[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(vorbis_comments(&oversized, &mut AudioTags::default()).is_none());
+    }
+
+    #[test]
+    fn flac_comment_block() {
+        let comments = vorbis_block(&["TITLE=Title"]);
+        let streaminfo = [vec![0, 0, 0, 34], vec![0; 34]].concat();
+        let block = [vec![0x84], (comments.len() as u32).to_be_bytes()[1..].to_vec(), comments].concat();
+        let data = [b"fLaC".to_vec(), streaminfo, block].concat();
+        let mut tags = AudioTags::default();
+        flac(&mut temp_file("flac", &data), &mut tags);
+        assert_eq!(tags.title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn ogg_comment_packet() {
+        let page = |sequence: u8, packet: &[u8]| {
+            let mut page = b"OggS\0\0".to_vec();
+            page.extend_from_slice(&[0; 8]);
+            page.extend_from_slice(&[1, 0, 0, 0]);
+            page.extend_from_slice(&[sequence, 0, 0, 0]);
+            page.extend_from_slice(&[0; 4]);
+            page.push(1);
+            page.push(packet.len() as u8);
+            page.extend_from_slice(packet);
+            page
+        };
+        let comments = [b"\x03vorbis".to_vec(), vorbis_block(&["ALBUM=Album"])].concat();
+        let data = [page(0, b"\x01vorbis"), page(1, &comments)].concat();
+        let mut tags = AudioTags::default();
+        ogg(&mut temp_file("ogg", &data), &mut tags);
+        assert_eq!(tags.album.as_deref(), Some("Album"));
+    }
+
+    #[test]
+    fn mp4_ilst_items() {
+        let ilst = [
+            ilst_item(b"\xa9ART", b"Artist"),
+            ilst_item(b"trkn", &[0, 0, 0, 5, 0, 12, 0, 0]),
+            ilst_item(b"gnre", &[0, 81]),
+            ilst_item(b"\xa9day", b"2010-02-03T00:00:00Z"),
+        ]
+        .concat();
+        let hdlr = bmff_box(b"hdlr", &[0; 25]);
+        let meta = bmff_box(b"meta", &[vec![0; 4], hdlr, bmff_box(b"ilst", &ilst)].concat());
+        let moov = bmff_box(b"moov", &bmff_box(b"udta", &meta));
+        let data = [bmff_box(b"ftyp", b"M4A "), bmff_box(b"mdat", &[0; 32]), moov].concat();
+        let mut tags = AudioTags::default();
+        mp4(&mut temp_file("mp4", &data), &mut tags);
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!((tags.track, tags.track_total), (Some(5), Some(12)));
+        assert_eq!(tags.genre.as_deref(), Some("Folk"));
+        assert_eq!(tags.year, Some(2010));
+    }
+
+    #[test]
+    fn mp4_truncated() {
+        let meta = bmff_box(b"meta", &[vec![0; 4], bmff_box(b"ilst", &ilst_item(b"\xa9nam", b"Title"))].concat());
+        let data = bmff_box(b"moov", &bmff_box(b"udta", &meta));
+        for length in 0..data.len() {
+            mp4(&mut temp_file("mp4_truncated", &data[..length]), &mut AudioTags::default());
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, OnceLock};
+
+// Files read at the same time, more would only make the disk seek between them
+const WORKERS: usize = 4;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+// Reads of file contents that are too slow for a frame, like hashing or metadata, shared so they
+// do not compete for the disk.
+#[derive(Default)]
+struct Pool {
+    queue: VecDeque<Task>,
+    workers: usize,
+}
+
+fn pool() -> &'static Mutex<Pool> {
+    static POOL: OnceLock<Mutex<Pool>> = OnceLock::new();
+    POOL.get_or_init(|| Mutex::new(Pool::default()))
+}
+
+// Queues the task, tasks run in the order they were queued.
+pub fn run(task: impl FnOnce() + Send + 'static) {
+    let mut pool = pool().lock().unwrap();
+    pool.queue.push_back(Box::new(task));
+    while pool.workers < WORKERS.min(pool.queue.len()) {
+        pool.workers += 1;
+        // Runs on the app's tokio runtime when there is one
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(work);
+            }
+            Err(_) => {
+                std::thread::spawn(work);
+            }
+        }
+    }
+}
+
+fn work() {
+    loop {
+        // Retiring under the same lock, a task queued in between would otherwise find no worker
+        let task = {
+            let mut pool = pool().lock().unwrap();
+            let task = pool.queue.pop_front();
+            if task.is_none() {
+                pool.workers -= 1;
+            }
+            task
+        };
+        let Some(task) = task else {
+            return;
+        };
+        // A file that makes a reader panic costs only its own task, not the worker
+        let _ = panic::catch_unwind(AssertUnwindSafe(task));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn panicking_tasks_keep_their_worker() {
+        for _ in 0..WORKERS * 2 {
+            run(|| panic!("unreadable file"));
+        }
+        let (tx, rx) = mpsc::channel();
+        run(move || tx.send(()).unwrap());
+        assert!(rx.recv_timeout(Duration::from_secs(10)).is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+// ISO base media file format boxes, the container of MP4, MOV, HEIC and AVIF files.
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+// Iterates the boxes of a slice as (type, body) pairs. A box that runs past the end of the
+// slice ends the iteration.
+pub fn boxes(data: &[u8]) -> Boxes<'_> {
+    Boxes { data }
+}
+
+pub struct Boxes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Boxes<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = uint(self.data, 0, 4)?;
+        let box_type = self.data.get(4..8)?;
+        let (header, size) = match size {
+            // The box extends to the end of the data
+            0 => (8, self.data.len() as u64),
+            1 => (16, uint(self.data, 8, 8)?),
+            size => (8, size),
+        };
+        let size = usize::try_from(size).ok().filter(|size| *size >= header)?;
+        let body = self.data.get(header..size)?;
+        self.data = &self.data[size..];
+        Some((box_type, body))
+    }
+}
+
+pub fn find<'a>(data: &'a [u8], box_type: &[u8]) -> Option<&'a [u8]> {
+    boxes(data)
+        .find(|(found, _)| *found == box_type)
+        .map(|(_, body)| body)
+}
+
+// Big endian unsigned integer of 0 to 8 bytes, a size of 0 reads as 0.
+pub fn uint(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    if size > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)),
+    )
+}
+
+// Reads the body of a top level box straight from the file. Boxes before it are skipped
+// without reading, `moov` often follows gigabytes of `mdat`.
+pub fn read_top_level(file: &mut File, box_type: &[u8], limit: u64) -> Option<Vec<u8>> {
+    let file_len = file.metadata().ok()?.len();
+    let mut offset: u64 = 0;
+    while offset.checked_add(8)? <= file_len {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut header = [0; 16];
+        let read = file.read(&mut header).ok()?;
+        let size = uint(&header, 0, 4)?;
+        let (header_len, size) = match size {
+            0 => (8, file_len - offset),
+            1 if read >= 16 => (16, uint(&header, 8, 8)?),
+            size => (8, size),
+        };
+        // Also stops on a size of 0 in the 64-bit field, which would never move on
+        if size < header_len {
+            return None;
+        }
+        if &header[4..8] == box_type {
+            if size - header_len > limit {
+                return None;
+            }
+            file.seek(SeekFrom::Start(offset + header_len)).ok()?;
+            let mut body = Vec::new();
+            file.take(size - header_len).read_to_end(&mut body).ok()?;
+            return Some(body);
+        }
+        offset = offset.checked_add(size)?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn bmff_box(box_type: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut data = (8 + body.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn temp_file(name: &str, data: &[u8]) -> File {
+        let path =
+            std::env::temp_dir().join(format!("bulkrename_bmff_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let file = File::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        file
+    }
+
+    #[test]
+    fn boxes_in_order() {
+        let data = [bmff_box(b"ftyp", b"isom"), bmff_box(b"moov", b"body")].concat();
+        let found: Vec<_> = boxes(&data).collect();
+        assert_eq!(
+            found,
+            vec![(&b"ftyp"[..], &b"isom"[..]), (&b"moov"[..], &b"body"[..])]
+        );
+        assert_eq!(find(&data, b"moov"), Some(&b"body"[..]));
+    }
+
+    #[test]
+    fn box_past_the_end_stops() {
+        let mut data = bmff_box(b"moov", b"body");
+        data[3] = 0xff;
+        assert_eq!(boxes(&data).count(), 0);
+        // Truncated header
+        assert_eq!(boxes(&data[..6]).count(), 0);
+    }
+
+    #[test]
+    fn box_smaller_than_its_header_stops() {
+        let mut data = bmff_box(b"moov", b"body");
+        data[3] = 4;
+        assert_eq!(boxes(&data).count(), 0);
+    }
+
+    #[test]
+    fn uint_is_big_endian() {
+        assert_eq!(uint(&[0x12, 0x34, 0x56], 1, 2), Some(0x3456));
+        assert_eq!(uint(&[0x12], 0, 0), Some(0));
+        assert_eq!(uint(&[0x12, 0x34], 1, 2), None);
+        assert_eq!(uint(&[0; 9], 0, 9), None);
+        assert_eq!(uint(&[0; 4], usize::MAX, 2), None);
+    }
+
+    #[test]
+    fn top_level_box_after_others() {
+        let data = [
+            bmff_box(b"ftyp", b"isom"),
+            bmff_box(b"mdat", &[0; 64]),
+            bmff_box(b"moov", b"body"),
+        ]
+        .concat();
+        let mut file = temp_file("after_others", &data);
+        assert_eq!(
+            read_top_level(&mut file, b"moov", 1024),
+            Some(b"body".to_vec())
+        );
+        assert_eq!(read_top_level(&mut file, b"moov", 2), None);
+        assert_eq!(read_top_level(&mut file, b"meta", 1024), None);
+    }
+
+    #[test]
+    fn top_level_largesize_box() {
+        let mut mdat = 1u32.to_be_bytes().to_vec();
+        mdat.extend_from_slice(b"mdat");
+        mdat.extend_from_slice(&(16u64 + 4).to_be_bytes());
+        mdat.extend_from_slice(&[0; 4]);
+        let data = [mdat, bmff_box(b"moov", b"body")].concat();
+        let mut file = temp_file("largesize", &data);
+        assert_eq!(
+            read_top_level(&mut file, b"moov", 1024),
+            Some(b"body".to_vec())
+        );
+    }
+
+    #[test]
+    fn top_level_oversized_box_stops() {
+        for largesize in [u64::MAX, u64::MAX - 8, 0, 8] {
+            let mut data = 1u32.to_be_bytes().to_vec();
+            data.extend_from_slice(b"mdat");
+            data.extend_from_slice(&largesize.to_be_bytes());
+            data.extend(bmff_box(b"moov", b"body"));
+            let mut file = temp_file("oversized", &data);
+            assert_eq!(
+                read_top_level(&mut file, b"moov", 1024),
+                None,
+                "largesize {}",
+                largesize
+            );
+        }
+    }
+
+    #[test]
+    fn top_level_box_to_the_end() {
+        let mut moov = bmff_box(b"moov", b"body");
+        moov[..4].copy_from_slice(&[0; 4]);
+        let data = [bmff_box(b"ftyp", b"isom"), moov].concat();
+        let mut file = temp_file("to_the_end", &data);
+        assert_eq!(
+            read_top_level(&mut file, b"moov", 1024),
+            Some(b"body".to_vec())
+        );
+    }
+}
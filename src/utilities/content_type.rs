@@ -0,0 +1,447 @@
+use crate::utilities::file_cache::FileCache;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// File type detected from the leading bytes of the content.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ContentType {
+    pub mime: &'static str,
+    // Extensions that match this content, the first one is canonical
+    pub extensions: &'static [&'static str],
+}
+
+impl ContentType {
+    pub fn canonical_extension(&self) -> &'static str {
+        self.extensions[0]
+    }
+
+    pub fn matches_extension(&self, extension: &str) -> bool {
+        let extension = extension.trim_start_matches('.');
+        self.extensions
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(extension))
+    }
+}
+
+const fn content_type(mime: &'static str, extensions: &'static [&'static str]) -> ContentType {
+    ContentType { mime, extensions }
+}
+
+pub const PNG: ContentType = content_type("image/png", &["png"]);
+pub const JPEG: ContentType = content_type("image/jpeg", &["jpg", "jpeg", "jpe", "jfif"]);
+pub const GIF: ContentType = content_type("image/gif", &["gif"]);
+pub const BMP: ContentType = content_type("image/bmp", &["bmp", "dib"]);
+pub const WEBP: ContentType = content_type("image/webp", &["webp"]);
+pub const ICO: ContentType = content_type("image/x-icon", &["ico", "cur"]);
+pub const PSD: ContentType = content_type("image/vnd.adobe.photoshop", &["psd"]);
+// Most camera raw formats are TIFF containers
+pub const TIFF: ContentType = content_type(
+    "image/tiff",
+    &["tif", "tiff", "dng", "nef", "cr2", "arw", "pef", "srw", "rw2", "orf", "raf"],
+);
+pub const CR3: ContentType = content_type("image/x-canon-cr3", &["cr3"]);
+pub const HEIC: ContentType = content_type("image/heic", &["heic", "heif", "hif"]);
+pub const AVIF: ContentType = content_type("image/avif", &["avif"]);
+pub const PDF: ContentType = content_type("application/pdf", &["pdf", "ai"]);
+// Office documents, e-books and Java archives are ZIP containers
+pub const ZIP: ContentType = content_type(
+    "application/zip",
+    &[
+        "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub", "jar", "apk", "xpi", "kmz",
+    ],
+);
+pub const GZIP: ContentType = content_type("application/gzip", &["gz", "tgz"]);
+pub const BZIP2: ContentType = content_type("application/x-bzip2", &["bz2", "tbz2"]);
+pub const XZ: ContentType = content_type("application/x-xz", &["xz", "txz"]);
+pub const SEVEN_ZIP: ContentType = content_type("application/x-7z-compressed", &["7z"]);
+pub const RAR: ContentType = content_type("application/vnd.rar", &["rar"]);
+pub const MP3: ContentType = content_type("audio/mpeg", &["mp3"]);
+pub const FLAC: ContentType = content_type("audio/flac", &["flac"]);
+pub const OGG: ContentType = content_type("audio/ogg", &["ogg", "oga", "ogv", "opus"]);
+pub const WAV: ContentType = content_type("audio/wav", &["wav"]);
+pub const M4A: ContentType = content_type("audio/mp4", &["m4a", "m4b", "mp4"]);
+pub const MP4: ContentType = content_type("video/mp4", &["mp4", "m4v", "m4a"]);
+pub const MOV: ContentType = content_type("video/quicktime", &["mov", "qt"]);
+pub const THREE_GP: ContentType = content_type("video/3gpp", &["3gp", "3gpp"]);
+pub const THREE_G2: ContentType = content_type("video/3gpp2", &["3g2", "3gp2"]);
+pub const MKV: ContentType = content_type("video/x-matroska", &["mkv", "mka", "mks"]);
+pub const WEBM: ContentType = content_type("video/webm", &["webm"]);
+pub const AVI: ContentType = content_type("video/x-msvideo", &["avi"]);
+pub const SQLITE: ContentType = content_type("application/vnd.sqlite3", &["sqlite", "sqlite3", "db"]);
+pub const WASM: ContentType = content_type("application/wasm", &["wasm"]);
+
+// Enough for the second MPEG audio frame, the longest frame is 2880 bytes
+const HEADER_LENGTH: u64 = 4096;
+
+// The length of the MPEG audio frame starting with this header, None for anything that only
+// looks like a frame sync
+fn mpeg_frame_length(header: &[u8]) -> Option<usize> {
+    let [0xff, second, third, _] = *header.get(..4)? else {
+        return None;
+    };
+    if second & 0xe0 != 0xe0 {
+        return None;
+    }
+    // 0: MPEG 2.5, 2: MPEG 2, 3: MPEG 1, 1 is reserved
+    let version = (second >> 3) & 0x03;
+    // 1: Layer III, 2: Layer II, 3: Layer I, 0 is reserved
+    let layer = (second >> 1) & 0x03;
+    let bitrate_index = (third >> 4) as usize;
+    let sample_rate_index = ((third >> 2) & 0x03) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+    let padding = ((third >> 1) & 0x01) as usize;
+
+    const MPEG1_LAYER1: [usize; 15] = [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448];
+    const MPEG1_LAYER2: [usize; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384];
+    const MPEG1_LAYER3: [usize; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2_LAYER1: [usize; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+    const MPEG2_LAYER23: [usize; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    let bitrates = match (version, layer) {
+        (3, 3) => &MPEG1_LAYER1,
+        (3, 2) => &MPEG1_LAYER2,
+        (3, 1) => &MPEG1_LAYER3,
+        (_, 3) => &MPEG2_LAYER1,
+        _ => &MPEG2_LAYER23,
+    };
+    let bitrate = bitrates[bitrate_index] * 1000;
+    let sample_rate = [44100, 48000, 32000][sample_rate_index]
+        / match version {
+            3 => 1,
+            2 => 2,
+            _ => 4,
+        };
+    let length = match (version, layer) {
+        (_, 3) => (12 * bitrate / sample_rate + padding) * 4,
+        // MPEG 2 and 2.5 Layer III frames hold half the samples
+        (2 | 0, 1) => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    };
+    Some(length)
+}
+
+// A valid frame header followed by another one, unless the file ends first
+fn is_mpeg_audio(header: &[u8], file_length: u64) -> bool {
+    // The UTF-16 little endian byte order mark also passes for a frame sync
+    if header.starts_with(b"\xff\xfe") {
+        return false;
+    }
+    let Some(length) = mpeg_frame_length(header) else {
+        return false;
+    };
+    match header.get(length..) {
+        Some(next) if next.len() >= 4 => mpeg_frame_length(next).is_some(),
+        _ => length as u64 >= file_length,
+    }
+}
+
+// The 14 byte file header: size, two reserved zero fields and the offset of the pixels,
+// followed by one of the known DIB header sizes
+fn is_bmp(header: &[u8], file_length: u64) -> bool {
+    let u32_at = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let (Some(size), Some(reserved), Some(offset), Some(dib_size)) = (u32_at(2), u32_at(6), u32_at(10), u32_at(14))
+    else {
+        return false;
+    };
+    size as u64 == file_length
+        && reserved == 0
+        && [12, 40, 52, 56, 64, 108, 124].contains(&dib_size)
+        && offset >= 14 + dib_size
+        && (offset as u64) <= file_length
+}
+
+fn detect(header: &[u8], file_length: u64) -> Option<ContentType> {
+    let starts = |signature: &[u8]| header.starts_with(signature);
+    let at = |offset: usize, signature: &[u8]| {
+        header.get(offset..offset + signature.len()) == Some(signature)
+    };
+
+    let detected = if starts(b"\x89PNG\r\n\x1a\n") {
+        PNG
+    } else if starts(b"\xff\xd8\xff") {
+        JPEG
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        GIF
+    } else if starts(b"BM") && is_bmp(header, file_length) {
+        BMP
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        WEBP
+    } else if starts(b"RIFF") && at(8, b"WAVE") {
+        WAV
+    } else if starts(b"RIFF") && at(8, b"AVI ") {
+        AVI
+    } else if starts(b"\x00\x00\x01\x00") {
+        ICO
+    } else if starts(b"8BPS") {
+        PSD
+    } else if starts(b"II*\x00")
+        || starts(b"MM\x00*")
+        || starts(b"IIRO")
+        || starts(b"IIU\x00")
+        || starts(b"FUJIFILMCCD-RAW")
+    {
+        TIFF
+    } else if at(4, b"ftyp") {
+        match header.get(8..12)? {
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => HEIC,
+            b"avif" | b"avis" => AVIF,
+            b"qt  " => MOV,
+            b"M4A " | b"M4B " | b"M4P " => M4A,
+            b"crx " => CR3,
+            b"3gp4" | b"3gp5" | b"3gp6" | b"3gp7" | b"3gs7" | b"3gg6" | b"3ge6" | b"3ge7" => THREE_GP,
+            b"3g2a" | b"3g2b" | b"3g2c" => THREE_G2,
+            b"isom" | b"iso2" | b"iso3" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1"
+            | b"dash" | b"mmp4" | b"MSNV" | b"M4V " | b"M4VH" | b"M4VP" | b"f4v " | b"XAVC" => MP4,
+            // Brands not listed above could be anything, from camera raws to other containers
+            _ => return None,
+        }
+    } else if starts(b"%PDF-") {
+        PDF
+    } else if starts(b"PK\x03\x04") || starts(b"PK\x05\x06") {
+        ZIP
+    } else if starts(b"\x1f\x8b") {
+        GZIP
+    } else if starts(b"BZh") {
+        BZIP2
+    } else if starts(b"\xfd7zXZ\x00") {
+        XZ
+    } else if starts(b"7z\xbc\xaf\x27\x1c") {
+        SEVEN_ZIP
+    } else if starts(b"Rar!\x1a\x07") {
+        RAR
+    } else if starts(b"ID3") || is_mpeg_audio(header, file_length) {
+        MP3
+    } else if starts(b"fLaC") {
+        FLAC
+    } else if starts(b"OggS") {
+        OGG
+    } else if starts(b"\x1a\x45\xdf\xa3") {
+        // The EBML header names the document type
+        if header.windows(4).any(|window| window == b"webm") {
+            WEBM
+        } else {
+            MKV
+        }
+    } else if starts(b"SQLite format 3\x00") {
+        SQLITE
+    } else if starts(b"\x00asm") {
+        WASM
+    } else {
+        return None;
+    };
+    Some(detected)
+}
+
+fn cache() -> &'static FileCache<Option<ContentType>> {
+    static CACHE: OnceLock<FileCache<Option<ContentType>>> = OnceLock::new();
+    CACHE.get_or_init(FileCache::new)
+}
+
+fn read_header(path: &Path) -> Option<ContentType> {
+    let mut header = Vec::with_capacity(HEADER_LENGTH as usize);
+    let file = File::open(path).ok()?;
+    let file_length = file.metadata().ok()?.len();
+    file.take(HEADER_LENGTH).read_to_end(&mut header).ok()?;
+    detect(&header, file_length)
+}
+
+// Reads the first bytes of a file, results are cached until the file is modified.
+pub fn sniff(path: &Path) -> Option<ContentType> {
+    cache().get_or_insert_with(path, |_| read_header(path)).flatten()
+}
+
+// Like `sniff` for a whole folder listing, the file is read on the background pool and the
+// outer None means it has not been read yet.
+pub fn sniff_in_background(path: &Path) -> Option<Option<ContentType>> {
+    cache().get_or_read_in_background(path, |path, _| read_header(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn detect_all(data: &[u8]) -> Option<ContentType> {
+        detect(data, data.len() as u64)
+    }
+
+    // A frame header padded with zeros to the length of the frame
+    fn frame(header: [u8; 4], length: usize) -> Vec<u8> {
+        let mut frame = header.to_vec();
+        frame.resize(length, 0);
+        frame
+    }
+
+    // MPEG 1 Layer III, 128 kbit/s at 44.1 kHz, 417 bytes
+    const MPEG1_LAYER3: [u8; 4] = [0xff, 0xfb, 0x90, 0x00];
+
+    #[test]
+    fn mpeg_frame_lengths() {
+        assert_eq!(mpeg_frame_length(&MPEG1_LAYER3), Some(417));
+        // Padded
+        assert_eq!(mpeg_frame_length(&[0xff, 0xfb, 0x92, 0x00]), Some(418));
+        // MPEG 2 Layer III, 80 kbit/s at 22.05 kHz
+        assert_eq!(mpeg_frame_length(&[0xff, 0xf3, 0x90, 0x00]), Some(261));
+        // MPEG 1 Layer I, 288 kbit/s at 44.1 kHz, in slots of 4 bytes
+        assert_eq!(mpeg_frame_length(&[0xff, 0xff, 0x90, 0x00]), Some(312));
+        // MPEG 1 Layer II, 192 kbit/s at 48 kHz
+        assert_eq!(mpeg_frame_length(&[0xff, 0xfd, 0xa4, 0x00]), Some(576));
+    }
+
+    #[test]
+    fn mpeg_frame_sync_lookalikes() {
+        // Reserved version, reserved layer, free and bad bitrates, reserved sample rate
+        for header in [
+            [0xff, 0xeb, 0x90, 0x00],
+            [0xff, 0xf9, 0x90, 0x00],
+            [0xff, 0xfb, 0x00, 0x00],
+            [0xff, 0xfb, 0xf0, 0x00],
+            [0xff, 0xfb, 0x9c, 0x00],
+            [0xff, 0x1b, 0x90, 0x00],
+            [0xfe, 0xfb, 0x90, 0x00],
+        ] {
+            assert_eq!(mpeg_frame_length(&header), None, "{:x?}", header);
+        }
+        assert_eq!(mpeg_frame_length(&[0xff, 0xfb, 0x90]), None);
+    }
+
+    #[test]
+    fn mpeg_audio_needs_a_second_frame() {
+        let two_frames = [frame(MPEG1_LAYER3, 417), MPEG1_LAYER3.to_vec()].concat();
+        assert_eq!(detect_all(&two_frames), Some(MP3));
+
+        let garbage_after = [frame(MPEG1_LAYER3, 417), b"text".to_vec()].concat();
+        assert_eq!(detect_all(&garbage_after), None);
+        let second_frame_too_early = [frame(MPEG1_LAYER3, 400), frame(MPEG1_LAYER3, 100)].concat();
+        assert_eq!(detect_all(&second_frame_too_early), None);
+    }
+
+    #[test]
+    fn mpeg_audio_of_a_single_frame() {
+        assert_eq!(detect_all(&frame(MPEG1_LAYER3, 417)), Some(MP3));
+        // The header is cut off after the first frame, the file goes on
+        assert_eq!(detect(&frame(MPEG1_LAYER3, 417), 10_000), None);
+    }
+
+    #[test]
+    fn id3_tags_and_utf16_text() {
+        assert_eq!(detect_all(b"ID3\x04\x00\x00\x00\x00\x00\x00"), Some(MP3));
+        // The byte order mark reads as an MPEG 1 Layer I frame of 312 bytes
+        let bom = [0xff, 0xfe, 0x90, 0x00];
+        assert_eq!(mpeg_frame_length(&bom), Some(312));
+        let utf16 = [frame(bom, 312), bom.to_vec()].concat();
+        assert_eq!(detect_all(&utf16), None);
+    }
+
+    // File header and a DIB header of `dib_size` bytes, pixels follow at `offset`
+    fn bmp(size: u32, reserved: u32, offset: u32, dib_size: u32, length: usize) -> Vec<u8> {
+        let mut bmp = b"BM".to_vec();
+        for value in [size, reserved, offset, dib_size] {
+            bmp.extend_from_slice(&value.to_le_bytes());
+        }
+        bmp.resize(length, 0);
+        bmp
+    }
+
+    #[test]
+    fn bmp_headers() {
+        for dib_size in [12, 40, 108, 124] {
+            let offset = 14 + dib_size;
+            let length = offset as usize + 16;
+            assert_eq!(detect_all(&bmp(length as u32, 0, offset, dib_size, length)), Some(BMP));
+        }
+    }
+
+    #[test]
+    fn bmp_lookalikes() {
+        // Wrong file size, reserved fields in use, unknown DIB header, pixels inside the
+        // header and pixels past the end
+        for data in [
+            bmp(100, 0, 54, 40, 70),
+            bmp(70, 1, 54, 40, 70),
+            bmp(70, 0, 54, 41, 70),
+            bmp(70, 0, 50, 40, 70),
+            bmp(70, 0, 80, 40, 70),
+        ] {
+            assert_eq!(detect_all(&data), None);
+        }
+        assert_eq!(detect_all(b"BMW service invoice, March"), None);
+        assert_eq!(detect_all(b"BM"), None);
+    }
+
+    fn ftyp(brand: &[u8]) -> Vec<u8> {
+        [b"\x00\x00\x00\x18ftyp".to_vec(), brand.to_vec(), b"\x00\x00\x00\x00isommp41".to_vec()].concat()
+    }
+
+    #[test]
+    fn ftyp_brands() {
+        for (brand, expected) in [
+            (b"heic", HEIC),
+            (b"mif1", HEIC),
+            (b"avif", AVIF),
+            (b"qt  ", MOV),
+            (b"M4A ", M4A),
+            (b"crx ", CR3),
+            (b"3gp5", THREE_GP),
+            (b"3g2a", THREE_G2),
+            (b"isom", MP4),
+            (b"mp42", MP4),
+            (b"XAVC", MP4),
+        ] {
+            assert_eq!(detect_all(&ftyp(brand)), Some(expected), "{}", String::from_utf8_lossy(brand));
+        }
+    }
+
+    #[test]
+    fn unknown_ftyp_brands() {
+        // The compatible brands are not read, the major brand decides
+        assert_eq!(detect_all(&ftyp(b"abcd")), None);
+        assert_eq!(detect_all(&ftyp(b"HEIC")), None);
+        assert_eq!(detect_all(b"\x00\x00\x00\x18ftyphe"), None);
+        assert_eq!(detect_all(b"ftypisom"), None);
+    }
+
+    #[test]
+    fn other_signatures() {
+        assert_eq!(detect_all(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"), Some(PNG));
+        assert_eq!(detect_all(b"\xff\xd8\xff\xe0\x00\x10JFIF"), Some(JPEG));
+        assert_eq!(detect_all(b"RIFF\x24\x00\x00\x00WAVEfmt "), Some(WAV));
+        assert_eq!(detect_all(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some(WEBP));
+        assert_eq!(detect_all(b"RIFF\x24\x00\x00\x00ABCD"), None);
+        assert_eq!(detect_all(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm"), Some(WEBM));
+        assert_eq!(detect_all(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x88matroska"), Some(MKV));
+        assert_eq!(detect_all(b"%PDF-1.7\n"), Some(PDF));
+        assert_eq!(detect_all(b"PK\x03\x04\x14\x00"), Some(ZIP));
+        assert_eq!(detect_all(b"plain text"), None);
+        assert_eq!(detect_all(b""), None);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(JPEG.canonical_extension(), "jpg");
+        assert!(JPEG.matches_extension(".JPEG"));
+        assert!(TIFF.matches_extension("dng"));
+        assert!(!PNG.matches_extension("jpg"));
+        assert!(!PNG.matches_extension(""));
+    }
+
+    #[test]
+    fn reads_the_file() {
+        let path = std::env::temp_dir().join(format!("bulkrename_content_type_{}_clip", std::process::id()));
+        let mut data = ftyp(b"qt  ");
+        // Only the header is read
+        data.resize(HEADER_LENGTH as usize * 4, 0);
+        fs::write(&path, &data).unwrap();
+        let detected = read_header(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(detected, Some(MOV));
+        assert_eq!(read_header(&path), None);
+    }
+}
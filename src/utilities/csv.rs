@@ -0,0 +1,209 @@
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum Delimiter {
+    // Tab for .tsv files, otherwise whichever of the others the first line uses most
+    #[default]
+    Auto,
+    Comma,
+    // Spreadsheets in locales with a decimal comma export with semicolons
+    Semicolon,
+    Tab,
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delimiter::Auto => write!(f, "Detect"),
+            Delimiter::Comma => write!(f, "Comma"),
+            Delimiter::Semicolon => write!(f, "Semicolon"),
+            Delimiter::Tab => write!(f, "Tab"),
+        }
+    }
+}
+
+impl Delimiter {
+    pub fn resolve(&self, path: &Path, text: &str) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Semicolon => ';',
+            Delimiter::Tab => '\t',
+            Delimiter::Auto => {
+                let is_tsv = path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("tsv") || extension.eq_ignore_ascii_case("tab"));
+                if is_tsv {
+                    return '\t';
+                }
+                let first_line = text.lines().next().unwrap_or_default();
+                [',', ';', '\t']
+                    .into_iter()
+                    .max_by_key(|delimiter| first_line.matches(*delimiter).count())
+                    .filter(|delimiter| first_line.contains(*delimiter))
+                    .unwrap_or(',')
+            }
+        }
+    }
+}
+
+// Reads RFC 4180 records: quoted fields may hold delimiters, line breaks and doubled quotes.
+// Blank lines are skipped.
+pub fn parse(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    // Quotes only open a quoted field at its start
+    let mut field_started = false;
+    let mut line = 1;
+    let mut quote_line = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if !field_started => {
+                in_quotes = true;
+                field_started = true;
+                quote_line = line;
+            }
+            _ if in_quotes => field.push(c),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                if field_started || !record.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                field_started = false;
+            }
+            _ if c == delimiter => {
+                record.push(std::mem::take(&mut field));
+                field_started = false;
+            }
+            _ => {
+                field.push(c);
+                field_started = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err(format!("Quote opened on line {} is never closed", quote_line));
+    }
+    if field_started || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// Quotes only the fields that need it, lines end with CRLF as RFC 4180 asks
+pub fn write(records: &[Vec<String>], delimiter: char) -> String {
+    let mut text = String::new();
+    for record in records {
+        let fields: Vec<String> = record
+            .iter()
+            .map(|field| {
+                if field.contains([delimiter, '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect();
+        text.push_str(&fields.join(&delimiter.to_string()));
+        text.push_str("\r\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|field| field.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn quoted_delimiters() {
+        assert_eq!(parse("a,\"b,c\",d\n", ','), Ok(records(&[&["a", "b,c", "d"]])));
+        assert_eq!(parse("a;\"b;c\"", ';'), Ok(records(&[&["a", "b;c"]])));
+    }
+
+    #[test]
+    fn doubled_quotes() {
+        assert_eq!(
+            parse("\"say \"\"hi\"\"\",\"\"\"\"\n", ','),
+            Ok(records(&[&["say \"hi\"", "\""]]))
+        );
+        // A quote inside an unquoted field is kept as it is
+        assert_eq!(parse("5\" disk,b", ','), Ok(records(&[&["5\" disk", "b"]])));
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(
+            parse("\"one\r\ntwo\",b\r\nc,d\r\n", ','),
+            Ok(records(&[&["one\r\ntwo", "b"], &["c", "d"]]))
+        );
+        assert_eq!(parse("a\rb\n", ','), Ok(records(&[&["a"], &["b"]])));
+    }
+
+    #[test]
+    fn blank_lines_and_empty_fields() {
+        assert_eq!(
+            parse("a,,b\n\n\r\n,\n", ','),
+            Ok(records(&[&["a", "", "b"], &["", ""]]))
+        );
+        assert_eq!(parse("", ','), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        assert_eq!(parse("\u{feff}path,new_name\n", ','), Ok(records(&[&["path", "new_name"]])));
+    }
+
+    #[test]
+    fn unclosed_quote() {
+        assert_eq!(
+            parse("a,b\nc,\"d\ne\n", ','),
+            Err("Quote opened on line 2 is never closed".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let rows = records(&[
+            &["path", "name", "new_name"],
+            &["/a/b.txt", "b.txt", "c, \"d\".txt"],
+            &["", "semi;colon", "tab\there"],
+            &["line\nbreak", "carriage\r\nreturn", "é😀"],
+        ]);
+        for delimiter in [',', ';', '\t'] {
+            let text = write(&rows, delimiter);
+            assert!(text.ends_with("\r\n"));
+            assert_eq!(parse(&text, delimiter), Ok(rows.clone()), "{:?}", delimiter);
+        }
+    }
+
+    #[test]
+    fn detect_delimiter() {
+        let auto = Delimiter::Auto;
+        assert_eq!(auto.resolve(Path::new("list.csv"), "a;b;c,d\n1;2;3"), ';');
+        assert_eq!(auto.resolve(Path::new("list.csv"), "a,b\n"), ',');
+        assert_eq!(auto.resolve(Path::new("list.txt"), "name"), ',');
+        assert_eq!(auto.resolve(Path::new("list.TSV"), "a,b"), '\t');
+        assert_eq!(Delimiter::Semicolon.resolve(Path::new("list.tsv"), ""), ';');
+    }
+}
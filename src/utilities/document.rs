@@ -0,0 +1,644 @@
+use crate::utilities::bmff;
+use crate::utilities::content_type::{ContentType, PDF, ZIP};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit};
+use regex::bytes::Regex as BytesRegex;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+// Metadata is small, anything past this is not a metadata block worth inflating
+const INFLATE_LIMIT: usize = 16 * 1024 * 1024;
+// PDF objects and cross-reference sections are read in chunks of this size
+const CHUNK: u64 = 1024 * 1024;
+// The trailer and the last cross-reference section are at the end of the file
+const PDF_TAIL: u64 = 64 * 1024;
+// Incremental updates chain cross-reference sections, deeper chains are not followed
+const MAX_SECTIONS: usize = 32;
+// Cross-reference rows are a few bytes wide, wider predictor rows only come from broken files
+const MAX_COLUMNS: u64 = 4096;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub created: Option<NaiveDateTime>,
+}
+
+impl DocumentInfo {
+    // Earlier sources win, the PDF Info dictionary before XMP
+    fn merge(&mut self, other: DocumentInfo) {
+        self.title = self.title.take().or(other.title);
+        self.author = self.author.take().or(other.author);
+        self.subject = self.subject.take().or(other.subject);
+        self.created = self.created.take().or(other.created);
+    }
+}
+
+pub fn read(path: &Path, content_type: ContentType) -> Option<DocumentInfo> {
+    let mut file = File::open(path).ok()?;
+    let info = if content_type == PDF {
+        pdf(&mut file)
+    } else if content_type == ZIP {
+        office(&mut file)
+    } else {
+        None
+    }?;
+    Some(info).filter(|info| *info != DocumentInfo::default())
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut data = Vec::new();
+    file.take(len).read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn non_empty(text: String) -> Option<String> {
+    let text = text.trim();
+    Some(text.to_string()).filter(|text| !text.is_empty())
+}
+
+// ISO 8601 dates as written by XMP, OOXML and ODF. Dates with an offset are shown in local time,
+// dates without one are taken as written.
+fn parse_iso_date(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&Local).naive_local());
+    }
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+        .ok()
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()?;
+            date.and_hms_opt(0, 0, 0)
+        })
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// Patterns are compiled once and shared by every file after that. They are fixed or built from a
+// handful of tag and key names, so the caches stay small.
+fn regex(pattern: &str) -> Regex {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    let mut patterns = PATTERNS.get_or_init(Default::default).lock().unwrap();
+    if let Some(regex) = patterns.get(pattern) {
+        return regex.clone();
+    }
+    let regex = Regex::new(pattern).unwrap();
+    patterns.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+fn bytes_regex(pattern: &str) -> BytesRegex {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, BytesRegex>>> = OnceLock::new();
+    let mut patterns = PATTERNS.get_or_init(Default::default).lock().unwrap();
+    if let Some(regex) = patterns.get(pattern) {
+        return regex.clone();
+    }
+    let regex = BytesRegex::new(pattern).unwrap();
+    patterns.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+// Inner markup of the first `<tag>` element
+fn xml_element_raw(xml: &str, tag: &str) -> Option<String> {
+    let escaped = regex::escape(tag);
+    let pattern = regex(&format!(r"(?s)<{}(?:\s[^>]*)?>(.*?)</{}>", escaped, escaped));
+    Some(pattern.captures(xml)?.get(1)?.as_str().to_string())
+}
+
+// Text of the first `<tag>` element, attributes and nested markup are ignored
+fn xml_element(xml: &str, tag: &str) -> Option<String> {
+    let text = xml_element_raw(xml, tag)?;
+    let markup = regex(r"<[^>]*>");
+    non_empty(unescape_xml(&markup.replace_all(&text, "")))
+}
+
+// Little endian unsigned integer, the byte order of ZIP headers
+fn le(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    Some(bytes.iter().rev().fold(0, |value, byte| value << 8 | u64::from(*byte)))
+}
+
+// Reads one entry of a ZIP archive through the central directory at the end of the file.
+fn zip_entry(file: &mut File, name: &str) -> Option<Vec<u8>> {
+    let len = file.metadata().ok()?.len();
+    // The end of central directory record is followed by a comment of up to 64 KiB
+    let tail_len = len.min(22 + 0xffff);
+    let tail = read_at(file, len - tail_len, tail_len)?;
+    let end = tail.windows(4).rposition(|window| window == b"PK\x05\x06")?;
+    let directory_size = le(&tail, end + 12, 4)?;
+    let directory_offset = le(&tail, end + 16, 4)?;
+    let directory = read_at(file, directory_offset, directory_size)?;
+
+    let mut offset = 0;
+    while directory.get(offset..offset + 4) == Some(b"PK\x01\x02") {
+        let method = le(&directory, offset + 10, 2)?;
+        let compressed_size = le(&directory, offset + 20, 4)?;
+        let name_len = le(&directory, offset + 28, 2)? as usize;
+        let extra_len = le(&directory, offset + 30, 2)? as usize;
+        let comment_len = le(&directory, offset + 32, 2)? as usize;
+        let local_offset = le(&directory, offset + 42, 4)?;
+        let entry_name = directory.get(offset + 46..offset + 46 + name_len)?;
+        offset += 46 + name_len + extra_len + comment_len;
+        if entry_name != name.as_bytes() {
+            continue;
+        }
+
+        let local = read_at(file, local_offset, 30)?;
+        if !local.starts_with(b"PK\x03\x04") {
+            return None;
+        }
+        let data_offset = local_offset + 30 + le(&local, 26, 2)? + le(&local, 28, 2)?;
+        let data = read_at(file, data_offset, compressed_size)?;
+        return match method {
+            0 => Some(data),
+            8 => decompress_to_vec_with_limit(&data, INFLATE_LIMIT).ok(),
+            _ => None,
+        };
+    }
+    None
+}
+
+// OOXML keeps Dublin Core properties in `docProps/core.xml`, ODF in `meta.xml`
+fn office(file: &mut File) -> Option<DocumentInfo> {
+    if let Some(core) = zip_entry(file, "docProps/core.xml") {
+        let core = String::from_utf8_lossy(&core);
+        return Some(DocumentInfo {
+            title: xml_element(&core, "dc:title"),
+            author: xml_element(&core, "dc:creator"),
+            subject: xml_element(&core, "dc:subject"),
+            created: xml_element(&core, "dcterms:created").as_deref().and_then(parse_iso_date),
+        });
+    }
+    let meta = zip_entry(file, "meta.xml")?;
+    let meta = String::from_utf8_lossy(&meta);
+    Some(DocumentInfo {
+        title: xml_element(&meta, "dc:title"),
+        // `dc:creator` is whoever saved the document last in ODF
+        author: xml_element(&meta, "meta:initial-creator").or_else(|| xml_element(&meta, "dc:creator")),
+        subject: xml_element(&meta, "dc:subject"),
+        created: xml_element(&meta, "meta:creation-date").as_deref().and_then(parse_iso_date),
+    })
+}
+
+fn pdf(file: &mut File) -> Option<DocumentInfo> {
+    let len = file.metadata().ok()?.len();
+    let head = read_at(file, 0, CHUNK)?;
+    let tail_offset = len.saturating_sub(PDF_TAIL);
+    let tail = read_at(file, tail_offset, PDF_TAIL)?;
+
+    let mut info = pdf_info_dictionary(file, &head, &tail)
+        .map(|dictionary| DocumentInfo {
+            title: pdf_text(&dictionary, "Title"),
+            author: pdf_text(&dictionary, "Author"),
+            subject: pdf_text(&dictionary, "Subject"),
+            created: pdf_text(&dictionary, "CreationDate").as_deref().and_then(parse_pdf_date),
+        })
+        .unwrap_or_default();
+    // Uncompressed XMP packets, usually near the start or the end of the file
+    for buffer in [&head, &tail] {
+        if let Some(xmp) = xmp(buffer) {
+            info.merge(xmp);
+        }
+    }
+    Some(info)
+}
+
+fn xmp(data: &[u8]) -> Option<DocumentInfo> {
+    let start = data.windows(10).position(|window| window == b"<x:xmpmeta")?;
+    let end = data[start..]
+        .windows(12)
+        .position(|window| window == b"</x:xmpmeta>")
+        .map_or(data.len(), |end| start + end + 12);
+    let xml = String::from_utf8_lossy(&data[start..end]);
+
+    // Titles and descriptions are language alternatives, creators an ordered list
+    let first_item = |tag: &str| xml_element(&xml_element_raw(&xml, tag)?, "rdf:li");
+    let created = xml_element(&xml, "xmp:CreateDate").or_else(|| {
+        let attribute = regex(r#"xmp:CreateDate\s*=\s*"([^"]*)""#);
+        Some(attribute.captures(&xml)?.get(1)?.as_str().to_string())
+    });
+    Some(DocumentInfo {
+        title: first_item("dc:title"),
+        author: first_item("dc:creator"),
+        subject: first_item("dc:description"),
+        created: created.as_deref().and_then(parse_iso_date),
+    })
+}
+
+// "D:YYYYMMDDHHmmSS" followed by an optional offset, every part after the year is optional.
+// The date is taken as written, like EXIF dates.
+fn parse_pdf_date(text: &str) -> Option<NaiveDateTime> {
+    let digits: String = text
+        .trim()
+        .trim_start_matches("D:")
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    let part = |range: std::ops::Range<usize>, default: u32| -> Option<u32> {
+        match digits.get(range) {
+            Some(part) => part.parse().ok(),
+            None => Some(default),
+        }
+    };
+    let year = digits.get(0..4)?.parse().ok()?;
+    NaiveDate::from_ymd_opt(year, part(4..6, 1)?, part(6..8, 1)?)?.and_hms_opt(
+        part(8..10, 0)?,
+        part(10..12, 0)?,
+        part(12..14, 0)?,
+    )
+}
+
+// Text strings are UTF-16 with a byte order mark, UTF-8 with one or PDFDocEncoding, which
+// matches Latin-1 for printable characters.
+fn decode_pdf_text(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(b"\xfe\xff") {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        String::from_utf8_lossy(utf8).to_string()
+    } else {
+        bytes.iter().map(|byte| char::from(*byte)).collect()
+    }
+}
+
+// Literal `(...)` or hex `<...>` string value of a dictionary key.
+fn pdf_text(dictionary: &[u8], key: &str) -> Option<String> {
+    let pattern = bytes_regex(&format!(r"/{}\s*([(<])", regex::escape(key)));
+    let start = pattern.captures(dictionary)?.get(1)?.start();
+    let bytes = if dictionary[start] == b'<' {
+        let end = start + dictionary[start..].iter().position(|byte| *byte == b'>')?;
+        let hex: Vec<u8> = dictionary[start + 1..end]
+            .iter()
+            .filter(|byte| byte.is_ascii_hexdigit())
+            .copied()
+            .collect();
+        hex.chunks(2)
+            .map(|pair| {
+                // An odd final digit is followed by an implied zero
+                let high = char::from(pair[0]).to_digit(16).unwrap_or(0);
+                let low = pair.get(1).and_then(|low| char::from(*low).to_digit(16)).unwrap_or(0);
+                (high * 16 + low) as u8
+            })
+            .collect()
+    } else {
+        pdf_literal(&dictionary[start + 1..])?
+    };
+    non_empty(decode_pdf_text(&bytes))
+}
+
+// Body of a literal string after its opening parenthesis, with escapes resolved.
+fn pdf_literal(data: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut depth = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        index += 1;
+        match byte {
+            b'\\' => {
+                let escaped = *data.get(index)?;
+                index += 1;
+                match escaped {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(0x08),
+                    b'f' => bytes.push(0x0c),
+                    b'0'..=b'7' => {
+                        let mut value = u32::from(escaped - b'0');
+                        for _ in 0..2 {
+                            match data.get(index) {
+                                Some(digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    index += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(value as u8);
+                    }
+                    // A backslash at the end of a line continues the string
+                    b'\r' => {
+                        if data.get(index) == Some(&b'\n') {
+                            index += 1;
+                        }
+                    }
+                    b'\n' => {}
+                    other => bytes.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                bytes.push(byte);
+            }
+            b')' if depth == 0 => return Some(bytes),
+            b')' => {
+                depth -= 1;
+                bytes.push(byte);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    None
+}
+
+fn trim_start(data: &[u8]) -> &[u8] {
+    let start = data.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(data.len());
+    &data[start..]
+}
+
+fn last_capture(pattern: &str, data: &[u8]) -> Option<Vec<u64>> {
+    let pattern = bytes_regex(pattern);
+    let captures = pattern.captures_iter(data).last()?;
+    captures
+        .iter()
+        .skip(1)
+        .map(|capture| std::str::from_utf8(capture?.as_bytes()).ok()?.parse().ok())
+        .collect()
+}
+
+enum PdfLocation {
+    Offset(u64),
+    // Object number of the object stream and the index inside it
+    Compressed(u64, u64),
+}
+
+// The Info dictionary, found through the trailer and the cross-reference sections. Strings of
+// encrypted files are unreadable without the key, so those only get XMP.
+fn pdf_info_dictionary(file: &mut File, head: &[u8], tail: &[u8]) -> Option<Vec<u8>> {
+    if tail.windows(8).any(|window| window == b"/Encrypt") {
+        return None;
+    }
+    let info = last_capture(r"/Info\s+(\d+)\s+\d+\s+R", tail)?[0];
+    let start = last_capture(r"startxref\s+(\d+)", tail)?[0];
+
+    let object = match pdf_locate(file, start, info) {
+        Some(PdfLocation::Offset(offset)) => pdf_object_at(file, offset, info),
+        Some(PdfLocation::Compressed(stream, index)) => {
+            let PdfLocation::Offset(offset) = pdf_locate(file, start, stream)? else {
+                return None;
+            };
+            pdf_compressed_object(&pdf_object_at(file, offset, stream)?, index)
+        }
+        None => None,
+    };
+    // Broken cross-references are common, fall back to searching for the object itself
+    object.or_else(|| {
+        let pattern = bytes_regex(r"(?:^|\s)(\d+)\s+\d+\s+obj\b");
+        [head, tail].iter().find_map(|buffer| {
+            let found = pattern
+                .captures_iter(buffer)
+                .find(|captures| object_number(captures) == Some(info))?;
+            Some(buffer[found.get(0)?.end()..].to_vec())
+        })
+    })
+}
+
+// Reads `N G obj ... endobj` at an offset and returns what follows `obj`.
+fn pdf_object_at(file: &mut File, offset: u64, number: u64) -> Option<Vec<u8>> {
+    let chunk = read_at(file, offset, CHUNK)?;
+    let header = bytes_regex(r"^\s*(\d+)\s+\d+\s+obj\b").captures(&chunk)?;
+    if object_number(&header) != Some(number) {
+        return None;
+    }
+    Some(chunk[header.get(0)?.end()..].to_vec())
+}
+
+// Number of an object header matched with the number as the first group
+fn object_number(captures: &regex::bytes::Captures<'_>) -> Option<u64> {
+    std::str::from_utf8(captures.get(1)?.as_bytes()).ok()?.parse().ok()
+}
+
+// Stream bytes of an object, inflated when it uses FlateDecode.
+fn pdf_stream(object: &[u8]) -> Option<(Vec<u8>, &[u8])> {
+    let keyword = object.windows(6).position(|window| window == b"stream")?;
+    let dictionary = &object[..keyword];
+    let mut start = keyword + 6;
+    if object.get(start) == Some(&b'\r') {
+        start += 1;
+    }
+    if object.get(start) == Some(&b'\n') {
+        start += 1;
+    }
+    let length = last_capture(r"/Length\s+(\d+)(?:\s|/|>)", dictionary)
+        .and_then(|length| usize::try_from(length[0]).ok())
+        .filter(|length| {
+            // An indirect length reads as its object number, check that endstream follows
+            start
+                .checked_add(*length)
+                .and_then(|end| object.get(end..))
+                .map_or(false, |rest| trim_start(rest).starts_with(b"endstream"))
+        })
+        .or_else(|| {
+            let end = object[start..].windows(9).position(|window| window == b"endstream")?;
+            Some(end)
+        })?;
+    let data = object.get(start..start.checked_add(length)?)?;
+    let data = if dictionary.windows(12).any(|window| window == b"/FlateDecode") {
+        decompress_to_vec_zlib_with_limit(data, INFLATE_LIMIT).ok()?
+    } else {
+        data.to_vec()
+    };
+    Some((data, dictionary))
+}
+
+// Reverses the PNG row filters that cross-reference streams use.
+fn pdf_unpredict(data: Vec<u8>, dictionary: &[u8]) -> Option<Vec<u8>> {
+    let predictor = last_capture(r"/Predictor\s+(\d+)", dictionary).map_or(1, |value| value[0]);
+    if predictor < 10 {
+        return Some(data);
+    }
+    let columns = last_capture(r"/Columns\s+(\d+)", dictionary).map_or(1, |value| value[0]);
+    if !(1..=MAX_COLUMNS).contains(&columns) {
+        return None;
+    }
+    let columns = columns as usize;
+    let mut rows = Vec::with_capacity(data.len());
+    let mut previous = vec![0u8; columns];
+    for row in data.chunks(columns + 1) {
+        let (filter, row) = row.split_first()?;
+        let mut current = vec![0u8; columns];
+        for (index, byte) in row.iter().enumerate() {
+            let left = if index > 0 { current[index - 1] } else { 0 };
+            let up = previous[index];
+            let up_left = if index > 0 { previous[index - 1] } else { 0 };
+            current[index] = match filter {
+                1 => byte.wrapping_add(left),
+                2 => byte.wrapping_add(up),
+                3 => byte.wrapping_add(((u16::from(left) + u16::from(up)) / 2) as u8),
+                4 => {
+                    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+                    let distance = |value: u8| (estimate - i16::from(value)).abs();
+                    let nearest = if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+                        left
+                    } else if distance(up) <= distance(up_left) {
+                        up
+                    } else {
+                        up_left
+                    };
+                    byte.wrapping_add(nearest)
+                }
+                _ => *byte,
+            };
+        }
+        rows.extend_from_slice(&current);
+        previous = current;
+    }
+    Some(rows)
+}
+
+// Looks an object up in the cross-reference sections, newest first.
+fn pdf_locate(file: &mut File, start: u64, object: u64) -> Option<PdfLocation> {
+    let mut section = Some(start);
+    for _ in 0..MAX_SECTIONS {
+        let offset = section?;
+        let chunk = read_at(file, offset, CHUNK)?;
+        let trimmed = trim_start(&chunk);
+
+        let (location, trailer) = if let Some(table) = trimmed.strip_prefix(b"xref") {
+            let trailer_start = table.windows(7).position(|window| window == b"trailer")?;
+            (pdf_table_lookup(&table[..trailer_start], object), table[trailer_start..].to_vec())
+        } else {
+            let (data, dictionary) = pdf_stream(trimmed)?;
+            let data = pdf_unpredict(data, dictionary)?;
+            (pdf_stream_lookup(&data, dictionary, object), dictionary.to_vec())
+        };
+        if location.is_some() {
+            return location;
+        }
+        section = last_capture(r"/Prev\s+(\d+)", &trailer).map(|prev| prev[0]);
+    }
+    None
+}
+
+// Classic tables are subsections of "first count" followed by 20 byte entries.
+fn pdf_table_lookup(table: &[u8], object: u64) -> Option<PdfLocation> {
+    let text = String::from_utf8_lossy(table);
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    while let Some(header) = lines.next() {
+        let mut numbers = header.split_whitespace().map(str::parse::<u64>);
+        let (Some(Ok(first)), Some(Ok(count))) = (numbers.next(), numbers.next()) else {
+            return None;
+        };
+        for number in first..first.checked_add(count)? {
+            let entry = lines.next()?;
+            if number == object {
+                let mut fields = entry.split_whitespace();
+                let offset = fields.next()?.parse().ok()?;
+                return (fields.nth(1) == Some("n")).then_some(PdfLocation::Offset(offset));
+            }
+        }
+    }
+    None
+}
+
+// Cross-reference streams pack entries in fields whose widths are given by /W.
+fn pdf_stream_lookup(data: &[u8], dictionary: &[u8], object: u64) -> Option<PdfLocation> {
+    let widths_pattern = bytes_regex(r"/W\s*\[\s*(\d+)\s+(\d+)\s+(\d+)\s*\]");
+    let widths = widths_pattern.captures(dictionary)?;
+    let width = |index: usize| -> Option<usize> {
+        std::str::from_utf8(widths.get(index)?.as_bytes()).ok()?.parse().ok()
+    };
+    let widths = [width(1)?, width(2)?, width(3)?];
+    let entry_len = widths.iter().try_fold(0usize, |sum, width| sum.checked_add(*width))?;
+
+    let size = last_capture(r"/Size\s+(\d+)", dictionary)?[0];
+    let index_pattern = bytes_regex(r"/Index\s*\[([\d\s]*)\]");
+    let ranges: Vec<u64> = match index_pattern.captures(dictionary) {
+        Some(index) => String::from_utf8_lossy(index.get(1)?.as_bytes())
+            .split_whitespace()
+            .filter_map(|number| number.parse().ok())
+            .collect(),
+        None => vec![0, size],
+    };
+
+    let mut entry: usize = 0;
+    for range in ranges.chunks_exact(2) {
+        let (first, count) = (range[0], range[1]);
+        if (first..first.checked_add(count)?).contains(&object) {
+            let offset = entry
+                .checked_add(usize::try_from(object - first).ok()?)?
+                .checked_mul(entry_len)?;
+            // The widths before a field add up to less than the entry, which was checked
+            let field = |number: usize| -> Option<u64> {
+                let start = offset.checked_add(widths[..number].iter().sum::<usize>())?;
+                bmff::uint(data, start, widths[number])
+            };
+            // A missing type field means type 1
+            let entry_type = if widths[0] == 0 { 1 } else { field(0)? };
+            return match entry_type {
+                1 => Some(PdfLocation::Offset(field(1)?)),
+                2 => Some(PdfLocation::Compressed(field(1)?, field(2)?)),
+                _ => None,
+            };
+        }
+        entry = entry.checked_add(usize::try_from(count).ok()?)?;
+    }
+    None
+}
+
+// Object streams start with pairs of object number and offset relative to /First.
+fn pdf_compressed_object(stream_object: &[u8], index: u64) -> Option<Vec<u8>> {
+    let (data, dictionary) = pdf_stream(stream_object)?;
+    let first = usize::try_from(last_capture(r"/First\s+(\d+)", dictionary)?[0]).ok()?;
+    let header = String::from_utf8_lossy(data.get(..first)?).to_string();
+    let offsets: Vec<usize> = header
+        .split_whitespace()
+        .filter_map(|number| number.parse().ok())
+        .skip(1)
+        .step_by(2)
+        .collect();
+    let index = usize::try_from(index).ok()?;
+    let start = first.checked_add(*offsets.get(index)?)?;
+    let end = match offsets.get(index.checked_add(1)?) {
+        Some(next) => first.checked_add(*next)?,
+        None => data.len(),
+    };
+    Some(data.get(start..end)?.to_vec())
+}
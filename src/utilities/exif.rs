@@ -0,0 +1,469 @@
+use crate::utilities::bmff;
+use crate::utilities::content_type::{ContentType, AVIF, HEIC, JPEG, TIFF};
+use chrono::NaiveDateTime;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// EXIF blocks sit near the start of the file, reads are capped so a large RAW file is not read
+// whole just to find its camera model.
+const HEADER_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Exif {
+    pub date_time_original: Option<NaiveDateTime>,
+    pub sub_sec: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    pub iso: Option<u32>,
+    pub focal_length: Option<f64>,
+    pub image_number: Option<u32>,
+}
+
+pub fn read(path: &Path, content_type: ContentType) -> Option<Exif> {
+    let mut file = File::open(path).ok()?;
+    let header = read_at(&mut file, 0, HEADER_LIMIT)?;
+    if content_type == JPEG {
+        jpeg(&header)
+    } else if content_type == TIFF && header.starts_with(b"FUJIFILMCCD-RAW") {
+        // RAF files embed a full JPEG preview that carries the EXIF block
+        let offset = bmff::uint(&header, 84, 4)?;
+        jpeg(&read_at(&mut file, offset, HEADER_LIMIT)?)
+    } else if content_type == TIFF {
+        parse_tiff(&header)
+    } else if content_type == HEIC || content_type == AVIF {
+        heif(&mut file, &header)
+    } else {
+        None
+    }
+}
+
+fn read_at(file: &mut File, offset: u64, limit: u64) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut data = Vec::new();
+    file.take(limit).read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn jpeg(data: &[u8]) -> Option<Exif> {
+    if !data.starts_with(b"\xff\xd8") {
+        return None;
+    }
+    let mut offset = 2;
+    while data.get(offset) == Some(&0xff) {
+        let marker = *data.get(offset + 1)?;
+        // Image data follows the start of scan marker, there are no more headers
+        if marker == 0xda {
+            return None;
+        }
+        let length = bmff::uint(data, offset + 2, 2)? as usize;
+        let segment = data.get(offset + 4..offset + 2 + length)?;
+        if marker == 0xe1 && segment.starts_with(b"Exif\0\0") {
+            return parse_tiff(&segment[6..]);
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+// HEIF stores EXIF as an item, found through the item info and item location boxes of `meta`.
+fn heif(file: &mut File, header: &[u8]) -> Option<Exif> {
+    let meta = bmff::find(header, b"meta")?.get(4..)?;
+
+    let item_info = bmff::find(meta, b"iinf")?;
+    let entries_offset = if *item_info.first()? == 0 { 6 } else { 8 };
+    let exif_item = bmff::boxes(item_info.get(entries_offset..)?)
+        .filter(|(box_type, _)| *box_type == b"infe")
+        .find_map(|(_, entry)| {
+            let id_size = match entry.first()? {
+                2 => 2,
+                3 => 4,
+                _ => return None,
+            };
+            let item_type = entry.get(4 + id_size + 2..4 + id_size + 6)?;
+            (item_type == b"Exif")
+                .then(|| bmff::uint(entry, 4, id_size))
+                .flatten()
+        })?;
+
+    let location = bmff::find(meta, b"iloc")?;
+    let version = *location.first()?;
+    let offset_size = usize::from(*location.get(4)? >> 4);
+    let length_size = usize::from(location[4] & 0x0f);
+    let base_offset_size = usize::from(*location.get(5)? >> 4);
+    let index_size = if version > 0 {
+        usize::from(location[5] & 0x0f)
+    } else {
+        0
+    };
+    let id_size = if version < 2 { 2 } else { 4 };
+    let item_count = bmff::uint(location, 6, id_size)?;
+
+    let mut cursor = 6 + id_size;
+    for _ in 0..item_count {
+        let id = bmff::uint(location, cursor, id_size)?;
+        cursor += id_size;
+        if version > 0 {
+            // Construction method
+            cursor += 2;
+        }
+        // Data reference index
+        cursor += 2;
+        let base_offset = bmff::uint(location, cursor, base_offset_size)?;
+        cursor += base_offset_size;
+        let extent_count = bmff::uint(location, cursor, 2)?;
+        cursor += 2;
+        for extent in 0..extent_count {
+            cursor += index_size;
+            let offset = bmff::uint(location, cursor, offset_size)?;
+            let length = bmff::uint(location, cursor + offset_size, length_size)?;
+            cursor += offset_size + length_size;
+            if id == exif_item && extent == 0 {
+                let data = read_at(file, base_offset.checked_add(offset)?, length.min(HEADER_LIMIT))?;
+                // The item starts with the offset of the TIFF header past this field
+                let tiff_offset = bmff::uint(&data, 0, 4)? as usize;
+                return parse_tiff(data.get(tiff_offset.checked_add(4)?..)?);
+            }
+        }
+    }
+    None
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    // Offset of the value, inline in the entry when it fits in four bytes
+    value_offset: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn uint(&self, offset: usize, size: usize) -> Option<u64> {
+        let value = bmff::uint(self.data, offset, size)?;
+        if self.big_endian {
+            return Some(value);
+        }
+        Some(match size {
+            2 => u64::from((value as u16).swap_bytes()),
+            4 => u64::from((value as u32).swap_bytes()),
+            _ => value,
+        })
+    }
+
+    fn entries(&self, ifd_offset: usize) -> Vec<Entry> {
+        let Some(count) = self.uint(ifd_offset, 2) else {
+            return Vec::new();
+        };
+        (0..count as usize)
+            .map_while(|index| {
+                let offset = ifd_offset + 2 + index * 12;
+                let field_type = self.uint(offset + 2, 2)? as u16;
+                let count = self.uint(offset + 4, 4)? as u32;
+                let size = match field_type {
+                    3 => 2,
+                    4 | 9 => 4,
+                    5 | 10 => 8,
+                    _ => 1,
+                } * count as usize;
+                let value_offset = if size <= 4 {
+                    offset + 8
+                } else {
+                    self.uint(offset + 8, 4)? as usize
+                };
+                Some(Entry {
+                    tag: self.uint(offset, 2)? as u16,
+                    field_type,
+                    count,
+                    value_offset,
+                })
+            })
+            .collect()
+    }
+
+    fn text(&self, entry: &Entry) -> Option<String> {
+        let bytes = self
+            .data
+            .get(entry.value_offset..entry.value_offset + entry.count as usize)?;
+        let text = String::from_utf8_lossy(bytes);
+        Some(
+            text.trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                .to_string(),
+        )
+        .filter(|text| !text.is_empty())
+    }
+
+    fn number(&self, entry: &Entry) -> Option<u32> {
+        match entry.field_type {
+            3 => self.uint(entry.value_offset, 2).map(|value| value as u32),
+            4 => self.uint(entry.value_offset, 4).map(|value| value as u32),
+            _ => None,
+        }
+    }
+
+    fn rational(&self, entry: &Entry) -> Option<f64> {
+        if entry.field_type != 5 {
+            return None;
+        }
+        let numerator = self.uint(entry.value_offset, 4)?;
+        let denominator = self.uint(entry.value_offset + 4, 4)?;
+        (denominator != 0).then(|| numerator as f64 / denominator as f64)
+    }
+}
+
+fn parse_date(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y:%m:%d %H:%M:%S").ok()
+}
+
+fn parse_tiff(data: &[u8]) -> Option<Exif> {
+    // Olympus and Panasonic RAW files use their own magic number after the byte order
+    let big_endian = match data.get(0..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+    let tiff = Tiff { data, big_endian };
+
+    let mut exif = Exif::default();
+    let mut date_time = None;
+    let mut exif_ifd = None;
+    for entry in tiff.entries(tiff.uint(4, 4)? as usize) {
+        match entry.tag {
+            0x010f => exif.make = tiff.text(&entry),
+            0x0110 => exif.model = tiff.text(&entry),
+            0x0132 => date_time = tiff.text(&entry).as_deref().and_then(parse_date),
+            0x8769 => exif_ifd = tiff.uint(entry.value_offset, 4),
+            _ => {}
+        }
+    }
+    for entry in exif_ifd
+        .map(|offset| tiff.entries(offset as usize))
+        .unwrap_or_default()
+    {
+        match entry.tag {
+            0x9003 => exif.date_time_original = tiff.text(&entry).as_deref().and_then(parse_date),
+            0x9291 => exif.sub_sec = tiff.text(&entry),
+            0x8827 => exif.iso = tiff.number(&entry),
+            0x920a => exif.focal_length = tiff.rational(&entry),
+            0xa434 => exif.lens = tiff.text(&entry),
+            0x9211 => exif.image_number = tiff.number(&entry),
+            _ => {}
+        }
+    }
+    // Edited files sometimes only keep the IFD0 date
+    exif.date_time_original = exif.date_time_original.or(date_time);
+
+    Some(exif).filter(|exif| *exif != Exif::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::fs;
+
+    struct Writer {
+        data: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Writer {
+        fn u16(&mut self, value: u16) {
+            let bytes = if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            };
+            self.data.extend_from_slice(&bytes);
+        }
+
+        fn u32(&mut self, value: u32) {
+            let bytes = if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            };
+            self.data.extend_from_slice(&bytes);
+        }
+
+        // The value or offset field is written by the caller
+        fn entry(&mut self, tag: u16, field_type: u16, count: u32) {
+            self.u16(tag);
+            self.u16(field_type);
+            self.u32(count);
+        }
+    }
+
+    // IFD0 with the make, the model and the EXIF IFD, which holds the date taken, ISO and focal
+    // length. The EXIF IFD starts at 56 and its values at 98.
+    fn tiff(big_endian: bool) -> Vec<u8> {
+        let mut tiff = Writer {
+            data: Vec::new(),
+            big_endian,
+        };
+        tiff.data
+            .extend_from_slice(if big_endian { b"MM" } else { b"II" });
+        tiff.u16(42);
+        tiff.u32(8);
+
+        tiff.u16(3);
+        tiff.entry(0x010f, 2, 6);
+        tiff.u32(50);
+        tiff.entry(0x0110, 2, 3);
+        tiff.data.extend_from_slice(b"R5\0\0");
+        tiff.entry(0x8769, 4, 1);
+        tiff.u32(56);
+        tiff.u32(0);
+        tiff.data.extend_from_slice(b"Canon\0");
+
+        tiff.u16(3);
+        tiff.entry(0x9003, 2, 20);
+        tiff.u32(98);
+        tiff.entry(0x8827, 3, 1);
+        tiff.u16(400);
+        tiff.u16(0);
+        tiff.entry(0x920a, 5, 1);
+        tiff.u32(118);
+        tiff.u32(0);
+        tiff.data.extend_from_slice(b"2024:05:17 14:03:22\0");
+        tiff.u32(50);
+        tiff.u32(1);
+        tiff.data
+    }
+
+    fn expected() -> Exif {
+        Exif {
+            date_time_original: NaiveDate::from_ymd_opt(2024, 5, 17)
+                .and_then(|date| date.and_hms_opt(14, 3, 22)),
+            make: Some("Canon".to_string()),
+            model: Some("R5".to_string()),
+            iso: Some(400),
+            focal_length: Some(50.0),
+            ..Exif::default()
+        }
+    }
+
+    fn jpeg_with(segments: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut data = b"\xff\xd8".to_vec();
+        for (marker, body) in segments {
+            data.extend_from_slice(&[0xff, *marker]);
+            data.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+            data.extend_from_slice(body);
+        }
+        data
+    }
+
+    fn temp_file(name: &str, data: &[u8]) -> File {
+        let path = std::env::temp_dir().join(format!("bulkrename_exif_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let file = File::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        file
+    }
+
+    fn bmff_box(box_type: &[u8], body: &[u8]) -> Vec<u8> {
+        [(8 + body.len() as u32).to_be_bytes().to_vec(), box_type.to_vec(), body.to_vec()].concat()
+    }
+
+    // A HEIF file whose EXIF item is at `base_offset + offset`, the item itself follows `meta`
+    fn heif_with(base_offset: Option<u64>, offset: u64) -> Vec<u8> {
+        let item = [vec![0; 4], tiff(true)].concat();
+        let item_entry = bmff_box(b"infe", &[&[2, 0, 0, 0, 0, 1, 0, 0][..], b"Exif\0"].concat());
+        let item_info = bmff_box(b"iinf", &[&[0, 0, 0, 0, 0, 1][..], &item_entry].concat());
+        let meta = |base_offset: u64| {
+            let mut location = vec![0, 0, 0, 0, 0x84, 0x80, 0, 1, 0, 1, 0, 0];
+            location.extend_from_slice(&base_offset.to_be_bytes());
+            location.extend_from_slice(&[0, 1]);
+            location.extend_from_slice(&offset.to_be_bytes());
+            location.extend_from_slice(&(item.len() as u32).to_be_bytes());
+            bmff_box(b"meta", &[vec![0; 4], item_info.clone(), bmff_box(b"iloc", &location)].concat())
+        };
+        let file_type = bmff_box(b"ftyp", b"heic");
+        // The item starts right after the boxes unless told otherwise
+        let base_offset = base_offset.unwrap_or((file_type.len() + meta(0).len()) as u64);
+        [file_type, meta(base_offset), item].concat()
+    }
+
+    fn read_heif(data: &[u8]) -> Option<Exif> {
+        heif(&mut temp_file("heif", data), data)
+    }
+
+    #[test]
+    fn heif_item() {
+        assert_eq!(read_heif(&heif_with(None, 0)), Some(expected()));
+        assert_eq!(read_heif(&heif_with(Some(0), 1 << 20)), None);
+    }
+
+    #[test]
+    fn heif_item_offset_overflows() {
+        assert_eq!(read_heif(&heif_with(Some(u64::MAX), 1)), None);
+        assert_eq!(read_heif(&heif_with(Some(1), u64::MAX)), None);
+    }
+
+    #[test]
+    fn both_byte_orders() {
+        assert_eq!(parse_tiff(&tiff(false)), Some(expected()));
+        assert_eq!(parse_tiff(&tiff(true)), Some(expected()));
+    }
+
+    #[test]
+    fn wrong_byte_order() {
+        let mut data = tiff(false);
+        data[..2].copy_from_slice(b"MM");
+        assert_eq!(parse_tiff(&data), None);
+        data[..2].copy_from_slice(b"XX");
+        assert_eq!(parse_tiff(&data), None);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = tiff(true);
+        for length in 0..data.len() {
+            parse_tiff(&data[..length]);
+        }
+        // Cut before the EXIF IFD, the make and model are still there
+        let ifd0_only = Exif {
+            make: Some("Canon".to_string()),
+            model: Some("R5".to_string()),
+            ..Exif::default()
+        };
+        assert_eq!(parse_tiff(&data[..56]), Some(ifd0_only));
+        // Cut inside the date, the rest of the EXIF IFD is still read
+        let without_date = parse_tiff(&data[..110]).unwrap();
+        assert_eq!(without_date.date_time_original, None);
+        assert_eq!(without_date.iso, Some(400));
+    }
+
+    #[test]
+    fn jpeg_app1() {
+        let exif = [b"Exif\0\0".to_vec(), tiff(false)].concat();
+        let data = jpeg_with(&[(0xe0, b"JFIF\0\x01\x02"), (0xe1, &exif)]);
+        assert_eq!(jpeg(&data), Some(expected()));
+        for length in 0..data.len() {
+            jpeg(&data[..length]);
+        }
+    }
+
+    #[test]
+    fn jpeg_stops_at_the_image_data() {
+        let exif = [b"Exif\0\0".to_vec(), tiff(false)].concat();
+        let data = jpeg_with(&[(0xda, b""), (0xe1, &exif)]);
+        assert_eq!(jpeg(&data), None);
+    }
+
+    #[test]
+    fn jpeg_segment_past_the_end() {
+        let exif = [b"Exif\0\0".to_vec(), tiff(false)].concat();
+        let mut data = jpeg_with(&[(0xe1, &exif)]);
+        data[4..6].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert_eq!(jpeg(&data), None);
+        // A length below the two bytes of the length itself
+        data[4..6].copy_from_slice(&1u16.to_be_bytes());
+        assert_eq!(jpeg(&data), None);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::utilities::background;
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// Results derived from file contents, kept until the file is modified. The pipeline runs every
+// frame, so anything that reads more than the directory entry goes through one of these.
+pub struct FileCache<T> {
+    // None while the value is being read in the background
+    entries: Mutex<HashMap<PathBuf, (FileStamp, Option<T>)>>,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn new(metadata: &Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+}
+
+impl<T: Clone> Default for FileCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> FileCache<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Returns None for paths that are not regular files.
+    pub fn get_or_insert_with(&self, path: &Path, read: impl FnOnce(&Metadata) -> T) -> Option<T> {
+        let metadata = path.metadata().ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        let stamp = FileStamp::new(&metadata);
+        if let Some((cached_stamp, Some(value))) = self.entries.lock().unwrap().get(path) {
+            if *cached_stamp == stamp {
+                return Some(value.clone());
+            }
+        }
+
+        // Read without holding the lock, other files can be served meanwhile
+        let value = read(&metadata);
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), (stamp, Some(value.clone())));
+        Some(value)
+    }
+}
+
+impl<T: Clone + Default + Send + 'static> FileCache<T> {
+    // Like `get_or_insert_with` without blocking the frame: the value is read on the background
+    // pool and None is returned until it is there. Paths that are not regular files get the default.
+    pub fn get_or_read_in_background(
+        &'static self,
+        path: &Path,
+        read: impl FnOnce(&Path, &Metadata) -> T + Send + 'static,
+    ) -> Option<T> {
+        let Some(metadata) = path.metadata().ok().filter(Metadata::is_file) else {
+            return Some(T::default());
+        };
+        let stamp = FileStamp::new(&metadata);
+        let mut entries = self.entries.lock().unwrap();
+        if let Some((cached_stamp, value)) = entries.get(path) {
+            if *cached_stamp == stamp {
+                return value.clone();
+            }
+        }
+
+        entries.insert(path.to_path_buf(), (stamp, None));
+        let path = path.to_path_buf();
+        background::run(move || {
+            let value = read(&path, &metadata);
+            let mut entries = self.entries.lock().unwrap();
+            // Dropped when the file changed meanwhile, a newer read has been queued for it
+            if let Some((cached_stamp, cached)) = entries.get_mut(&path) {
+                if *cached_stamp == stamp {
+                    *cached = Some(value);
+                }
+            }
+        });
+        None
+    }
+}
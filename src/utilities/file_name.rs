@@ -0,0 +1,81 @@
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Device names Windows reserves with any extension, "CON.txt" included
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+// Splits "photo.tar.gz" into ("photo.tar", ".gz"). Dotfiles such as ".bashrc" have no extension.
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(0) | None => (name, ""),
+        Some(index) => name.split_at(index),
+    }
+}
+
+// The part before the first dot when it is a reserved device name, which Windows matches
+// ignoring case and trailing spaces.
+pub fn reserved_stem(name: &str) -> Option<&str> {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+        .then_some(stem)
+}
+
+// Why a new name cannot be used, it would move the file or not be a name at all
+pub fn name_problem(name: &str) -> Option<&'static str> {
+    if name == "." || name == ".." {
+        Some("is not a file name")
+    } else if name.contains(['/', '\\']) {
+        Some("contains a slash")
+    } else if name.chars().any(char::is_control) {
+        Some("contains control characters")
+    } else if reserved_stem(name).is_some() {
+        Some("is a name Windows reserves")
+    } else {
+        None
+    }
+}
+
+// Inserts at a grapheme position, negative positions count from the end.
+pub fn insert_at(input: &str, text: &str, position: i32) -> String {
+    let graphemes: Vec<&str> = input.graphemes(true).collect();
+    let len = graphemes.len() as i64;
+    let position = position as i64;
+    let index = if position >= 0 {
+        position.min(len)
+    } else {
+        (len + position).max(0)
+    } as usize;
+    [graphemes[..index].concat().as_str(), text, graphemes[index..].concat().as_str()].concat()
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum LengthUnit {
+    // UTF-8 bytes, what ext4 and most Unix file systems limit
+    #[default]
+    Bytes,
+    // Characters as a reader counts them
+    Graphemes,
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthUnit::Bytes => write!(f, "bytes"),
+            LengthUnit::Graphemes => write!(f, "characters"),
+        }
+    }
+}
+
+pub fn name_length(name: &str, unit: LengthUnit) -> usize {
+    match unit {
+        LengthUnit::Bytes => name.len(),
+        LengthUnit::Graphemes => name.graphemes(true).count(),
+    }
+}
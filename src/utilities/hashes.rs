@@ -0,0 +1,250 @@
+use crate::utilities::background;
+use crate::utilities::tokens::Segment;
+use sha2::Digest;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs::{File, Metadata};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+const CHUNK: usize = 1024 * 1024;
+// A failed file is hashed again on the first request after this, the pipeline asks every frame
+const RETRY_AFTER: Duration = Duration::from_secs(2);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum HashAlgorithm {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 5] = [
+        HashAlgorithm::Crc32,
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+    ];
+
+    // Also the name of the token
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Crc32 => "crc32",
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.name() == name)
+    }
+}
+
+// Algorithms named by the tokens of a template, each once
+pub fn used_by(segments: &[Segment]) -> Vec<HashAlgorithm> {
+    let mut algorithms: Vec<HashAlgorithm> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Token { name, .. } => HashAlgorithm::from_name(name),
+            Segment::Literal(_) => None,
+        })
+        .collect();
+    algorithms.sort();
+    algorithms.dedup();
+    algorithms
+}
+
+enum Hasher {
+    Crc32(crc32fast::Hasher),
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc32(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    // Lower case hex, the way checksum tools print digests
+    fn finish(self) -> String {
+        let bytes = match self {
+            Hasher::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+    }
+}
+
+// Identifies file contents without reading them. The inode survives renames, so files renamed
+// with hash tokens are not hashed again.
+#[derive(PartialEq, Eq, Hash, Clone)]
+struct ContentKey {
+    #[cfg(unix)]
+    inode: (u64, u64),
+    #[cfg(not(unix))]
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl ContentKey {
+    #[allow(unused_variables)]
+    fn new(path: &Path, metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+        Self {
+            #[cfg(unix)]
+            inode: (metadata.dev(), metadata.ino()),
+            #[cfg(not(unix))]
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Entry {
+    digests: BTreeMap<HashAlgorithm, String>,
+    // Bytes read so far while a job for this file is queued or running
+    progress: Option<u64>,
+    // With when it failed, errors like a locked file can go away
+    error: Option<(String, Instant)>,
+}
+
+struct Job {
+    key: ContentKey,
+    path: PathBuf,
+    algorithms: Vec<HashAlgorithm>,
+}
+
+#[derive(Default)]
+struct HashStore {
+    entries: HashMap<ContentKey, Entry>,
+}
+
+fn store() -> &'static Mutex<HashStore> {
+    static STORE: OnceLock<Mutex<HashStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(HashStore::default()))
+}
+
+pub enum HashStatus {
+    Ready(BTreeMap<HashAlgorithm, String>),
+    Pending,
+    Failed(String),
+}
+
+// Digests of a file, computed in the background. Missing digests are queued and reported as
+// pending until they are done.
+pub fn digests(path: &Path, algorithms: &[HashAlgorithm]) -> HashStatus {
+    let metadata = match path.metadata() {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => return HashStatus::Failed("Not a file".to_string()),
+        Err(err) => return HashStatus::Failed(err.to_string()),
+    };
+    let key = ContentKey::new(path, &metadata);
+
+    let mut store = store().lock().unwrap();
+    let entry = store.entries.entry(key.clone()).or_default();
+    match &entry.error {
+        Some((err, failed_at)) if failed_at.elapsed() < RETRY_AFTER => {
+            return HashStatus::Failed(err.clone());
+        }
+        Some(_) => entry.error = None,
+        None => {}
+    }
+    let missing: Vec<HashAlgorithm> = algorithms
+        .iter()
+        .filter(|algorithm| !entry.digests.contains_key(algorithm))
+        .copied()
+        .collect();
+    if missing.is_empty() {
+        return HashStatus::Ready(entry.digests.clone());
+    }
+    if entry.progress.is_none() {
+        entry.progress = Some(0);
+        let job = Job {
+            key,
+            path: path.to_path_buf(),
+            algorithms: missing,
+        };
+        background::run(move || work(job));
+    }
+    HashStatus::Pending
+}
+
+// Bytes hashed and bytes to hash over the queued and running jobs, None when idle.
+pub fn progress() -> Option<(u64, u64)> {
+    let store = store().lock().unwrap();
+    store
+        .entries
+        .iter()
+        .filter_map(|(key, entry)| entry.progress.map(|done| (done, key.len)))
+        .reduce(|(done, total), (entry_done, len)| (done + entry_done, total + len))
+}
+
+fn work(job: Job) {
+    let result = hash_file(&job);
+
+    let mut store = store().lock().unwrap();
+    let entry = store.entries.entry(job.key).or_default();
+    entry.progress = None;
+    match result {
+        Ok(digests) => entry.digests.extend(digests),
+        Err(err) => entry.error = Some((err, Instant::now())),
+    }
+}
+
+fn hash_file(job: &Job) -> Result<Vec<(HashAlgorithm, String)>, String> {
+    let mut file = File::open(&job.path).map_err(|err| err.to_string())?;
+    let mut hashers: Vec<Hasher> = job.algorithms.iter().map(|algorithm| Hasher::new(*algorithm)).collect();
+    let mut buffer = vec![0; CHUNK];
+    let mut done = 0;
+    loop {
+        let read = file.read(&mut buffer).map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+        done += read as u64;
+        if let Some(entry) = store().lock().unwrap().entries.get_mut(&job.key) {
+            entry.progress = Some(done);
+        }
+    }
+    Ok(job.algorithms.iter().copied().zip(hashers.into_iter().map(Hasher::finish)).collect())
+}
@@ -0,0 +1,143 @@
+use crate::utilities::audio_tags;
+use crate::utilities::content_type::sniff;
+use crate::utilities::document;
+use crate::utilities::exif;
+use crate::utilities::file_cache::FileCache;
+use crate::utilities::tokens::{TokenValue, Tokens};
+use crate::utilities::video;
+use chrono::{DateTime, Local};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+// Token names with a short description, listed in the stages that use them.
+pub const FILE_TOKENS: &[(&str, &str)] = &[
+    ("file.modified", "Modification date"),
+    ("file.created", "Creation date"),
+];
+
+pub const EXIF_TOKENS: &[(&str, &str)] = &[
+    ("exif.date", "Date taken (DateTimeOriginal)"),
+    ("exif.subsec", "Sub-seconds of the date taken"),
+    ("exif.make", "Camera make"),
+    ("exif.model", "Camera model"),
+    ("exif.lens", "Lens model"),
+    ("exif.iso", "ISO speed"),
+    ("exif.focal", "Focal length in mm"),
+    ("exif.image_number", "Image number"),
+];
+
+// Audio tags go without a prefix, names like `{track:02} - {artist} - {title}` read best that way
+pub const AUDIO_TOKENS: &[(&str, &str)] = &[
+    ("artist", "Artist"),
+    ("album", "Album"),
+    ("album_artist", "Album artist"),
+    ("track", "Track number"),
+    ("track_total", "Number of tracks"),
+    ("disc", "Disc number"),
+    ("disc_total", "Number of discs"),
+    ("title", "Title"),
+    ("year", "Year"),
+    ("genre", "Genre"),
+];
+
+pub const VIDEO_TOKENS: &[(&str, &str)] = &[
+    ("video.created", "Recording date from the container"),
+    ("video.duration", "Duration in seconds"),
+    ("video.width", "Width in pixels"),
+    ("video.height", "Height in pixels"),
+    ("video.codec", "Video codec, like h264 or hevc"),
+];
+
+pub const DOCUMENT_TOKENS: &[(&str, &str)] = &[
+    ("doc.title", "Title of a PDF or office document"),
+    ("doc.author", "Author"),
+    ("doc.subject", "Subject"),
+    ("doc.created", "Creation date"),
+];
+
+// Digests of the file content, hashed in the background instead of with the other tokens.
+// A length after the colon shortens them, like {sha256:12}.
+pub const HASH_TOKENS: &[(&str, &str)] = &[
+    ("crc32", "CRC-32 checksum"),
+    ("md5", "MD5 digest"),
+    ("sha1", "SHA-1 digest"),
+    ("sha256", "SHA-256 digest"),
+    ("blake3", "BLAKE3 digest"),
+];
+
+fn cache() -> &'static FileCache<Arc<Tokens>> {
+    static CACHE: OnceLock<FileCache<Arc<Tokens>>> = OnceLock::new();
+    CACHE.get_or_init(FileCache::new)
+}
+
+fn local_date(time: SystemTime) -> TokenValue {
+    TokenValue::Date(DateTime::<Local>::from(time).naive_local())
+}
+
+// Every token that could be read from the file, empty for folders and unreadable paths. Files
+// are read on the background pool, None until this one has been read.
+pub fn file_tokens(path: &Path) -> Option<Arc<Tokens>> {
+    cache().get_or_read_in_background(path, |path, metadata| {
+        let mut tokens = Tokens::new();
+        let mut insert = |name: &str, value: Option<TokenValue>| {
+            if let Some(value) = value {
+                tokens.insert(name.to_string(), value);
+            }
+        };
+
+        insert("file.modified", metadata.modified().ok().map(local_date));
+        insert("file.created", metadata.created().ok().map(local_date));
+
+        let content_type = sniff(path);
+        if let Some(exif) = content_type.and_then(|content_type| exif::read(path, content_type)) {
+            insert("exif.date", exif.date_time_original.map(TokenValue::Date));
+            insert("exif.subsec", exif.sub_sec.map(TokenValue::Text));
+            insert("exif.make", exif.make.map(TokenValue::Text));
+            insert("exif.model", exif.model.map(TokenValue::Text));
+            insert("exif.lens", exif.lens.map(TokenValue::Text));
+            insert("exif.iso", exif.iso.map(|iso| TokenValue::Number(iso.into())));
+            insert("exif.focal", exif.focal_length.map(TokenValue::Number));
+            insert(
+                "exif.image_number",
+                exif.image_number.map(|number| TokenValue::Number(number.into())),
+            );
+        }
+
+        let tags = content_type.and_then(|content_type| audio_tags::read(path, content_type));
+        if let Some(tags) = tags {
+            let number = |number: Option<u32>| number.map(|number| TokenValue::Number(number.into()));
+            insert("artist", tags.artist.map(TokenValue::Text));
+            insert("album", tags.album.map(TokenValue::Text));
+            insert("album_artist", tags.album_artist.map(TokenValue::Text));
+            insert("track", number(tags.track));
+            insert("track_total", number(tags.track_total));
+            insert("disc", number(tags.disc));
+            insert("disc_total", number(tags.disc_total));
+            insert("title", tags.title.map(TokenValue::Text));
+            insert("year", number(tags.year));
+            insert("genre", tags.genre.map(TokenValue::Text));
+        }
+
+        if let Some(info) = content_type.and_then(|content_type| video::read(path, content_type)) {
+            let created = info.created.map(|created| created.with_timezone(&Local).naive_local());
+            insert("video.created", created.map(TokenValue::Date));
+            insert(
+                "video.duration",
+                info.duration_secs.map(|duration| TokenValue::Number(duration.round())),
+            );
+            insert("video.width", info.width.map(|width| TokenValue::Number(width.into())));
+            insert("video.height", info.height.map(|height| TokenValue::Number(height.into())));
+            insert("video.codec", info.codec.map(TokenValue::Text));
+        }
+
+        if let Some(info) = content_type.and_then(|content_type| document::read(path, content_type)) {
+            insert("doc.title", info.title.map(TokenValue::Text));
+            insert("doc.author", info.author.map(TokenValue::Text));
+            insert("doc.subject", info.subject.map(TokenValue::Text));
+            insert("doc.created", info.created.map(TokenValue::Date));
+        }
+
+        Arc::new(tokens)
+    })
+}
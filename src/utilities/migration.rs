@@ -0,0 +1,493 @@
+use crate::components::add::AddMutation;
+use crate::components::auto_date::{AutoDateMutation, DateSource};
+use crate::components::numbering::{
+    BaseCase, DateBucket, KeySource, NumberingBase, NumberingKey, NumberingMode, NumberingMutation,
+};
+use crate::components::remove::RemoveMutation;
+use crate::components::replace::{ReplaceMutation, ReplacePair};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+// Settings persisted before the side panel stages were typed, when every field was a loose
+// `String`/`bool` on the app itself. Only read once to carry old settings over.
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
+pub struct LegacyState {
+    replace_match: Loose<String>,
+    replace_with: Loose<String>,
+    replace_case_sensitive: Loose<bool>,
+    replace_first_only: Loose<bool>,
+    replace_enabled: Loose<bool>,
+
+    remove_first_n: Loose<String>,
+    remove_last_n: Loose<String>,
+    remove_from: Loose<String>,
+    remove_to: Loose<String>,
+    remove_chars: Loose<String>,
+    remove_words: Loose<String>,
+    remove_trim: Loose<bool>,
+    remove_digits: Loose<bool>,
+    remove_accents: Loose<bool>,
+    remove_enabled: Loose<bool>,
+
+    add_prefix: Loose<String>,
+    add_insert: Loose<String>,
+    add_at_pos: Loose<String>,
+    add_suffix: Loose<String>,
+    add_word_space: Loose<bool>,
+    add_enabled: Loose<bool>,
+
+    auto_date_type: Loose<String>,
+    auto_date_format: Loose<String>,
+    auto_date_enabled: Loose<bool>,
+
+    numbering_mode: Loose<String>,
+    numbering_at: Loose<String>,
+    numbering_start: Loose<String>,
+    numbering_increment: Loose<String>,
+    numbering_separator: Loose<String>,
+    numbering_pad: Loose<String>,
+    numbering_break: Loose<String>,
+    numbering_base: Loose<String>,
+    numbering_base_case: Loose<String>,
+    numbering_enabled: Loose<bool>,
+}
+
+// One loose field, None when the saved state does not have it. Old state wrote plain values
+// rather than `Some(..)`, so this reads the value itself.
+struct Loose<T>(Option<T>);
+
+impl<T> Default for Loose<T> {
+    fn default() -> Self {
+        Loose(None)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Loose<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(|value| Loose(Some(value)))
+    }
+}
+
+impl<T: Clone + Default> Loose<T> {
+    fn get(&self) -> T {
+        self.0.clone().unwrap_or_default()
+    }
+
+    fn is_present(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+// Free-form text that does not parse keeps the typed default.
+fn parse<T: FromStr>(value: &str) -> Option<T> {
+    value.trim().parse().ok()
+}
+
+fn text(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.trim().is_empty())
+}
+
+impl LegacyState {
+    // Any of the loose fields marks the state as saved before the stages were typed
+    pub fn has_loose_fields(&self) -> bool {
+        [
+            self.replace_match.is_present(),
+            self.replace_with.is_present(),
+            self.replace_case_sensitive.is_present(),
+            self.replace_first_only.is_present(),
+            self.replace_enabled.is_present(),
+            self.remove_first_n.is_present(),
+            self.remove_last_n.is_present(),
+            self.remove_from.is_present(),
+            self.remove_to.is_present(),
+            self.remove_chars.is_present(),
+            self.remove_words.is_present(),
+            self.remove_trim.is_present(),
+            self.remove_digits.is_present(),
+            self.remove_accents.is_present(),
+            self.remove_enabled.is_present(),
+            self.add_prefix.is_present(),
+            self.add_insert.is_present(),
+            self.add_at_pos.is_present(),
+            self.add_suffix.is_present(),
+            self.add_word_space.is_present(),
+            self.add_enabled.is_present(),
+            self.auto_date_type.is_present(),
+            self.auto_date_format.is_present(),
+            self.auto_date_enabled.is_present(),
+            self.numbering_mode.is_present(),
+            self.numbering_at.is_present(),
+            self.numbering_start.is_present(),
+            self.numbering_increment.is_present(),
+            self.numbering_separator.is_present(),
+            self.numbering_pad.is_present(),
+            self.numbering_break.is_present(),
+            self.numbering_base.is_present(),
+            self.numbering_base_case.is_present(),
+            self.numbering_enabled.is_present(),
+        ]
+        .contains(&true)
+    }
+
+    pub fn replace_mutation(&self) -> ReplaceMutation {
+        let mut mutation = ReplaceMutation::default();
+        if let Some(find) = text(&self.replace_match.get()) {
+            mutation.pairs = vec![ReplacePair {
+                find,
+                replace_with: self.replace_with.get(),
+            }];
+        }
+        mutation.case_sensitive = self.replace_case_sensitive.get();
+        mutation.first_only = self.replace_first_only.get();
+        mutation.enabled = self.replace_enabled.0.unwrap_or(mutation.enabled);
+        mutation
+    }
+
+    pub fn remove_mutation(&self) -> RemoveMutation {
+        let mut mutation = RemoveMutation::default();
+        mutation.first_n = parse(&self.remove_first_n.get()).unwrap_or(mutation.first_n);
+        mutation.last_n = parse(&self.remove_last_n.get()).unwrap_or(mutation.last_n);
+        mutation.from = parse(&self.remove_from.get()).unwrap_or(mutation.from);
+        mutation.to = parse(&self.remove_to.get()).unwrap_or(mutation.to);
+        mutation.chars = self.remove_chars.get();
+        mutation.words = self.remove_words.get();
+        mutation.trim = self.remove_trim.get();
+        mutation.digits = self.remove_digits.get();
+        mutation.accents = self.remove_accents.get();
+        mutation.enabled = self.remove_enabled.get();
+        mutation
+    }
+
+    pub fn add_mutation(&self) -> AddMutation {
+        let mut mutation = AddMutation::default();
+        mutation.prefix = self.add_prefix.get();
+        mutation.insert = self.add_insert.get();
+        mutation.at_pos = parse(&self.add_at_pos.get()).unwrap_or(mutation.at_pos);
+        mutation.suffix = self.add_suffix.get();
+        mutation.word_space = self.add_word_space.get();
+        mutation.enabled = self.add_enabled.get();
+        mutation
+    }
+
+    pub fn auto_date_mutation(&self) -> AutoDateMutation {
+        let mut mutation = AutoDateMutation::default();
+        let source = text(&self.auto_date_type.get()).map(|source| source.trim().to_lowercase());
+        mutation.source = match source.as_deref() {
+            Some("created" | "creation") => DateSource::Created,
+            Some("modified" | "modification") => DateSource::Modified,
+            Some("accessed" | "access") => DateSource::Accessed,
+            Some("current" | "now" | "today") => DateSource::Current,
+            _ => mutation.source,
+        };
+        mutation.format = text(&self.auto_date_format.get()).unwrap_or(mutation.format);
+        mutation.enabled = self.auto_date_enabled.get();
+        mutation
+    }
+
+    pub fn numbering_mutation(&self) -> NumberingMutation {
+        let mut mutation = NumberingMutation::default();
+        let mode = text(&self.numbering_mode.get()).map(|mode| mode.trim().to_lowercase());
+        mutation.mode = match mode.as_deref() {
+            Some("prefix") => NumberingMode::Prefix,
+            Some("suffix") => NumberingMode::Suffix,
+            Some("insert") => NumberingMode::Insert,
+            _ => mutation.mode,
+        };
+        mutation.at_pos = parse(&self.numbering_at.get()).unwrap_or(mutation.at_pos);
+        mutation.start = parse(&self.numbering_start.get()).unwrap_or(mutation.start);
+        mutation.increment = parse(&self.numbering_increment.get()).unwrap_or(mutation.increment);
+        mutation.separator = text(&self.numbering_separator.get()).unwrap_or(mutation.separator);
+        if let Some(pad) = parse(&self.numbering_pad.get()) {
+            mutation.pad = pad;
+            mutation.auto_pad = false;
+        }
+        let base = text(&self.numbering_base.get()).map(|base| base.trim().to_lowercase());
+        match base.as_deref() {
+            Some("roman") => mutation.base = NumberingBase::Roman,
+            Some("alpha" | "alphabetic" | "letters") => mutation.base = NumberingBase::Alphabetic,
+            Some(radix) => {
+                if let Ok(radix @ 2..=36) = radix.parse::<u32>() {
+                    mutation.radix = radix;
+                }
+            }
+            None => {}
+        }
+        let base_case = text(&self.numbering_base_case.get()).map(|case| case.trim().to_lowercase());
+        mutation.base_case = match base_case.as_deref() {
+            Some("upper" | "uppercase") => BaseCase::Upper,
+            Some("lower" | "lowercase") => BaseCase::Lower,
+            _ => mutation.base_case,
+        };
+        mutation.break_key = self.numbering_break_key().unwrap_or(mutation.break_key);
+        mutation.enabled = self.numbering_enabled.get();
+        mutation
+    }
+
+    // The break was free text: a key name, a character range like "1-4" or a regex.
+    // Text that is none of these is dropped and the counter never restarts.
+    fn numbering_break_key(&self) -> Option<NumberingKey> {
+        let text = text(&self.numbering_break.get())?.trim().to_string();
+        let key = NumberingKey::default();
+        let date = |date_bucket| NumberingKey {
+            source: KeySource::ModifiedDate,
+            date_bucket,
+            ..NumberingKey::default()
+        };
+        match text.to_lowercase().as_str() {
+            "ext" | "extension" => {
+                return Some(NumberingKey {
+                    source: KeySource::Extension,
+                    ..key
+                })
+            }
+            "date" | "day" | "modified" => return Some(date(DateBucket::Day)),
+            "month" => return Some(date(DateBucket::Month)),
+            "year" => return Some(date(DateBucket::Year)),
+            _ => {}
+        }
+        let range = text.split_once(['-', ':']).and_then(|(from, to)| Some((parse(from)?, parse(to)?)));
+        if let Some((from, to)) = range.filter(|(from, to): &(usize, usize)| *from >= 1 && from <= to) {
+            return Some(NumberingKey {
+                source: KeySource::CharacterRange,
+                from,
+                to,
+                ..key
+            });
+        }
+        let regex = Regex::new(&text).ok()?;
+        Some(NumberingKey {
+            source: KeySource::RegexCapture,
+            // The whole match when the pattern has no group
+            group: regex.captures_len().min(2) - 1,
+            pattern: text,
+            ..key
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Side panel settings as the app saved them before the stages were typed
+    const OLD_STATE: &str = r#"{
+        "file_browser": {"directory_path": "/home/user/photos"},
+        "replace_match": "IMG",
+        "replace_with": "photo",
+        "replace_case_sensitive": true,
+        "replace_first_only": false,
+        "replace_enabled": true,
+        "remove_first_n": "2",
+        "remove_last_n": "",
+        "remove_from": " 3 ",
+        "remove_to": "five",
+        "remove_chars": "-",
+        "remove_words": "copy",
+        "remove_trim": true,
+        "remove_digits": false,
+        "remove_accents": true,
+        "remove_enabled": true,
+        "add_prefix": "trip ",
+        "add_insert": "",
+        "add_at_pos": "-1",
+        "add_suffix": "",
+        "add_word_space": false,
+        "add_enabled": false,
+        "auto_date_type": "Created",
+        "auto_date_format": "",
+        "auto_date_enabled": true,
+        "numbering_mode": "Prefix",
+        "numbering_at": "",
+        "numbering_start": "10",
+        "numbering_increment": "5",
+        "numbering_separator": " - ",
+        "numbering_pad": "3",
+        "numbering_break": "1-4",
+        "numbering_base": "roman",
+        "numbering_base_case": "UPPER",
+        "numbering_enabled": true
+    }"#;
+
+    fn legacy(json: &str) -> LegacyState {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn break_key(text: &str) -> Option<NumberingKey> {
+        legacy(&format!(r#"{{"numbering_break": {:?}}}"#, text)).numbering_break_key()
+    }
+
+    #[test]
+    fn old_state_has_loose_fields() {
+        assert!(legacy(OLD_STATE).has_loose_fields());
+        assert!(legacy(r#"{"replace_enabled": false}"#).has_loose_fields());
+    }
+
+    #[test]
+    fn typed_state_has_none() {
+        let state = r#"{
+            "config_version": 1,
+            "replace_mutation": {"enabled": true, "pairs": []},
+            "numbering_mutation": {"enabled": false, "start": 1}
+        }"#;
+        assert!(!legacy(state).has_loose_fields());
+        assert!(!legacy("{}").has_loose_fields());
+    }
+
+    #[test]
+    fn loose_fields_read_plain_values() {
+        let state = legacy(r#"{"remove_chars": "x", "remove_trim": true}"#);
+        assert_eq!(state.remove_chars.0.as_deref(), Some("x"));
+        assert_eq!(state.remove_trim.0, Some(true));
+        assert!(!state.remove_words.is_present());
+        assert_eq!(state.remove_words.get(), "");
+        assert!(!state.remove_digits.get());
+    }
+
+    #[test]
+    fn loose_fields_keep_their_type() {
+        assert!(serde_json::from_str::<LegacyState>(r#"{"remove_trim": "yes"}"#).is_err());
+    }
+
+    #[test]
+    fn replace() {
+        let mutation = legacy(OLD_STATE).replace_mutation();
+        assert_eq!(mutation.pairs.len(), 1);
+        assert_eq!(mutation.pairs[0].find, "IMG");
+        assert_eq!(mutation.pairs[0].replace_with, "photo");
+        assert!(mutation.case_sensitive);
+        assert!(!mutation.first_only);
+        assert!(mutation.enabled);
+
+        let mutation = legacy(r#"{"replace_match": "  ", "replace_with": "x"}"#).replace_mutation();
+        // Blank text keeps the one empty pair of a new stage
+        assert_eq!(mutation.pairs.len(), 1);
+        assert!(mutation.pairs[0].find.is_empty());
+        assert_eq!(mutation.enabled, ReplaceMutation::default().enabled);
+    }
+
+    #[test]
+    fn remove_keeps_defaults_for_text_that_does_not_parse() {
+        let mutation = legacy(OLD_STATE).remove_mutation();
+        let default = RemoveMutation::default();
+        assert_eq!(mutation.first_n, 2);
+        assert_eq!(mutation.last_n, default.last_n);
+        assert_eq!(mutation.from, 3);
+        assert_eq!(mutation.to, default.to);
+        assert_eq!(mutation.chars, "-");
+        assert_eq!(mutation.words, "copy");
+        assert!(mutation.trim && mutation.accents && !mutation.digits);
+        assert!(mutation.enabled);
+    }
+
+    #[test]
+    fn add() {
+        let mutation = legacy(OLD_STATE).add_mutation();
+        assert_eq!(mutation.prefix, "trip ");
+        assert_eq!(mutation.at_pos, -1);
+        assert!(!mutation.enabled);
+    }
+
+    #[test]
+    fn auto_date() {
+        let mutation = legacy(OLD_STATE).auto_date_mutation();
+        assert_eq!(mutation.source, DateSource::Created);
+        assert_eq!(mutation.format, AutoDateMutation::default().format);
+        assert!(mutation.enabled);
+
+        let mutation = legacy(r#"{"auto_date_type": "sometime"}"#).auto_date_mutation();
+        assert_eq!(mutation.source, AutoDateMutation::default().source);
+    }
+
+    #[test]
+    fn numbering() {
+        let mutation = legacy(OLD_STATE).numbering_mutation();
+        assert_eq!(mutation.mode, NumberingMode::Prefix);
+        assert_eq!(mutation.at_pos, NumberingMutation::default().at_pos);
+        assert_eq!(mutation.start, 10);
+        assert_eq!(mutation.increment, 5);
+        assert_eq!(mutation.separator, " - ");
+        assert_eq!(mutation.pad, 3);
+        assert!(!mutation.auto_pad);
+        assert_eq!(mutation.base, NumberingBase::Roman);
+        assert_eq!(mutation.base_case, BaseCase::Upper);
+        assert_eq!(mutation.break_key.source, KeySource::CharacterRange);
+        assert!(mutation.enabled);
+    }
+
+    #[test]
+    fn numbering_radix() {
+        let mutation = legacy(r#"{"numbering_base": "16"}"#).numbering_mutation();
+        assert_eq!((mutation.base, mutation.radix), (NumberingBase::Positional, 16));
+        let mutation = legacy(r#"{"numbering_base": "40"}"#).numbering_mutation();
+        assert_eq!(mutation.radix, NumberingMutation::default().radix);
+        let mutation = legacy(r#"{"numbering_base": "letters"}"#).numbering_mutation();
+        assert_eq!(mutation.base, NumberingBase::Alphabetic);
+    }
+
+    #[test]
+    fn numbering_break_key_names() {
+        let key = break_key(" Ext ").unwrap();
+        assert_eq!(key.source, KeySource::Extension);
+        let key = break_key("month").unwrap();
+        assert_eq!((key.source, key.date_bucket), (KeySource::ModifiedDate, DateBucket::Month));
+        let key = break_key("modified").unwrap();
+        assert_eq!((key.source, key.date_bucket), (KeySource::ModifiedDate, DateBucket::Day));
+    }
+
+    #[test]
+    fn numbering_break_key_ranges() {
+        for text in ["2-5", "2:5"] {
+            let key = break_key(text).unwrap();
+            assert_eq!((key.source, key.from, key.to), (KeySource::CharacterRange, 2, 5));
+        }
+        // Not a range, so read as a pattern
+        let key = break_key("5-2").unwrap();
+        assert_eq!((key.source, key.pattern.as_str()), (KeySource::RegexCapture, "5-2"));
+        let key = break_key("0-2").unwrap();
+        assert_eq!(key.source, KeySource::RegexCapture);
+    }
+
+    #[test]
+    fn numbering_break_key_patterns() {
+        let key = break_key(r"^(\w+)_\d+").unwrap();
+        assert_eq!((key.source, key.group), (KeySource::RegexCapture, 1));
+        let key = break_key(r"^\w+").unwrap();
+        assert_eq!((key.source, key.group), (KeySource::RegexCapture, 0));
+        assert!(break_key("[unclosed").is_none());
+        assert!(break_key("   ").is_none());
+        assert!(legacy("{}").numbering_break_key().is_none());
+    }
+
+    // eframe keeps the state as RON under the app key
+    struct Storage(HashMap<String, String>);
+
+    impl eframe::Storage for Storage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn read_from_eframe_storage() {
+        let state = r#"(
+            file_browser: (directory_path: "/home/user/photos"),
+            remove_first_n: "4",
+            remove_enabled: true,
+            numbering_break: "ext",
+        )"#;
+        let storage = Storage(HashMap::from([(eframe::APP_KEY.to_string(), state.to_string())]));
+        let legacy = eframe::get_value::<LegacyState>(&storage, eframe::APP_KEY).unwrap();
+        assert!(legacy.has_loose_fields());
+        assert_eq!(legacy.remove_mutation().first_n, 4);
+        assert_eq!(legacy.numbering_mutation().break_key.source, KeySource::Extension);
+    }
+}
@@ -1 +1,17 @@
-pub mod mutation_pipeline;
\ No newline at end of file
+pub mod audio_tags;
+pub mod background;
+pub mod bmff;
+pub mod content_type;
+pub mod csv;
+pub mod document;
+pub mod exif;
+pub mod file_cache;
+pub mod file_name;
+pub mod hashes;
+pub mod metadata;
+pub mod migration;
+pub mod mutation_pipeline;
+pub mod name_list;
+pub mod tokens;
+pub mod transliteration;
+pub mod video;
\ No newline at end of file
@@ -1,5 +1,23 @@
+use crate::components::file_browser::FileAbsolutePath;
+use std::path::Path;
+
 pub trait Mutation {
     fn mutate(&self, input: &str) -> String;
+
+    // Stages that read the file itself (timestamps, metadata) override this.
+    fn mutate_file(&self, input: &str, _path: &Path) -> String {
+        self.mutate(input)
+    }
+
+    // Stages that need to see the whole selection at once (e.g. an external command)
+    // override this, everything else is applied name by name.
+    fn mutate_all(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
+        inputs
+            .iter()
+            .zip(paths)
+            .map(|(input, path)| self.mutate_file(input, Path::new(path)))
+            .collect()
+    }
 }
 
 pub struct FunctionMutation<F> {
@@ -25,14 +43,13 @@ impl MutationPipeline {
         self.mutations.push(mutation);
     }
 
-    pub fn apply_mutation(&self, input: &str) -> String {
+    pub fn apply_mutations(&self, inputs: Vec<String>, paths: &[FileAbsolutePath]) -> Vec<String> {
         // fold: useful when you have a collection of something and want to produce a single value from it
         // fold() takes two arguments: an initial value, and a closure with two arguments:
         //      an ‘accumulator’, and an element.
         // The closure returns the value that the accumulator should have for the next iteration.
         // https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.fold
         // takes in the initial value
-        self.mutations.iter().fold(
-            input.to_string(), |acc, m| m.mutate(&acc))
+        self.mutations.iter().fold(inputs, |acc, m| m.mutate_all(acc, paths))
     }
 }
\ No newline at end of file
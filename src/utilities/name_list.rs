@@ -0,0 +1,99 @@
+use crate::components::file_browser::{FileAbsolutePath, FileNewName};
+use crate::utilities::csv::{self, Delimiter};
+use crate::utilities::file_name::name_problem;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+// Import looks for these headers, so the other columns can be edited, moved or removed freely
+const PATH_HEADER: &str = "path";
+const NEW_NAME_HEADER: &str = "new_name";
+
+// One selected file as written by "Export list"
+pub struct ListEntry {
+    pub path: FileAbsolutePath,
+    pub name: String,
+    pub new_name: FileNewName,
+    pub kind: String,
+    pub title: String,
+}
+
+pub struct ImportedList {
+    pub new_names: HashMap<FileAbsolutePath, FileNewName>,
+    // Rows whose new name cell was empty
+    pub skipped: usize,
+    // Rows whose new name cannot be used, with why
+    pub rejected: Vec<String>,
+}
+
+// Sortable in any spreadsheet, unlike the dates shown in the file browser
+fn format_time(time: std::io::Result<SystemTime>) -> String {
+    match time {
+        Ok(time) => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string(),
+        Err(_) => "".to_string(),
+    }
+}
+
+pub fn export(file: &Path, entries: &[ListEntry], with_title: bool) -> Result<(), String> {
+    let mut header = vec![PATH_HEADER, "name", NEW_NAME_HEADER, "size", "modified", "created", "kind"];
+    if with_title {
+        header.push("title");
+    }
+    let mut records = vec![header.into_iter().map(|field| field.to_string()).collect()];
+    for entry in entries {
+        let metadata = fs::metadata(&entry.path).ok();
+        let mut record = vec![
+            entry.path.clone(),
+            entry.name.clone(),
+            entry.new_name.clone(),
+            metadata.as_ref().map(|metadata| metadata.len().to_string()).unwrap_or_default(),
+            metadata.as_ref().map(|metadata| format_time(metadata.modified())).unwrap_or_default(),
+            metadata.as_ref().map(|metadata| format_time(metadata.created())).unwrap_or_default(),
+            entry.kind.clone(),
+        ];
+        if with_title {
+            record.push(entry.title.clone());
+        }
+        records.push(record);
+    }
+    let delimiter = Delimiter::Auto.resolve(file, "");
+    fs::write(file, csv::write(&records, delimiter))
+        .map_err(|err| format!("Cannot write {}: {}", file.display(), err))
+}
+
+pub fn import(file: &Path) -> Result<ImportedList, String> {
+    let text = fs::read_to_string(file).map_err(|err| format!("Cannot read {}: {}", file.display(), err))?;
+    let records = csv::parse(&text, Delimiter::Auto.resolve(file, &text))?;
+    let header = records.first().ok_or("The list is empty")?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+            .ok_or(format!("The list has no \"{}\" column", name))
+    };
+    let (path_column, new_name_column) = (column(PATH_HEADER)?, column(NEW_NAME_HEADER)?);
+
+    let mut list = ImportedList {
+        new_names: HashMap::new(),
+        skipped: 0,
+        rejected: Vec::new(),
+    };
+    for (index, record) in records.iter().enumerate().skip(1) {
+        let path = record.get(path_column).map(|path| path.trim()).unwrap_or_default();
+        match record.get(new_name_column).map(|new_name| new_name.trim()) {
+            Some(new_name) if !path.is_empty() && !new_name.is_empty() => match name_problem(new_name) {
+                // Rows counted from 1 as spreadsheets do
+                Some(problem) => list
+                    .rejected
+                    .push(format!("Row {}: \"{}\" {}", index + 1, new_name.escape_debug(), problem)),
+                None => {
+                    list.new_names.insert(path.to_string(), new_name.to_string());
+                }
+            },
+            _ => list.skipped += 1,
+        }
+    }
+    Ok(list)
+}
@@ -0,0 +1,459 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Names are limited to 255 bytes on most file systems, a wider number could never be used
+const MAX_WIDTH: usize = 255;
+
+// Values read from a file that can be placed in a name, keyed by token name like `exif.model`.
+pub type Tokens = BTreeMap<String, TokenValue>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValue {
+    Text(String),
+    Number(f64),
+    Date(NaiveDateTime),
+}
+
+impl TokenValue {
+    // The spec after the colon of `{name:spec}`: text is cut to that many characters, numbers are
+    // zero padded to that width and dates take a strftime format.
+    pub fn format(&self, spec: Option<&str>) -> Result<String, String> {
+        match self {
+            TokenValue::Text(text) => match spec {
+                None => Ok(text.clone()),
+                Some(spec) => {
+                    let length: usize = spec
+                        .parse()
+                        .map_err(|_| format!("Invalid length \"{}\"", spec))?;
+                    Ok(text.chars().take(length).collect())
+                }
+            },
+            TokenValue::Number(number) => {
+                let formatted = if number.fract() == 0.0 {
+                    format!("{}", *number as i64)
+                } else {
+                    format!("{}", number)
+                };
+                let Some(spec) = spec else {
+                    return Ok(formatted);
+                };
+                let width: usize = spec
+                    .parse()
+                    .ok()
+                    .filter(|width| *width <= MAX_WIDTH)
+                    .ok_or_else(|| format!("Invalid width \"{}\"", spec))?;
+                let (sign, digits) = match formatted.strip_prefix('-') {
+                    Some(digits) => ("-", digits),
+                    None => ("", formatted.as_str()),
+                };
+                Ok(format!("{}{:0>2$}", sign, digits, width.saturating_sub(sign.len())))
+            }
+            TokenValue::Date(date) => {
+                let spec = spec.unwrap_or("%Y-%m-%d");
+                let items: Vec<Item<'_>> = StrftimeItems::new(spec).collect();
+                if items.iter().any(|item| matches!(item, Item::Error)) {
+                    return Err(format!("Invalid date format \"{}\"", spec));
+                }
+                let mut formatted = String::new();
+                write!(formatted, "{}", date.format_with_items(items.iter()))
+                    .map_err(|_| format!("Invalid date format \"{}\"", spec))?;
+                Ok(formatted)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    // `{name[slice]:spec|filter|filter}`, the span covers the braces
+    Token {
+        name: String,
+        spec: Option<String>,
+        slice: Option<Slice>,
+        filters: Vec<Filter>,
+        span: Range<usize>,
+    },
+}
+
+// Grapheme range like Python's `[start:end]`, negative bounds count from the end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slice {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl Slice {
+    fn apply(&self, text: &str) -> String {
+        let graphemes: Vec<&str> = text.graphemes(true).collect();
+        let len = graphemes.len() as i64;
+        let index = |bound: i64| if bound < 0 { len.saturating_add(bound).max(0) } else { bound.min(len) } as usize;
+        let start = self.start.map_or(0, index);
+        let end = self.end.map_or(graphemes.len(), index);
+        if start >= end {
+            return String::new();
+        }
+        graphemes[start..end].concat()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    Slug,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 4] = [Filter::Upper, Filter::Lower, Filter::Trim, Filter::Slug];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Upper => "upper",
+            Filter::Lower => "lower",
+            Filter::Trim => "trim",
+            Filter::Slug => "slug",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Filter::Upper => "Upper case",
+            Filter::Lower => "Lower case",
+            Filter::Trim => "Remove surrounding whitespace",
+            Filter::Slug => "Lower case ASCII letters and digits joined by dashes",
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        match self {
+            Filter::Upper => text.to_uppercase(),
+            Filter::Lower => text.to_lowercase(),
+            Filter::Trim => text.trim().to_string(),
+            Filter::Slug => slug(text),
+        }
+    }
+}
+
+// "Café Olé!" becomes "cafe-ole", accents are dropped and everything else separates words
+pub fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    let mut separate = false;
+    for c in text.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_ascii_alphanumeric() {
+            if separate && !slug.is_empty() {
+                slug.push('-');
+            }
+            slug.push(c.to_ascii_lowercase());
+            separate = false;
+        } else {
+            separate = true;
+        }
+    }
+    slug
+}
+
+// Values come from the file, a title like "AC/DC" or a tag with a line break must not turn
+// into a folder or a broken name.
+fn name_safe(text: &str) -> String {
+    text.chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+// A template error with the byte range of the template it is about, for highlighting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl TemplateError {
+    pub fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Splits a template like `{exif.date:%Y%m%d}_{exif.model|upper}` into literals and tokens, `{{`
+// and `}}` stand for literal braces.
+pub fn parse(template: &str) -> Result<Vec<Segment>, TemplateError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek().map(|(_, c)| *c) == Some('}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let end = loop {
+                    match chars.next() {
+                        Some((end, '}')) => break end,
+                        Some((inner, '{')) => {
+                            return Err(TemplateError::new("Unexpected \"{\" inside a token", inner..inner + 1))
+                        }
+                        Some(_) => {}
+                        None => return Err(TemplateError::new("Unclosed \"{\"", index..template.len())),
+                    }
+                };
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(parse_token(template, index..end + 1)?);
+            }
+            '}' => return Err(TemplateError::new("Unmatched \"}\"", index..index + 1)),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+fn parse_token(template: &str, span: Range<usize>) -> Result<Segment, TemplateError> {
+    let body_start = span.start + 1;
+    let body = &template[body_start..span.end - 1];
+    let mut parts = body.split('|');
+    let head = parts.next().unwrap_or_default();
+
+    let mut filters = Vec::new();
+    let mut offset = body_start + head.len() + 1;
+    for part in parts {
+        let filter_span = offset..offset + part.len();
+        offset += part.len() + 1;
+        let filter = Filter::ALL
+            .into_iter()
+            .find(|filter| filter.name() == part.trim())
+            .ok_or_else(|| TemplateError::new(format!("Unknown filter \"{}\"", part.trim()), filter_span))?;
+        filters.push(filter);
+    }
+
+    let name_len = head.find(['[', ':']).unwrap_or(head.len());
+    let name = head[..name_len].trim();
+    if name.is_empty() {
+        return Err(TemplateError::new("Empty token name", span));
+    }
+    let mut rest = &head[name_len..];
+    let mut slice = None;
+    if let Some(inner) = rest.strip_prefix('[') {
+        let slice_start = body_start + name_len;
+        let Some(close) = inner.find(']') else {
+            return Err(TemplateError::new("Unclosed \"[\"", slice_start..body_start + head.len()));
+        };
+        let slice_span = slice_start..slice_start + close + 2;
+        let invalid = || TemplateError::new(format!("Invalid range \"[{}]\"", &inner[..close]), slice_span.clone());
+        let (start, end) = inner[..close].split_once(':').ok_or_else(invalid)?;
+        let bound = |bound: &str| match bound.trim() {
+            "" => Ok(None),
+            bound => bound.parse().map(Some).map_err(|_| invalid()),
+        };
+        slice = Some(Slice {
+            start: bound(start)?,
+            end: bound(end)?,
+        });
+        rest = &inner[close + 1..];
+    }
+    let spec = match rest.strip_prefix(':') {
+        Some(spec) => Some(spec.to_string()),
+        None if rest.is_empty() => None,
+        None => {
+            let rest_start = body_start + head.len() - rest.len();
+            return Err(TemplateError::new(
+                format!("Unexpected \"{}\" after the token name", rest),
+                rest_start..body_start + head.len(),
+            ));
+        }
+    };
+    Ok(Segment::Token {
+        name: name.to_string(),
+        spec,
+        slice,
+        filters,
+        span,
+    })
+}
+
+pub fn expand(
+    segments: &[Segment],
+    lookup: impl Fn(&str) -> Option<TokenValue>,
+) -> Result<String, String> {
+    let mut expanded = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => expanded.push_str(literal),
+            Segment::Token {
+                name,
+                spec,
+                slice,
+                filters,
+                ..
+            } => {
+                let value = lookup(name).ok_or_else(|| format!("No value for {{{}}}", name))?;
+                let mut text = value.format(spec.as_deref())?;
+                if let Some(slice) = slice {
+                    text = slice.apply(&text);
+                }
+                for filter in filters {
+                    text = filter.apply(&text);
+                }
+                expanded.push_str(&name_safe(&text));
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn token(name: &str, span: Range<usize>) -> Segment {
+        Segment::Token {
+            name: name.to_string(),
+            spec: None,
+            slice: None,
+            filters: Vec::new(),
+            span,
+        }
+    }
+
+    fn values(name: &str) -> Option<TokenValue> {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 17).and_then(|date| date.and_hms_opt(14, 3, 22));
+        match name {
+            "stem" => Some(TokenValue::Text("Holiday Photo".to_string())),
+            "ext" => Some(TokenValue::Text(".jpg".to_string())),
+            "n" => Some(TokenValue::Number(7.0)),
+            "negative" => Some(TokenValue::Number(-7.0)),
+            "ratio" => Some(TokenValue::Number(2.5)),
+            "date" => date.map(TokenValue::Date),
+            "title" => Some(TokenValue::Text("AC/DC\nLive".to_string())),
+            _ => None,
+        }
+    }
+
+    fn expand_template(template: &str) -> Result<String, String> {
+        let segments = parse(template).map_err(|err| err.message)?;
+        expand(&segments, values)
+    }
+
+    #[test]
+    fn literals_and_tokens() {
+        assert_eq!(
+            parse("a_{stem}.txt"),
+            Ok(vec![
+                Segment::Literal("a_".to_string()),
+                token("stem", 2..8),
+                Segment::Literal(".txt".to_string()),
+            ])
+        );
+        assert_eq!(parse("{{stem}}"), Ok(vec![Segment::Literal("{stem}".to_string())]));
+        assert_eq!(parse(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn token_parts() {
+        let Ok(segments) = parse("{stem[1:-2]:5|upper|trim}") else {
+            panic!("template does not parse");
+        };
+        assert_eq!(
+            segments,
+            vec![Segment::Token {
+                name: "stem".to_string(),
+                spec: Some("5".to_string()),
+                slice: Some(Slice {
+                    start: Some(1),
+                    end: Some(-2),
+                }),
+                filters: vec![Filter::Upper, Filter::Trim],
+                span: 0..25,
+            }]
+        );
+        // The spec takes everything after the first colon, dates use colons too
+        let Ok(segments) = parse("{date:%H:%M}") else {
+            panic!("template does not parse");
+        };
+        assert!(matches!(&segments[0], Segment::Token { spec: Some(spec), .. } if spec == "%H:%M"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (template, message, span) in [
+            ("a{stem", "Unclosed \"{\"", 1..6),
+            ("a}b", "Unmatched \"}\"", 1..2),
+            ("{st{em}", "Unexpected \"{\" inside a token", 3..4),
+            ("{ }", "Empty token name", 0..3),
+            ("{stem|shout}", "Unknown filter \"shout\"", 6..11),
+            ("{stem[1:2}", "Unclosed \"[\"", 5..9),
+            ("{stem[a:2]}", "Invalid range \"[a:2]\"", 5..10),
+            ("{stem[3]}", "Invalid range \"[3]\"", 5..8),
+            ("{stem[0:1]x}", "Unexpected \"x\" after the token name", 10..11),
+        ] {
+            assert_eq!(parse(template), Err(TemplateError::new(message, span)), "{}", template);
+        }
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(expand_template("{n:03}_{negative:03}_{ratio}"), Ok("007_-07_2.5".to_string()));
+        assert_eq!(expand_template("{date}_{date:%Y%m%d-%H%M}"), Ok("2024-05-17_20240517-1403".to_string()));
+        assert_eq!(expand_template("{stem:4}"), Ok("Holi".to_string()));
+        assert_eq!(expand_template("{n:x}"), Err("Invalid width \"x\"".to_string()));
+        assert_eq!(expand_template("{n:100000}"), Err("Invalid width \"100000\"".to_string()));
+        assert_eq!(expand_template("{stem:x}"), Err("Invalid length \"x\"".to_string()));
+        assert_eq!(expand_template("{date:%Q}"), Err("Invalid date format \"%Q\"".to_string()));
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(expand_template("{stem[0:7]}"), Ok("Holiday".to_string()));
+        assert_eq!(expand_template("{stem[-5:]}{ext}"), Ok("Photo.jpg".to_string()));
+        assert_eq!(expand_template("{stem[:-6]}"), Ok("Holiday".to_string()));
+        assert_eq!(expand_template("{stem[5:2]}"), Ok("".to_string()));
+        assert_eq!(expand_template("{stem[-9223372036854775808:99]}"), Ok("Holiday Photo".to_string()));
+        // Graphemes stay whole
+        assert_eq!(Slice { start: Some(0), end: Some(1) }.apply("e\u{301}te\u{301}"), "e\u{301}");
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(expand_template("{stem|upper}"), Ok("HOLIDAY PHOTO".to_string()));
+        assert_eq!(expand_template("{stem|slug}{ext}"), Ok("holiday-photo.jpg".to_string()));
+        assert_eq!(expand_template("{stem[7:]|trim|lower}"), Ok("photo".to_string()));
+        assert_eq!(slug("Café Olé!"), "cafe-ole");
+    }
+
+    #[test]
+    fn values_cannot_break_the_name() {
+        assert_eq!(expand_template("{title}"), Ok("AC_DC_Live".to_string()));
+        // Literal text is the user's own and is left alone
+        assert_eq!(expand_template("a/{n}"), Ok("a/7".to_string()));
+    }
+
+    #[test]
+    fn missing_values() {
+        assert_eq!(expand_template("{exif.model}"), Err("No value for {exif.model}".to_string()));
+    }
+}
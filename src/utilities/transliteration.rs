@@ -0,0 +1,855 @@
+use pinyin::ToPinyin;
+use std::fmt;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum CyrillicScheme {
+    Keep,
+    // GOST 7.79 System B, the ASCII form of ISO 9
+    #[default]
+    Iso9,
+    Bgn,
+    // ICAO Doc 9303, as printed in passports
+    Icao,
+}
+
+impl fmt::Display for CyrillicScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CyrillicScheme::Keep => write!(f, "Keep"),
+            CyrillicScheme::Iso9 => write!(f, "ISO 9 (System B)"),
+            CyrillicScheme::Bgn => write!(f, "BGN/PCGN"),
+            CyrillicScheme::Icao => write!(f, "Passport (ICAO)"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum GreekScheme {
+    Keep,
+    // Modern pronunciation, as used for Greek passports and road signs
+    #[default]
+    Elot743,
+    Classical,
+}
+
+impl fmt::Display for GreekScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GreekScheme::Keep => write!(f, "Keep"),
+            GreekScheme::Elot743 => write!(f, "ELOT 743"),
+            GreekScheme::Classical => write!(f, "Classical"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum PinyinStyle {
+    Keep,
+    #[default]
+    Plain,
+    ToneNumbers,
+    // Not ASCII, the tones are marked on the vowels
+    ToneMarks,
+}
+
+impl fmt::Display for PinyinStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinyinStyle::Keep => write!(f, "Keep"),
+            PinyinStyle::Plain => write!(f, "Pinyin"),
+            PinyinStyle::ToneNumbers => write!(f, "Pinyin with tone numbers"),
+            PinyinStyle::ToneMarks => write!(f, "Pinyin with tone marks"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum KanaScheme {
+    Keep,
+    #[default]
+    Hepburn,
+}
+
+impl fmt::Display for KanaScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KanaScheme::Keep => write!(f, "Keep"),
+            KanaScheme::Hepburn => write!(f, "Hepburn"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum HangulScheme {
+    Keep,
+    // Syllable by syllable, without the sound changes between them
+    #[default]
+    Revised,
+}
+
+impl fmt::Display for HangulScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HangulScheme::Keep => write!(f, "Keep"),
+            HangulScheme::Revised => write!(f, "Revised Romanization"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum LatinScheme {
+    Keep,
+    #[default]
+    StripMarks,
+    // Vietnamese keyboard encodings, which keep the tones: "Việt" becomes "Vieetj" or "Vie6t5"
+    Telex,
+    Vni,
+}
+
+impl fmt::Display for LatinScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatinScheme::Keep => write!(f, "Keep"),
+            LatinScheme::StripMarks => write!(f, "Remove accents"),
+            LatinScheme::Telex => write!(f, "Telex"),
+            LatinScheme::Vni => write!(f, "VNI"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Options<'a> {
+    pub cyrillic: CyrillicScheme,
+    pub greek: GreekScheme,
+    pub pinyin: PinyinStyle,
+    pub kana: KanaScheme,
+    pub hangul: HangulScheme,
+    pub latin: LatinScheme,
+    // Between Chinese syllables and the letters or digits next to them
+    pub syllable_separator: &'a str,
+    // Other scripts get a rough ASCII spelling before falling back to the replacement
+    pub approximate: bool,
+    pub replacement: &'a str,
+}
+
+// Lower case letters with their ISO 9 (System B), BGN/PCGN and ICAO spellings
+const CYRILLIC: &[(char, &str, &str, &str)] = &[
+    ('а', "a", "a", "a"),
+    ('б', "b", "b", "b"),
+    ('в', "v", "v", "v"),
+    ('г', "g", "g", "g"),
+    ('д', "d", "d", "d"),
+    ('е', "e", "e", "e"),
+    ('ё', "yo", "yo", "e"),
+    ('ж', "zh", "zh", "zh"),
+    ('з', "z", "z", "z"),
+    ('и', "i", "i", "i"),
+    ('й', "j", "y", "i"),
+    ('к', "k", "k", "k"),
+    ('л', "l", "l", "l"),
+    ('м', "m", "m", "m"),
+    ('н', "n", "n", "n"),
+    ('о', "o", "o", "o"),
+    ('п', "p", "p", "p"),
+    ('р', "r", "r", "r"),
+    ('с', "s", "s", "s"),
+    ('т', "t", "t", "t"),
+    ('у', "u", "u", "u"),
+    ('ф', "f", "f", "f"),
+    ('х', "x", "kh", "kh"),
+    ('ц', "cz", "ts", "ts"),
+    ('ч', "ch", "ch", "ch"),
+    ('ш', "sh", "sh", "sh"),
+    ('щ', "shh", "shch", "shch"),
+    // BGN marks the signs with quotes, which most file systems or shells object to
+    ('ъ', "``", "", "ie"),
+    ('ы', "y`", "y", "y"),
+    ('ь', "`", "", ""),
+    ('э', "e`", "e", "e"),
+    ('ю', "yu", "yu", "iu"),
+    ('я', "ya", "ya", "ia"),
+    ('і', "i", "i", "i"),
+    ('ї', "yi", "yi", "i"),
+    ('є', "ye", "ye", "ie"),
+    ('ґ', "g`", "g", "g"),
+    ('ў', "u`", "w", "u"),
+];
+
+const CYRILLIC_VOWELS: &str = "аеёиоуыэюяіїє";
+
+// Lower case letters with their ELOT 743 and classical spellings
+const GREEK: &[(char, &str, &str)] = &[
+    ('α', "a", "a"),
+    ('β', "v", "b"),
+    ('γ', "g", "g"),
+    ('δ', "d", "d"),
+    ('ε', "e", "e"),
+    ('ζ', "z", "z"),
+    ('η', "i", "e"),
+    ('θ', "th", "th"),
+    ('ι', "i", "i"),
+    ('κ', "k", "k"),
+    ('λ', "l", "l"),
+    ('μ', "m", "m"),
+    ('ν', "n", "n"),
+    ('ξ', "x", "x"),
+    ('ο', "o", "o"),
+    ('π', "p", "p"),
+    ('ρ', "r", "r"),
+    ('σ', "s", "s"),
+    ('ς', "s", "s"),
+    ('τ', "t", "t"),
+    ('υ', "y", "y"),
+    ('φ', "f", "ph"),
+    ('χ', "ch", "ch"),
+    ('ψ', "ps", "ps"),
+    ('ω', "o", "o"),
+];
+
+const GREEK_VOWELS: &str = "αεηιουω";
+// After these αυ, ευ and ηυ are pronounced av, ev and iv, otherwise af, ef and if
+const GREEK_VOICED: &str = "αεηιουωβγδζλμνρ";
+
+// Hiragana, katakana are mapped onto them first
+const KANA: &[(char, &str)] = &[
+    ('あ', "a"), ('い', "i"), ('う', "u"), ('え', "e"), ('お', "o"),
+    ('か', "ka"), ('き', "ki"), ('く', "ku"), ('け', "ke"), ('こ', "ko"),
+    ('さ', "sa"), ('し', "shi"), ('す', "su"), ('せ', "se"), ('そ', "so"),
+    ('た', "ta"), ('ち', "chi"), ('つ', "tsu"), ('て', "te"), ('と', "to"),
+    ('な', "na"), ('に', "ni"), ('ぬ', "nu"), ('ね', "ne"), ('の', "no"),
+    ('は', "ha"), ('ひ', "hi"), ('ふ', "fu"), ('へ', "he"), ('ほ', "ho"),
+    ('ま', "ma"), ('み', "mi"), ('む', "mu"), ('め', "me"), ('も', "mo"),
+    ('や', "ya"), ('ゆ', "yu"), ('よ', "yo"),
+    ('ら', "ra"), ('り', "ri"), ('る', "ru"), ('れ', "re"), ('ろ', "ro"),
+    ('わ', "wa"), ('ゐ', "i"), ('ゑ', "e"), ('を', "o"), ('ん', "n"),
+    ('が', "ga"), ('ぎ', "gi"), ('ぐ', "gu"), ('げ', "ge"), ('ご', "go"),
+    ('ざ', "za"), ('じ', "ji"), ('ず', "zu"), ('ぜ', "ze"), ('ぞ', "zo"),
+    ('だ', "da"), ('ぢ', "ji"), ('づ', "zu"), ('で', "de"), ('ど', "do"),
+    ('ば', "ba"), ('び', "bi"), ('ぶ', "bu"), ('べ', "be"), ('ぼ', "bo"),
+    ('ぱ', "pa"), ('ぴ', "pi"), ('ぷ', "pu"), ('ぺ', "pe"), ('ぽ', "po"),
+    ('ぁ', "a"), ('ぃ', "i"), ('ぅ', "u"), ('ぇ', "e"), ('ぉ', "o"),
+    ('ゃ', "ya"), ('ゅ', "yu"), ('ょ', "yo"), ('ゎ', "wa"), ('ゔ', "vu"),
+];
+
+const SMALL_Y: &str = "ゃゅょ";
+const SMALL_VOWELS: &str = "ぁぃぅぇぉ";
+const SOKUON: char = 'っ';
+const LONG_VOWEL: char = 'ー';
+
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p", "h",
+];
+const HANGUL_VOWELS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const HANGUL_FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+// Latin letters that do not decompose into a base letter and accents
+const LATIN_LETTERS: &[(char, &str)] = &[
+    ('ß', "ss"), ('æ', "ae"), ('Æ', "AE"), ('œ', "oe"), ('Œ', "OE"), ('ø', "o"), ('Ø', "O"),
+    ('ł', "l"), ('Ł', "L"), ('đ', "d"), ('Đ', "D"), ('ð', "d"), ('Ð', "D"), ('þ', "th"),
+    ('Þ', "Th"), ('ı', "i"), ('ħ', "h"), ('Ħ', "H"), ('ŋ', "ng"), ('Ŋ', "Ng"),
+];
+
+const CIRCUMFLEX: char = '\u{302}';
+const BREVE: char = '\u{306}';
+const HORN: char = '\u{31b}';
+const ACUTE: char = '\u{301}';
+const GRAVE: char = '\u{300}';
+const HOOK_ABOVE: char = '\u{309}';
+const TILDE: char = '\u{303}';
+const DOT_BELOW: char = '\u{323}';
+const DIAERESIS: char = '\u{308}';
+const ROUGH_BREATHING: char = '\u{314}';
+
+enum Script {
+    Ascii,
+    Cyrillic,
+    Greek,
+    Han,
+    Kana,
+    Hangul,
+    Latin,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c {
+        _ if c.is_ascii() => Script::Ascii,
+        '\u{400}'..='\u{4ff}' => Script::Cyrillic,
+        '\u{370}'..='\u{3ff}' | '\u{1f00}'..='\u{1fff}' => Script::Greek,
+        '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' => Script::Han,
+        '\u{3041}'..='\u{3096}' | '\u{30a1}'..='\u{30fa}' | LONG_VOWEL => Script::Kana,
+        '\u{ac00}'..='\u{d7a3}' => Script::Hangul,
+        '\u{c0}'..='\u{24f}' | '\u{1e00}'..='\u{1eff}' => Script::Latin,
+        _ => Script::Other,
+    }
+}
+
+// Base letter and accents of a precomposed letter
+fn decompose(c: char) -> (char, Vec<char>) {
+    let mut chars = std::iter::once(c).nfd();
+    let base = chars.next().unwrap_or(c);
+    (base, chars.collect())
+}
+
+// Follows the case of the original letter: "Ж" becomes "Zh", or "ZH" inside an upper case word
+fn cased(latin: &str, upper: bool, all_caps: bool) -> String {
+    if !upper {
+        return latin.to_string();
+    }
+    if all_caps {
+        return latin.to_uppercase();
+    }
+    let mut chars = latin.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+struct Transliterator<'a> {
+    options: Options<'a>,
+    chars: Vec<char>,
+    output: String,
+    // Telex and VNI put the tone of a Vietnamese word after it
+    pending_tone: Option<char>,
+    after_syllable: bool,
+}
+
+impl<'a> Transliterator<'a> {
+    fn is_upper_at(&self, index: usize) -> bool {
+        self.chars.get(index).is_some_and(|c| c.is_uppercase())
+    }
+
+    // An upper case letter next to another one is taken as part of an upper case word
+    fn all_caps_at(&self, index: usize) -> bool {
+        self.is_upper_at(index + 1) || (index > 0 && self.is_upper_at(index - 1))
+    }
+
+    fn push(&mut self, text: &str) {
+        if self.after_syllable && text.starts_with(|c: char| c.is_alphanumeric()) {
+            self.output.push_str(self.options.syllable_separator);
+        }
+        self.after_syllable = false;
+        self.output.push_str(text);
+    }
+
+    fn push_syllable(&mut self, syllable: &str) {
+        if self.output.ends_with(|c: char| c.is_alphanumeric()) {
+            self.output.push_str(self.options.syllable_separator);
+        }
+        self.output.push_str(syllable);
+        self.after_syllable = true;
+    }
+
+    fn flush_tone(&mut self) {
+        if let Some(tone) = self.pending_tone.take() {
+            self.output.push(tone);
+        }
+    }
+
+    fn fallback(&mut self, c: char) {
+        let approximation = deunicode::deunicode_char(c)
+            .map(str::trim)
+            .filter(|approximation| self.options.approximate && !approximation.is_empty());
+        match approximation {
+            Some(approximation) => self.push(approximation),
+            None => self.push(self.options.replacement),
+        }
+    }
+
+    fn run(mut self) -> String {
+        let mut index = 0;
+        while index < self.chars.len() {
+            let c = self.chars[index];
+            if !c.is_alphabetic() {
+                self.flush_tone();
+            }
+            index += match script(c) {
+                Script::Ascii => {
+                    self.push(&c.to_string());
+                    1
+                }
+                Script::Cyrillic => self.cyrillic(index),
+                Script::Greek => self.greek(index),
+                Script::Han => self.han(index),
+                Script::Kana => self.kana(index),
+                Script::Hangul => self.hangul(index),
+                Script::Latin => self.latin(index),
+                Script::Other => {
+                    self.fallback(c);
+                    1
+                }
+            };
+        }
+        self.flush_tone();
+        self.output
+    }
+
+    fn cyrillic_spelling(&self, c: char) -> Option<&'static str> {
+        let lower = c.to_lowercase().next()?;
+        let (_, iso9, bgn, icao) = CYRILLIC.iter().find(|(letter, ..)| *letter == lower)?;
+        Some(match self.options.cyrillic {
+            CyrillicScheme::Iso9 | CyrillicScheme::Keep => iso9,
+            CyrillicScheme::Bgn => bgn,
+            CyrillicScheme::Icao => icao,
+        })
+    }
+
+    fn cyrillic(&mut self, index: usize) -> usize {
+        let c = self.chars[index];
+        if self.options.cyrillic == CyrillicScheme::Keep {
+            self.push(&c.to_string());
+            return 1;
+        }
+        let Some(mut spelling) = self.cyrillic_spelling(c) else {
+            self.fallback(c);
+            return 1;
+        };
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let previous = index
+            .checked_sub(1)
+            .and_then(|previous| self.chars.get(previous))
+            .and_then(|previous| previous.to_lowercase().next());
+        match (self.options.cyrillic, lower) {
+            // ц is c before the letters spelled with i, e, y or j and cz elsewhere
+            (CyrillicScheme::Iso9, 'ц') => {
+                let next = self.chars.get(index + 1).and_then(|next| self.cyrillic_spelling(*next));
+                if next.is_some_and(|next| next.starts_with(['i', 'e', 'y', 'j'])) {
+                    spelling = "c";
+                }
+            }
+            // е is ye at the start of a word and after vowels and signs
+            (CyrillicScheme::Bgn, 'е') => {
+                let after = previous.map_or(true, |previous| {
+                    !previous.is_alphabetic() || CYRILLIC_VOWELS.contains(previous) || "йъь".contains(previous)
+                });
+                if after {
+                    spelling = "ye";
+                }
+            }
+            _ => {}
+        }
+        let spelling = cased(spelling, c.is_uppercase(), self.all_caps_at(index));
+        self.push(&spelling);
+        1
+    }
+
+    fn greek(&mut self, index: usize) -> usize {
+        let c = self.chars[index];
+        if self.options.greek == GreekScheme::Keep {
+            self.push(&c.to_string());
+            return 1;
+        }
+        let letter = |index: usize| {
+            self.chars.get(index).map(|c| {
+                let (base, marks) = decompose(*c);
+                (base.to_lowercase().next().unwrap_or(base), marks)
+            })
+        };
+        let (lower, marks) = letter(index).unwrap_or((c, Vec::new()));
+        let Some(&(_, modern, classical)) = GREEK.iter().find(|(letter, ..)| *letter == lower) else {
+            self.fallback(c);
+            return 1;
+        };
+        let classical_scheme = self.options.greek == GreekScheme::Classical;
+        let next = letter(index + 1).filter(|(_, marks)| !marks.contains(&DIAERESIS));
+        let at_word_start = index == 0 || !self.chars[index - 1].is_alphabetic();
+
+        let (mut spelling, consumed) = match (lower, next.as_ref().map(|(next, _)| *next)) {
+            ('α' | 'ε' | 'η', Some('υ')) => {
+                let vowel = if lower == 'η' && !classical_scheme { "i" } else { &classical[..1] };
+                let second = if classical_scheme {
+                    "u"
+                } else {
+                    let voiced = letter(index + 2).is_some_and(|(after, _)| GREEK_VOICED.contains(after));
+                    if voiced {
+                        "v"
+                    } else {
+                        "f"
+                    }
+                };
+                (format!("{}{}", vowel, second), 2)
+            }
+            ('ο', Some('υ')) => ("ou".to_string(), 2),
+            ('α' | 'ε' | 'ο' | 'υ', Some('ι')) if classical_scheme => (format!("{}i", classical), 2),
+            ('γ', Some('γ')) => ("ng".to_string(), 2),
+            ('γ', Some('κ')) if classical_scheme => ("nk".to_string(), 2),
+            ('γ', Some('κ')) => ("gk".to_string(), 2),
+            ('γ', Some('ξ')) => ("nx".to_string(), 2),
+            ('γ', Some('χ')) => ("nch".to_string(), 2),
+            ('μ', Some('π')) if at_word_start && !classical_scheme => ("b".to_string(), 2),
+            _ if classical_scheme => (classical.to_string(), 1),
+            _ => (modern.to_string(), 1),
+        };
+        // Classical spellings write the rough breathing, which a diphthong carries on its second vowel
+        if classical_scheme {
+            let rough = marks.contains(&ROUGH_BREATHING)
+                || (consumed == 2 && next.is_some_and(|(_, marks)| marks.contains(&ROUGH_BREATHING)));
+            if rough && lower == 'ρ' {
+                spelling = "rh".to_string();
+            } else if rough && GREEK_VOWELS.contains(lower) {
+                spelling = format!("h{}", spelling);
+            }
+        }
+        let spelling = cased(&spelling, decompose(c).0.is_uppercase(), self.all_caps_at(index));
+        self.push(&spelling);
+        consumed
+    }
+
+    fn han(&mut self, index: usize) -> usize {
+        let c = self.chars[index];
+        let syllable = c.to_pinyin().map(|pinyin| match self.options.pinyin {
+            PinyinStyle::Keep => "",
+            PinyinStyle::Plain => pinyin.plain(),
+            PinyinStyle::ToneNumbers => pinyin.with_tone_num_end(),
+            PinyinStyle::ToneMarks => pinyin.with_tone(),
+        });
+        match syllable {
+            _ if self.options.pinyin == PinyinStyle::Keep => self.push(&c.to_string()),
+            Some(syllable) => self.push_syllable(syllable),
+            None => self.fallback(c),
+        }
+        1
+    }
+
+    fn kana(&mut self, index: usize) -> usize {
+        // Katakana sit 0x60 above the matching hiragana
+        let hiragana = |c: char| match c {
+            '\u{30a1}'..='\u{30f6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            c => c,
+        };
+        let c = self.chars[index];
+        if self.options.kana == KanaScheme::Keep {
+            self.push(&c.to_string());
+            return 1;
+        }
+        let kana = hiragana(c);
+        let spelling_of = |kana: char| KANA.iter().find(|(letter, _)| *letter == kana).map(|(_, spelling)| *spelling);
+
+        if kana == LONG_VOWEL {
+            let vowel = self.output.chars().last().filter(|last| "aeiou".contains(*last));
+            self.push(&vowel.map(String::from).unwrap_or_default());
+            return 1;
+        }
+        // A small tsu doubles the consonant after it, ch becomes tch
+        if kana == SOKUON {
+            let next = self.chars.get(index + 1).map(|next| hiragana(*next)).and_then(spelling_of);
+            match next.and_then(|next| next.chars().next()) {
+                Some('c') => self.push("t"),
+                Some(consonant) if !"aeiou".contains(consonant) => self.push(&consonant.to_string()),
+                _ => {}
+            }
+            return 1;
+        }
+        let Some(spelling) = spelling_of(kana) else {
+            self.fallback(c);
+            return 1;
+        };
+        let next = self.chars.get(index + 1).map(|next| hiragana(*next));
+        match next {
+            // きゃ is kya, しゃ is sha
+            Some(small) if SMALL_Y.contains(small) && spelling.len() > 1 && spelling.ends_with('i') => {
+                let consonant = &spelling[..spelling.len() - 1];
+                let vowel = &spelling_of(small).unwrap_or_default()[1..];
+                let glide = if ["sh", "ch", "j"].contains(&consonant) { "" } else { "y" };
+                self.push(&format!("{}{}{}", consonant, glide, vowel));
+                2
+            }
+            // ファ is fa, ティ is ti, ウィ is wi
+            Some(small) if SMALL_VOWELS.contains(small) && spelling.ends_with(['i', 'u', 'e', 'o']) => {
+                let consonant = match &spelling[..spelling.len() - 1] {
+                    "" => "w",
+                    consonant => consonant,
+                };
+                self.push(&format!("{}{}", consonant, spelling_of(small).unwrap_or_default()));
+                2
+            }
+            _ => {
+                self.push(spelling);
+                1
+            }
+        }
+    }
+
+    fn hangul(&mut self, index: usize) -> usize {
+        let c = self.chars[index];
+        if self.options.hangul == HangulScheme::Keep {
+            self.push(&c.to_string());
+            return 1;
+        }
+        // Syllables are numbered by initial, vowel and final consonant
+        let syllable = c as usize - 0xac00;
+        let spelling = format!(
+            "{}{}{}",
+            HANGUL_INITIALS[syllable / 588],
+            HANGUL_VOWELS[syllable % 588 / 28],
+            HANGUL_FINALS[syllable % 28]
+        );
+        self.push(&spelling);
+        1
+    }
+
+    fn latin(&mut self, index: usize) -> usize {
+        let c = self.chars[index];
+        let scheme = self.options.latin;
+        if scheme == LatinScheme::Keep {
+            self.push(&c.to_string());
+            return 1;
+        }
+        let vietnamese = matches!(scheme, LatinScheme::Telex | LatinScheme::Vni);
+        if vietnamese && (c == 'đ' || c == 'Đ') {
+            let spelling = match (scheme, c) {
+                (LatinScheme::Telex, 'đ') => "dd",
+                (LatinScheme::Telex, _) => "DD",
+                (_, 'đ') => "d9",
+                _ => "D9",
+            };
+            self.push(spelling);
+            return 1;
+        }
+        if let Some((_, spelling)) = LATIN_LETTERS.iter().find(|(letter, _)| *letter == c) {
+            self.push(spelling);
+            return 1;
+        }
+        let (base, marks) = decompose(c);
+        if !base.is_ascii_alphabetic() {
+            self.fallback(c);
+            return 1;
+        }
+        let mut spelling = base.to_string();
+        if vietnamese {
+            let telex = scheme == LatinScheme::Telex;
+            let lower = base.to_ascii_lowercase();
+            for mark in &marks {
+                let modifier = match (*mark, lower) {
+                    (CIRCUMFLEX, 'a' | 'e' | 'o') if telex => Some(base),
+                    (CIRCUMFLEX, 'a' | 'e' | 'o') => Some('6'),
+                    (BREVE, 'a') => Some(if telex { 'w' } else { '8' }),
+                    (HORN, 'o' | 'u') => Some(if telex { 'w' } else { '7' }),
+                    _ => None,
+                };
+                spelling.extend(modifier);
+                let tone = match *mark {
+                    ACUTE => Some(if telex { 's' } else { '1' }),
+                    GRAVE => Some(if telex { 'f' } else { '2' }),
+                    HOOK_ABOVE => Some(if telex { 'r' } else { '3' }),
+                    TILDE => Some(if telex { 'x' } else { '4' }),
+                    DOT_BELOW => Some(if telex { 'j' } else { '5' }),
+                    _ => None,
+                };
+                if tone.is_some() {
+                    self.pending_tone = tone;
+                }
+            }
+        }
+        self.push(&spelling);
+        1
+    }
+}
+
+// Spells the name in ASCII, script by script as chosen in the options.
+pub fn transliterate(input: &str, options: Options<'_>) -> String {
+    // Names from macOS arrive decomposed, precomposed letters match the tables
+    let chars: Vec<char> = input.nfc().filter(|c| !is_combining_mark(*c)).collect();
+    Transliterator {
+        options,
+        chars,
+        output: String::new(),
+        pending_tone: None,
+        after_syllable: false,
+    }
+    .run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Options<'static> {
+        Options {
+            cyrillic: CyrillicScheme::Iso9,
+            greek: GreekScheme::Elot743,
+            pinyin: PinyinStyle::Plain,
+            kana: KanaScheme::Hepburn,
+            hangul: HangulScheme::Revised,
+            latin: LatinScheme::StripMarks,
+            syllable_separator: "",
+            approximate: false,
+            replacement: "_",
+        }
+    }
+
+    // Name with its ISO 9 (System B), BGN/PCGN and ICAO spellings
+    const CYRILLIC_NAMES: &[(&str, &str, &str, &str)] = &[
+        ("Щука", "Shhuka", "Shchuka", "Shchuka"),
+        ("Юлия", "Yuliya", "Yuliya", "Iuliia"),
+        ("Цой", "Czoj", "Tsoy", "Tsoi"),
+        ("Цирк", "Cirk", "Tsirk", "Tsirk"),
+        ("Объект", "Ob``ekt", "Obyekt", "Obieekt"),
+        ("Елена", "Elena", "Yelena", "Elena"),
+        ("Ёжик", "Yozhik", "Yozhik", "Ezhik"),
+        ("Мышь", "My`sh`", "Mysh", "Mysh"),
+        ("Эхо", "E`xo", "Ekho", "Ekho"),
+        ("Київ", "Kiyiv", "Kiyiv", "Kiiv"),
+    ];
+
+    // Name with its ELOT 743 and classical spellings
+    const GREEK_NAMES: &[(&str, &str, &str)] = &[
+        ("Αθήνα", "Athina", "Athena"),
+        ("Ευαγγελία", "Evangelia", "Euangelia"),
+        ("Ευχαριστώ", "Efcharisto", "Eucharisto"),
+        ("Ἑλλάς", "Ellas", "Hellas"),
+        ("Φίλιππος", "Filippos", "Philippos"),
+        ("Μπάμπης", "Bampis", "Mpampes"),
+        ("ΑΥΤΟΣ", "AFTOS", "AUTOS"),
+    ];
+
+    // Name with its plain, tone number and tone mark spellings
+    const PINYIN_NAMES: &[(&str, &str, &str, &str)] = &[
+        ("北京", "beijing", "bei3jing1", "běijīng"),
+        ("中文名", "zhongwenming", "zhong1wen2ming2", "zhōngwénmíng"),
+        ("上海2024", "shanghai2024", "shang4hai32024", "shànghǎi2024"),
+    ];
+
+    const KANA_NAMES: &[(&str, &str)] = &[
+        ("とうきょう", "toukyou"),
+        ("がっこう", "gakkou"),
+        ("きっちゃ", "kitcha"),
+        ("きゃく", "kyaku"),
+        ("しんぶん", "shinbun"),
+        ("ラーメン", "raamen"),
+        ("ファイル", "fairu"),
+    ];
+
+    const HANGUL_NAMES: &[(&str, &str)] = &[
+        ("한국", "hanguk"),
+        ("서울", "seoul"),
+        ("부산", "busan"),
+        ("안녕하세요", "annyeonghaseyo"),
+        ("값", "gap"),
+    ];
+
+    // Name without accents and in Telex and VNI
+    const LATIN_NAMES: &[(&str, &str, &str, &str)] = &[
+        ("Việt Nam", "Viet Nam", "Vieetj Nam", "Vie6t5 Nam"),
+        ("Đà Nẵng", "Da Nang", "DDaf Nawngx", "D9a2 Na8ng4"),
+        ("Thủy", "Thuy", "Thuyr", "Thuy3"),
+        ("Crème brûlée", "Creme brulee", "Cremef brulees", "Creme2 brulee1"),
+        ("Straße", "Strasse", "Strasse", "Strasse"),
+        ("Łódź", "Lodz", "Lodzs", "Lodz1"),
+        ("Ærø", "AEro", "AEro", "AEro"),
+    ];
+
+    #[test]
+    fn cyrillic() {
+        for (name, iso9, bgn, icao) in CYRILLIC_NAMES {
+            for (cyrillic, expected) in [
+                (CyrillicScheme::Iso9, iso9),
+                (CyrillicScheme::Bgn, bgn),
+                (CyrillicScheme::Icao, icao),
+                (CyrillicScheme::Keep, name),
+            ] {
+                let options = Options { cyrillic, ..options() };
+                assert_eq!(transliterate(name, options), *expected, "{} in {}", name, cyrillic);
+            }
+        }
+    }
+
+    #[test]
+    fn greek() {
+        for (name, elot, classical) in GREEK_NAMES {
+            for (greek, expected) in [
+                (GreekScheme::Elot743, elot),
+                (GreekScheme::Classical, classical),
+                (GreekScheme::Keep, name),
+            ] {
+                let options = Options { greek, ..options() };
+                assert_eq!(transliterate(name, options), *expected, "{} in {}", name, greek);
+            }
+        }
+    }
+
+    #[test]
+    fn pinyin() {
+        for (name, plain, tone_numbers, tone_marks) in PINYIN_NAMES {
+            for (pinyin, expected) in [
+                (PinyinStyle::Plain, plain),
+                (PinyinStyle::ToneNumbers, tone_numbers),
+                (PinyinStyle::ToneMarks, tone_marks),
+                (PinyinStyle::Keep, name),
+            ] {
+                let options = Options { pinyin, ..options() };
+                assert_eq!(transliterate(name, options), *expected, "{} in {}", name, pinyin);
+            }
+        }
+    }
+
+    #[test]
+    fn pinyin_separator() {
+        let options = Options {
+            pinyin: PinyinStyle::ToneNumbers,
+            syllable_separator: "_",
+            ..options()
+        };
+        assert_eq!(transliterate("北京", options), "bei3_jing1");
+        assert_eq!(transliterate("上海2024", options), "shang4_hai3_2024");
+        assert_eq!(transliterate("a北京.jpg", options), "a_bei3_jing1.jpg");
+    }
+
+    #[test]
+    fn kana_and_hangul() {
+        for (name, expected) in KANA_NAMES.iter().chain(HANGUL_NAMES) {
+            assert_eq!(transliterate(name, options()), *expected, "{}", name);
+        }
+        let options = Options {
+            kana: KanaScheme::Keep,
+            hangul: HangulScheme::Keep,
+            ..options()
+        };
+        assert_eq!(transliterate("とうきょう 한국", options), "とうきょう 한국");
+    }
+
+    #[test]
+    fn latin() {
+        for (name, stripped, telex, vni) in LATIN_NAMES {
+            for (latin, expected) in [
+                (LatinScheme::StripMarks, stripped),
+                (LatinScheme::Telex, telex),
+                (LatinScheme::Vni, vni),
+                (LatinScheme::Keep, name),
+            ] {
+                let options = Options { latin, ..options() };
+                assert_eq!(transliterate(name, options), *expected, "{} in {}", name, latin);
+            }
+        }
+    }
+
+    #[test]
+    fn decomposed_names() {
+        assert_eq!(transliterate("Cre\u{300}me", options()), "Creme");
+        assert_eq!(transliterate("Чаи\u{306}ка", options()), "Chajka");
+    }
+
+    #[test]
+    fn other_scripts() {
+        assert_eq!(transliterate("😀.jpg", options()), "_.jpg");
+        assert_eq!(transliterate("ภาษา", options()), "____");
+        let options = Options { approximate: true, ..options() };
+        assert_eq!(transliterate("😀.jpg", options), "grinning.jpg");
+        assert_eq!(transliterate("ภาษา", options), "phaasaa");
+    }
+}
@@ -0,0 +1,438 @@
+use crate::utilities::bmff;
+use crate::utilities::content_type::{ContentType, MKV, MOV, MP4, WEBM};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// `moov` grows with the length of the recording, hours of footage stay well below this
+const MOVIE_LIMIT: u64 = 64 * 1024 * 1024;
+// Matroska puts segment info and tracks before the first cluster
+const MATROSKA_HEADER_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoInfo {
+    pub created: Option<DateTime<Utc>>,
+    pub duration_secs: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub codec: Option<String>,
+}
+
+pub fn read(path: &Path, content_type: ContentType) -> Option<VideoInfo> {
+    let mut file = File::open(path).ok()?;
+    let info = if content_type == MP4 || content_type == MOV {
+        quicktime(&mut file)?
+    } else if content_type == MKV || content_type == WEBM {
+        let mut header = Vec::new();
+        file.take(MATROSKA_HEADER_LIMIT).read_to_end(&mut header).ok()?;
+        matroska(&header)?
+    } else {
+        return None;
+    };
+    // Audio-only MP4 files have a movie header too, without a video track they are not videos
+    Some(info).filter(|info| info.width.is_some())
+}
+
+fn quicktime(file: &mut File) -> Option<VideoInfo> {
+    let movie = bmff::read_top_level(file, b"moov", MOVIE_LIMIT)?;
+    let mut info = VideoInfo::default();
+
+    let header = bmff::find(&movie, b"mvhd")?;
+    let (created, timescale, duration) = match header.first()? {
+        1 => (bmff::uint(header, 4, 8)?, bmff::uint(header, 20, 4)?, bmff::uint(header, 24, 8)?),
+        _ => (bmff::uint(header, 4, 4)?, bmff::uint(header, 12, 4)?, bmff::uint(header, 16, 4)?),
+    };
+    // Seconds since 1904, zero when the recorder did not set a date
+    if created > 0 {
+        let epoch = Utc.with_ymd_and_hms(1904, 1, 1, 0, 0, 0).single()?;
+        info.created = i64::try_from(created)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|since_epoch| epoch.checked_add_signed(since_epoch));
+    }
+    if timescale > 0 {
+        info.duration_secs = Some(duration as f64 / timescale as f64);
+    }
+
+    for (box_type, track) in bmff::boxes(&movie) {
+        if box_type != b"trak" {
+            continue;
+        }
+        let Some(media) = bmff::find(track, b"mdia") else {
+            continue;
+        };
+        let handler = bmff::find(media, b"hdlr").and_then(|handler| handler.get(8..12));
+        if handler != Some(b"vide") {
+            continue;
+        }
+        // Width and height end the track header as 16.16 fixed point numbers
+        if let Some(track_header) = bmff::find(track, b"tkhd") {
+            // A header too short to hold them leaves the size unknown, the rest is still read
+            if let Some(size) = track_header.len().checked_sub(8) {
+                let width = bmff::uint(track_header, size, 2).map(|width| width as u32);
+                let height = bmff::uint(track_header, size + 4, 2).map(|height| height as u32);
+                (info.width, info.height) = if quarter_turn(track_header) {
+                    (height, width)
+                } else {
+                    (width, height)
+                };
+            }
+        }
+        // The first sample description is named after the codec
+        let descriptions = bmff::find(media, b"minf")
+            .and_then(|media_info| bmff::find(media_info, b"stbl"))
+            .and_then(|sample_table| bmff::find(sample_table, b"stsd"))
+            .and_then(|descriptions| descriptions.get(8..));
+        if let Some((codec, _)) = descriptions.and_then(|descriptions| bmff::boxes(descriptions).next()) {
+            info.codec = Some(codec_name(&String::from_utf8_lossy(codec)));
+        }
+        break;
+    }
+    Some(info)
+}
+
+// Phones record portrait video as landscape with a 90 or 270 degree rotation in the track's
+// matrix, which comes right before the width and height. Only its b and c entries are set then.
+fn quarter_turn(track_header: &[u8]) -> bool {
+    let Some(matrix) = track_header.len().checked_sub(8 + 36) else {
+        return false;
+    };
+    let entry = |index: usize| bmff::uint(track_header, matrix + index * 4, 4);
+    matches!(
+        (entry(0), entry(1), entry(3), entry(4)),
+        (Some(0), Some(b), Some(c), Some(0)) if b != 0 && c != 0
+    )
+}
+
+fn codec_name(codec: &str) -> String {
+    match codec {
+        "avc1" | "avc3" | "V_MPEG4/ISO/AVC" => "h264".to_string(),
+        "hvc1" | "hev1" | "V_MPEGH/ISO/HEVC" => "hevc".to_string(),
+        "av01" | "V_AV1" => "av1".to_string(),
+        "vp08" | "V_VP8" => "vp8".to_string(),
+        "vp09" | "V_VP9" => "vp9".to_string(),
+        "mp4v" | "V_MPEG4/ISO/ASP" => "mpeg4".to_string(),
+        "apch" | "apcn" | "apcs" | "apco" | "ap4h" | "V_PRORES" => "prores".to_string(),
+        codec => codec.trim_start_matches("V_").trim().to_lowercase(),
+    }
+}
+
+// EBML variable length integers, IDs keep their length marker and sizes drop it.
+fn ebml_vint(data: &[u8], offset: usize, keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.get(offset)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = if keep_marker {
+        u64::from(first)
+    } else {
+        u64::from(first) & (0xff >> len)
+    };
+    for byte in data.get(offset + 1..offset + len)? {
+        value = value << 8 | u64::from(*byte);
+    }
+    Some((value, len))
+}
+
+// Iterates the elements of a master element as (id, body) pairs. An unknown size, all ones,
+// runs to the end of the data.
+fn ebml_elements(data: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut elements = Vec::new();
+    let mut offset = 0;
+    while let Some((id, id_len)) = ebml_vint(data, offset, true) {
+        let Some((size, size_len)) = ebml_vint(data, offset + id_len, false) else {
+            break;
+        };
+        let start = offset + id_len + size_len;
+        let unknown = size == (1 << (7 * size_len)) - 1;
+        let end = match usize::try_from(size) {
+            Ok(size) if !unknown => (start + size).min(data.len()),
+            _ => data.len(),
+        };
+        let Some(body) = data.get(start..end) else {
+            break;
+        };
+        elements.push((id, body));
+        offset = end;
+    }
+    elements
+}
+
+fn ebml_uint(data: &[u8]) -> Option<u64> {
+    bmff::uint(data, 0, data.len())
+}
+
+fn ebml_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f64::from(f32::from_bits(ebml_uint(data)? as u32))),
+        8 => Some(f64::from_bits(ebml_uint(data)?)),
+        _ => None,
+    }
+}
+
+const SEGMENT: u64 = 0x18538067;
+const INFO: u64 = 0x1549a966;
+const TIMECODE_SCALE: u64 = 0x2ad7b1;
+const DURATION: u64 = 0x4489;
+const DATE_UTC: u64 = 0x4461;
+const TRACKS: u64 = 0x1654ae6b;
+const TRACK_ENTRY: u64 = 0xae;
+const TRACK_TYPE: u64 = 0x83;
+const CODEC_ID: u64 = 0x86;
+const VIDEO: u64 = 0xe0;
+const PIXEL_WIDTH: u64 = 0xb0;
+const PIXEL_HEIGHT: u64 = 0xba;
+const CLUSTER: u64 = 0x1f43b675;
+
+fn matroska(data: &[u8]) -> Option<VideoInfo> {
+    let (_, segment) = ebml_elements(data)
+        .into_iter()
+        .find(|(id, _)| *id == SEGMENT)?;
+    let mut info = VideoInfo::default();
+
+    for (id, body) in ebml_elements(segment) {
+        match id {
+            INFO => {
+                // Durations count ticks of the timecode scale, in nanoseconds
+                let mut timecode_scale = 1_000_000;
+                let mut duration = None;
+                for (id, body) in ebml_elements(body) {
+                    match id {
+                        TIMECODE_SCALE => {
+                            timecode_scale = ebml_uint(body).unwrap_or(timecode_scale)
+                        }
+                        DURATION => duration = ebml_float(body),
+                        // Nanoseconds since 2001
+                        DATE_UTC => {
+                            let epoch = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).single();
+                            info.created = epoch.zip(ebml_uint(body)).and_then(|(epoch, nanoseconds)| {
+                                epoch.checked_add_signed(Duration::nanoseconds(nanoseconds as i64))
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                info.duration_secs =
+                    duration.map(|duration| duration * timecode_scale as f64 / 1e9);
+            }
+            TRACKS => {
+                for (_, entry) in ebml_elements(body)
+                    .into_iter()
+                    .filter(|(id, _)| *id == TRACK_ENTRY)
+                {
+                    let elements = ebml_elements(entry);
+                    let is_video = elements
+                        .iter()
+                        .any(|(id, body)| *id == TRACK_TYPE && ebml_uint(body) == Some(1));
+                    if !is_video {
+                        continue;
+                    }
+                    for (id, body) in elements {
+                        match id {
+                            CODEC_ID => {
+                                let codec = String::from_utf8_lossy(body);
+                                info.codec = Some(codec_name(codec.trim_end_matches('\0')));
+                            }
+                            VIDEO => {
+                                for (id, body) in ebml_elements(body) {
+                                    match id {
+                                        PIXEL_WIDTH => info.width = ebml_uint(body).map(|width| width as u32),
+                                        PIXEL_HEIGHT => info.height = ebml_uint(body).map(|height| height as u32),
+                                        _ => {}
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    break;
+                }
+            }
+            CLUSTER => break,
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_file(name: &str, data: &[u8]) -> File {
+        let path = std::env::temp_dir().join(format!("bulkrename_video_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let file = File::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        file
+    }
+
+    fn bmff_box(box_type: &[u8], body: &[u8]) -> Vec<u8> {
+        [(8 + body.len() as u32).to_be_bytes().to_vec(), box_type.to_vec(), body.to_vec()].concat()
+    }
+
+    // A version 0 track header, the matrix entries are 16.16 fixed point
+    fn track_header(matrix: [i32; 9], width: u16, height: u16) -> Vec<u8> {
+        let mut header = vec![0; 40];
+        for entry in matrix {
+            header.extend_from_slice(&entry.to_be_bytes());
+        }
+        header.extend_from_slice(&[&width.to_be_bytes()[..], &[0, 0], &height.to_be_bytes(), &[0, 0]].concat());
+        header
+    }
+
+    const IDENTITY: [i32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x40000000];
+    const ROTATE_90: [i32; 9] = [0, 0x10000, 0, -0x10000, 0, 0, 0, 0, 0x40000000];
+    const ROTATE_270: [i32; 9] = [0, -0x10000, 0, 0x10000, 0, 0, 0, 0, 0x40000000];
+
+    fn movie(track_header: &[u8]) -> Vec<u8> {
+        // Created 2020-01-01, 90 seconds at a timescale of 1000
+        let mut movie_header = vec![0; 4];
+        movie_header.extend_from_slice(&3_660_681_600u32.to_be_bytes());
+        movie_header.extend_from_slice(&[0; 4]);
+        movie_header.extend_from_slice(&1000u32.to_be_bytes());
+        movie_header.extend_from_slice(&90_000u32.to_be_bytes());
+        movie_header.extend_from_slice(&[0; 80]);
+        movie_with_header(&movie_header, track_header)
+    }
+
+    fn movie_with_header(movie_header: &[u8], track_header: &[u8]) -> Vec<u8> {
+        let handler = [vec![0; 8], b"vide".to_vec(), vec![0; 13]].concat();
+        let descriptions = [vec![0; 8], bmff_box(b"hvc1", &[0; 8])].concat();
+        let sample_table = bmff_box(b"stbl", &bmff_box(b"stsd", &descriptions));
+        let media = bmff_box(b"mdia", &[bmff_box(b"hdlr", &handler), bmff_box(b"minf", &sample_table)].concat());
+        let track = bmff_box(b"trak", &[bmff_box(b"tkhd", track_header), media].concat());
+        let moov = bmff_box(b"moov", &[bmff_box(b"mvhd", movie_header), track].concat());
+        [bmff_box(b"ftyp", b"isom"), bmff_box(b"mdat", &[0; 16]), moov].concat()
+    }
+
+    fn read_movie(name: &str, track_header: &[u8]) -> Option<VideoInfo> {
+        quicktime(&mut temp_file(name, &movie(track_header)))
+    }
+
+    #[test]
+    fn quicktime_movie() {
+        let info = read_movie("landscape", &track_header(IDENTITY, 1920, 1080)).unwrap();
+        assert_eq!(info.created, Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).single());
+        assert_eq!(info.duration_secs, Some(90.0));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+        assert_eq!(info.codec.as_deref(), Some("hevc"));
+    }
+
+    #[test]
+    fn quicktime_version_1_header() {
+        for (created, expected) in [
+            (3_660_681_600u64, Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).single()),
+            // Too far out for a date, the rest of the header is still read
+            (u64::MAX / 2, None),
+            (u64::MAX, None),
+        ] {
+            let mut movie_header = vec![1, 0, 0, 0];
+            movie_header.extend_from_slice(&created.to_be_bytes());
+            movie_header.extend_from_slice(&[0; 8]);
+            movie_header.extend_from_slice(&1000u32.to_be_bytes());
+            movie_header.extend_from_slice(&90_000u64.to_be_bytes());
+            movie_header.extend_from_slice(&[0; 80]);
+            let data = movie_with_header(&movie_header, &track_header(IDENTITY, 1920, 1080));
+            let info = quicktime(&mut temp_file("version_1", &data)).unwrap();
+            assert_eq!(info.created, expected, "created {}", created);
+            assert_eq!(info.duration_secs, Some(90.0));
+        }
+    }
+
+    #[test]
+    fn quicktime_rotated_track() {
+        for matrix in [ROTATE_90, ROTATE_270] {
+            let info = read_movie("rotated", &track_header(matrix, 1920, 1080)).unwrap();
+            assert_eq!((info.width, info.height), (Some(1080), Some(1920)));
+        }
+    }
+
+    #[test]
+    fn quicktime_short_track_header() {
+        let info = read_movie("short_track_header", &[0; 4]).unwrap();
+        assert_eq!((info.width, info.height), (None, None));
+        assert_eq!(info.duration_secs, Some(90.0));
+        assert_eq!(info.codec.as_deref(), Some("hevc"));
+    }
+
+    #[test]
+    fn quicktime_truncated() {
+        let data = movie(&track_header(IDENTITY, 1920, 1080));
+        for length in 0..data.len() {
+            quicktime(&mut temp_file("truncated", &data[..length]));
+        }
+    }
+
+    #[test]
+    fn vint_lengths() {
+        assert_eq!(ebml_vint(&[0x81], 0, false), Some((1, 1)));
+        assert_eq!(ebml_vint(&[0x81], 0, true), Some((0x81, 1)));
+        assert_eq!(ebml_vint(&[0x40, 0x02], 0, false), Some((2, 2)));
+        assert_eq!(ebml_vint(&[0x1a, 0x45, 0xdf, 0xa3], 0, true), Some((0x1a45dfa3, 4)));
+        assert_eq!(ebml_vint(&[0x01, 0, 0, 0, 0, 0, 0, 0x05], 0, false), Some((5, 8)));
+        assert_eq!(ebml_vint(&[0, 0x81], 1, false), Some((1, 1)));
+    }
+
+    #[test]
+    fn vint_invalid_or_truncated() {
+        // No length marker in the first byte
+        assert_eq!(ebml_vint(&[0x00, 0x81], 0, false), None);
+        assert_eq!(ebml_vint(&[0x40], 0, false), None);
+        assert_eq!(ebml_vint(&[0x81], 1, false), None);
+        assert_eq!(ebml_vint(&[], 0, false), None);
+    }
+
+    #[test]
+    fn elements_in_order() {
+        let data = [0xb0, 0x82, 0x07, 0x80, 0xba, 0x81, 0x04];
+        assert_eq!(
+            ebml_elements(&data),
+            vec![(PIXEL_WIDTH, &[0x07, 0x80][..]), (PIXEL_HEIGHT, &[0x04][..])]
+        );
+    }
+
+    #[test]
+    fn elements_of_unknown_size() {
+        // All ones, in one byte and in eight
+        let data = [0xe0, 0xff, 0xb0, 0x81, 0x10];
+        assert_eq!(ebml_elements(&data), vec![(VIDEO, &data[2..])]);
+        let data = [&[0xe0, 0x01][..], &[0xff; 7], &[0xb0, 0x81, 0x10]].concat();
+        assert_eq!(ebml_elements(&data), vec![(VIDEO, &data[9..])]);
+    }
+
+    #[test]
+    fn elements_past_the_end() {
+        // A size past the end is cut at the end of the data
+        let data = [0xb0, 0x88, 0x07, 0x80];
+        assert_eq!(ebml_elements(&data), vec![(PIXEL_WIDTH, &data[2..])]);
+        let data = [&[0xb0, 0x01][..], &[0xfe; 7], &[0x07]].concat();
+        assert_eq!(ebml_elements(&data), vec![(PIXEL_WIDTH, &data[9..])]);
+        // The size itself is cut off
+        assert_eq!(ebml_elements(&[0xb0, 0x40]), vec![]);
+        assert_eq!(ebml_elements(&[0x00, 0x81, 0x01]), vec![]);
+    }
+
+    #[test]
+    fn matroska_video_track() {
+        let element = |id: &[u8], body: &[u8]| [id, &[0x80 | body.len() as u8], body].concat();
+        let video = [element(&[0xb0], &[0x07, 0x80]), element(&[0xba], &[0x04, 0x38])].concat();
+        let track = [element(&[0x83], &[1]), element(&[0x86], b"V_VP9"), element(&[0xe0], &video)].concat();
+        let tracks = element(&[0x16, 0x54, 0xae, 0x6b], &element(&[0xae], &track));
+        let info = element(&[0x15, 0x49, 0xa9, 0x66], &element(&[0x44, 0x89], &30_000f64.to_bits().to_be_bytes()));
+        let segment = [&[0x18, 0x53, 0x80, 0x67, 0x01][..], &[0xff; 7], &info, &tracks].concat();
+        let header = element(&[0x1a, 0x45, 0xdf, 0xa3], &[]);
+        let data = [header, segment].concat();
+
+        let parsed = matroska(&data).unwrap();
+        assert_eq!((parsed.width, parsed.height), (Some(1920), Some(1080)));
+        assert_eq!(parsed.codec.as_deref(), Some("vp9"));
+        assert_eq!(parsed.duration_secs, Some(30.0));
+        for length in 0..data.len() {
+            matroska(&data[..length]);
+        }
+    }
+}